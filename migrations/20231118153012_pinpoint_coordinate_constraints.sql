@@ -0,0 +1,26 @@
+-- Pinpoints without a usable location can never be shown on the map,
+-- but they are still their owners' data. Rather than dropping them, the
+-- migration stops until they have been corrected or removed by hand.
+DO $$
+DECLARE
+    invalid_count BIGINT;
+BEGIN
+    SELECT COUNT(*) INTO invalid_count
+    FROM pinpoints
+    WHERE latitude IS NULL OR longitude IS NULL
+    OR NOT (latitude BETWEEN -90 AND 90)
+    OR NOT (longitude BETWEEN -180 AND 180);
+    IF invalid_count > 0 THEN
+        RAISE EXCEPTION '% pinpoints have a missing or out of range location, correct them before constraining the columns', invalid_count
+            USING HINT = 'SELECT id, latitude, longitude FROM pinpoints WHERE NOT (latitude BETWEEN -90 AND 90) OR NOT (longitude BETWEEN -180 AND 180) OR latitude IS NULL OR longitude IS NULL';
+    END IF;
+END;
+$$;
+
+-- NaN sorts above every other value in Postgres,
+-- so the range checks also reject it.
+ALTER TABLE pinpoints
+    ALTER COLUMN latitude SET NOT NULL,
+    ALTER COLUMN longitude SET NOT NULL,
+    ADD CONSTRAINT pinpoints_latitude_range CHECK (latitude BETWEEN -90 AND 90),
+    ADD CONSTRAINT pinpoints_longitude_range CHECK (longitude BETWEEN -180 AND 180);
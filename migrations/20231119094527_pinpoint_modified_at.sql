@@ -0,0 +1,2 @@
+-- Set whenever a pinpoint or its contents are edited
+ALTER TABLE pinpoints ADD COLUMN modified_at timestamptz NULL;
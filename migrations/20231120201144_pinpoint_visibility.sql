@@ -0,0 +1,13 @@
+-- Who a pinpoint is shown to besides its owner
+ALTER TABLE pinpoints
+    ADD COLUMN visibility TEXT NOT NULL DEFAULT 'public',
+    ADD CONSTRAINT pinpoints_visibility_level
+        CHECK (visibility IN ('public', 'followers', 'private'));
+
+-- follower_id follows followee_id
+CREATE TABLE user_follows(
+    follower_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    followee_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (follower_id, followee_id),
+    added_at timestamptz NOT NULL DEFAULT clock_timestamp()
+);
@@ -0,0 +1,6 @@
+-- Ephemeral pinpoints are hidden once expires_at passes
+-- and later purged by the expiry worker
+ALTER TABLE pinpoints ADD COLUMN expires_at timestamptz NULL;
+
+CREATE INDEX pinpoints_expires_at_idx ON pinpoints (expires_at)
+WHERE expires_at IS NOT NULL;
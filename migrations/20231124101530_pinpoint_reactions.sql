@@ -0,0 +1,10 @@
+-- One reaction per user per pinpoint, from a fixed emoji set
+CREATE TABLE pinpoint_reactions(
+    pinpoint_id uuid NOT NULL REFERENCES pinpoints(id) ON DELETE CASCADE,
+    user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    PRIMARY KEY (pinpoint_id, user_id),
+    reaction TEXT NOT NULL,
+    added_at timestamptz NOT NULL DEFAULT clock_timestamp(),
+    CONSTRAINT pinpoint_reactions_reaction_kind
+        CHECK (reaction IN ('thumbs_up', 'heart', 'laugh', 'wow', 'sad', 'fire'))
+);
@@ -0,0 +1,17 @@
+-- Comments on pinpoints, with one level of replies.
+-- The comment body lives in contents, like a pinpoint's description.
+CREATE TABLE pinpoint_comments(
+    id uuid NOT NULL,
+    PRIMARY KEY (id),
+    pinpoint_id uuid NOT NULL REFERENCES pinpoints(id) ON DELETE CASCADE,
+    user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Set on replies, pointing at a top-level comment on the same pinpoint
+    parent_id uuid NULL REFERENCES pinpoint_comments(id) ON DELETE CASCADE,
+    content_id uuid NOT NULL REFERENCES contents(id) ON DELETE CASCADE,
+    added_at timestamptz NOT NULL DEFAULT clock_timestamp(),
+    modified_at timestamptz NULL
+);
+
+CREATE INDEX pinpoint_comments_listing_idx
+    ON pinpoint_comments (pinpoint_id, parent_id, added_at, id);
+CREATE INDEX pinpoint_comments_parent_id_idx ON pinpoint_comments (parent_id);
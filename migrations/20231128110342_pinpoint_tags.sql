@@ -0,0 +1,15 @@
+-- Normalized tag names, shared between pinpoints
+CREATE TABLE tags(
+    id uuid NOT NULL DEFAULT gen_random_uuid(),
+    PRIMARY KEY (id),
+    name TEXT NOT NULL UNIQUE,
+    added_at timestamptz NOT NULL DEFAULT clock_timestamp()
+);
+
+CREATE TABLE pinpoint_tags(
+    pinpoint_id uuid NOT NULL REFERENCES pinpoints(id) ON DELETE CASCADE,
+    tag_id uuid NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (pinpoint_id, tag_id)
+);
+
+CREATE INDEX pinpoint_tags_tag_id_idx ON pinpoint_tags (tag_id);
@@ -0,0 +1,6 @@
+-- Full-text search over descriptions, kept up to date by Postgres
+ALTER TABLE contents
+    ADD COLUMN description_tsv tsvector NOT NULL
+        GENERATED ALWAYS AS (to_tsvector('english', COALESCE(description, ''))) STORED;
+
+CREATE INDEX contents_description_tsv_idx ON contents USING GIN (description_tsv);
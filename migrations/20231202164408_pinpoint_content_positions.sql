@@ -0,0 +1,20 @@
+-- Orders the contents of a pinpoint. Position 0 holds the description
+-- and first attachment, later positions hold the extra attachments.
+ALTER TABLE pinpoint_contents ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+
+-- Pinpoints that already have several contents keep them in the order
+-- they were added, so the oldest stays the one at position 0.
+UPDATE pinpoint_contents pin_con
+SET position = ordered.position
+FROM (
+    SELECT pin_con.pinpoint_id, pin_con.content_id,
+        ROW_NUMBER() OVER (PARTITION BY pin_con.pinpoint_id
+            ORDER BY con.added_at, con.id) - 1 AS position
+    FROM pinpoint_contents pin_con
+    INNER JOIN contents con ON con.id = pin_con.content_id
+) ordered
+WHERE ordered.pinpoint_id = pin_con.pinpoint_id
+AND ordered.content_id = pin_con.content_id;
+
+ALTER TABLE pinpoint_contents
+    ADD CONSTRAINT pinpoint_contents_position_unique UNIQUE (pinpoint_id, position);
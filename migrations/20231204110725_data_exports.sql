@@ -0,0 +1,15 @@
+-- Personal data export archives, queued by the user and built by the export worker
+CREATE TABLE data_exports(
+    id uuid PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'pending',
+    requested_at timestamptz NOT NULL DEFAULT clock_timestamp(),
+    finished_at timestamptz,
+    -- The zip archive once the export is done
+    archive BYTEA,
+    CONSTRAINT data_exports_status_kind
+        CHECK (status IN ('pending', 'done', 'failed'))
+);
+
+CREATE INDEX data_exports_pending_idx ON data_exports (requested_at) WHERE status = 'pending';
+CREATE INDEX data_exports_user_id_idx ON data_exports (user_id);
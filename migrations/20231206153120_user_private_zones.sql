@@ -0,0 +1,41 @@
+-- Places such as home or work where a user's pinpoints are only shown
+-- to other users at the zone's centre ('mask') or not at all ('hide').
+CREATE TABLE user_private_zones(
+    id uuid NOT NULL,
+    PRIMARY KEY (id),
+    user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    latitude DOUBLE PRECISION NOT NULL,
+    longitude DOUBLE PRECISION NOT NULL,
+    radius_meters DOUBLE PRECISION NOT NULL,
+    mode TEXT NOT NULL,
+    added_at timestamptz NOT NULL DEFAULT clock_timestamp(),
+    CONSTRAINT user_private_zones_latitude_range CHECK (latitude BETWEEN -90 AND 90),
+    CONSTRAINT user_private_zones_longitude_range CHECK (longitude BETWEEN -180 AND 180),
+    CONSTRAINT user_private_zones_radius_range CHECK (radius_meters > 0 AND radius_meters <= 50000),
+    CONSTRAINT user_private_zones_mode_kind CHECK (mode IN ('mask', 'hide'))
+);
+
+CREATE INDEX user_private_zones_user_id_idx ON user_private_zones (user_id);
+
+-- The zone of the owner covering a location, if any.
+-- Hiding zones win over masking ones, then the widest zone wins.
+-- Distances are haversine distances in meters, as in the pinpoint queries.
+CREATE FUNCTION pinpoint_private_zone(
+    pin_latitude DOUBLE PRECISION,
+    pin_longitude DOUBLE PRECISION,
+    owner_id uuid
+)
+RETURNS TABLE (latitude DOUBLE PRECISION, longitude DOUBLE PRECISION, mode TEXT)
+LANGUAGE sql STABLE AS $$
+    SELECT zon.latitude, zon.longitude, zon.mode
+    FROM user_private_zones zon
+    WHERE zon.user_id = owner_id
+    AND 2.0 * 6371008.8 * ASIN(LEAST(1.0, SQRT(
+        POWER(SIN(RADIANS(pin_latitude - zon.latitude) / 2.0), 2)
+        + COS(RADIANS(zon.latitude)) * COS(RADIANS(pin_latitude))
+        * POWER(SIN(RADIANS(pin_longitude - zon.longitude) / 2.0), 2)
+    ))) <= zon.radius_meters
+    ORDER BY zon.mode = 'hide' DESC, zon.radius_meters DESC
+    LIMIT 1
+$$;
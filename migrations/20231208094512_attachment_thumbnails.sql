@@ -0,0 +1,4 @@
+-- Smaller variants of each attachment, made when it is stored.
+-- Attachments stored before have none, their full image is served instead.
+ALTER TABLE contents ADD COLUMN attachment_small BYTEA;
+ALTER TABLE contents ADD COLUMN attachment_medium BYTEA;
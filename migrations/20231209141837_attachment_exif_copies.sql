@@ -0,0 +1,3 @@
+-- The full size attachment with the EXIF of the upload kept, stored only when
+-- the owner asked for it and only ever sent back to them.
+ALTER TABLE contents ADD COLUMN attachment_with_exif BYTEA;
@@ -0,0 +1,40 @@
+-- Attachments are kept in blob storage, contents only says where.
+-- Each size has its key, the full image also its size in bytes, SHA-256 and MIME type.
+ALTER TABLE contents ADD COLUMN attachment_key TEXT;
+ALTER TABLE contents ADD COLUMN attachment_small_key TEXT;
+ALTER TABLE contents ADD COLUMN attachment_medium_key TEXT;
+ALTER TABLE contents ADD COLUMN attachment_with_exif_key TEXT;
+ALTER TABLE contents ADD COLUMN attachment_size BIGINT;
+ALTER TABLE contents ADD COLUMN attachment_sha256 TEXT;
+ALTER TABLE contents ADD COLUMN attachment_mime TEXT;
+
+-- Blobs no contents row refers to any more, deleted from storage by the expiry worker.
+-- Queued from a trigger so that every way of deleting or replacing contents is covered,
+-- and only once the change commits.
+CREATE TABLE blob_deletions (
+    storage_key TEXT PRIMARY KEY,
+    queued_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE FUNCTION queue_contents_blob_deletions() RETURNS TRIGGER AS $$
+DECLARE
+    kept TEXT[] := '{}';
+BEGIN
+    IF TG_OP = 'UPDATE' THEN
+        kept := ARRAY[NEW.attachment_key, NEW.attachment_small_key,
+            NEW.attachment_medium_key, NEW.attachment_with_exif_key];
+    END IF;
+    INSERT INTO blob_deletions (storage_key)
+    SELECT old_key
+    FROM UNNEST(ARRAY[OLD.attachment_key, OLD.attachment_small_key,
+        OLD.attachment_medium_key, OLD.attachment_with_exif_key]) AS old_key
+    WHERE old_key IS NOT NULL AND old_key <> ALL(array_remove(kept, NULL))
+    ON CONFLICT (storage_key) DO NOTHING;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER contents_blob_deletions
+AFTER DELETE OR UPDATE OF attachment_key, attachment_small_key, attachment_medium_key,
+    attachment_with_exif_key ON contents
+FOR EACH ROW EXECUTE FUNCTION queue_contents_blob_deletions();
@@ -0,0 +1,19 @@
+-- Attachments stored before blob storage have to be moved out first, with
+--     cargo run --bin move_attachments
+-- after the previous migration, then this one can run.
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM contents
+        WHERE attachment IS NOT NULL OR attachment_small IS NOT NULL
+            OR attachment_medium IS NOT NULL OR attachment_with_exif IS NOT NULL
+    ) THEN
+        RAISE EXCEPTION 'contents still holds attachments, move them to blob storage with the move_attachments binary first';
+    END IF;
+END;
+$$;
+
+ALTER TABLE contents DROP COLUMN attachment;
+ALTER TABLE contents DROP COLUMN attachment_small;
+ALTER TABLE contents DROP COLUMN attachment_medium;
+ALTER TABLE contents DROP COLUMN attachment_with_exif;
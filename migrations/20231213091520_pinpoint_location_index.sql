@@ -0,0 +1,3 @@
+-- Radius searches narrow pinpoints down to a bounding box before
+-- computing distances, the latitude range is served by this index.
+CREATE INDEX pinpoints_location_idx ON pinpoints (latitude, longitude);
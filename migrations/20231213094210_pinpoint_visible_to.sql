@@ -0,0 +1,18 @@
+-- Whether the viewer, by username, may see a pinpoint of the owner with
+-- the given visibility: public ones, their own, and followers-only ones
+-- of users they follow. Every pinpoint query goes through this.
+CREATE FUNCTION pinpoint_visible_to(
+    visibility TEXT,
+    owner_id uuid,
+    viewer TEXT
+)
+RETURNS BOOLEAN
+LANGUAGE sql STABLE AS $$
+    SELECT visibility = 'public' OR EXISTS (
+        SELECT 1 FROM users viewer_usr
+        WHERE viewer_usr.username = viewer
+        AND (viewer_usr.id = owner_id OR (visibility = 'followers' AND EXISTS (
+            SELECT 1 FROM user_follows fol
+            WHERE fol.follower_id = viewer_usr.id AND fol.followee_id = owner_id)))
+    )
+$$;
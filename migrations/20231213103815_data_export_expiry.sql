@@ -0,0 +1,10 @@
+-- Finished exports are purged by the expiry worker once expires_at passes.
+-- Those finished before now are kept for the default week.
+ALTER TABLE data_exports ADD COLUMN expires_at timestamptz NULL;
+
+UPDATE data_exports
+SET expires_at = finished_at + INTERVAL '7 days'
+WHERE finished_at IS NOT NULL;
+
+CREATE INDEX data_exports_expires_at_idx ON data_exports (expires_at)
+WHERE expires_at IS NOT NULL;
@@ -0,0 +1,28 @@
+-- The centre of the grid cell holding a location, as LocationPrecision::obscure
+-- computes it, so queries can filter, sort and group other users' pinpoints
+-- by where those users are shown them rather than where they are.
+-- Zero precision leaves the location exact.
+CREATE FUNCTION obscured_location(
+    pin_latitude DOUBLE PRECISION,
+    pin_longitude DOUBLE PRECISION,
+    precision_meters DOUBLE PRECISION
+)
+RETURNS TABLE (latitude DOUBLE PRECISION, longitude DOUBLE PRECISION)
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT
+        CASE WHEN precision_meters = 0 THEN pin_latitude ELSE cel.latitude END,
+        CASE WHEN precision_meters = 0 THEN pin_longitude
+        ELSE LEAST(180.0, GREATEST(-180.0,
+            (FLOOR((pin_longitude + 180.0) / cel.longitude_step) + 0.5) * cel.longitude_step - 180.0))
+        END
+    FROM (
+        SELECT lat.latitude,
+            -- Cells keep about the same width in meters away from the equator
+            LEAST(360.0, stp.latitude_step / GREATEST(COS(RADIANS(lat.latitude)), 1e-6)) AS longitude_step
+        FROM (SELECT NULLIF(precision_meters, 0) / (6371008.8 * PI() / 180.0) AS latitude_step) stp
+        CROSS JOIN LATERAL (
+            SELECT LEAST(90.0, GREATEST(-90.0,
+                (FLOOR(pin_latitude / stp.latitude_step) + 0.5) * stp.latitude_step)) AS latitude
+        ) lat
+    ) cel
+$$;
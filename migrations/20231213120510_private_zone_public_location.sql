@@ -0,0 +1,73 @@
+-- Masked pinpoints used to be shown at the centre of their zone, which is
+-- usually the very place the zone hides. They are now shown at a point
+-- between half the radius and the radius away from it instead, so other
+-- users only learn the centre to within the zone's radius.
+-- The point follows from the owner and the zone alone, so deleting and
+-- adding the same zone again does not reveal a second one to average.
+CREATE FUNCTION private_zone_public_location(
+    zone_latitude DOUBLE PRECISION,
+    zone_longitude DOUBLE PRECISION,
+    radius_meters DOUBLE PRECISION,
+    owner_id uuid
+)
+RETURNS TABLE (latitude DOUBLE PRECISION, longitude DOUBLE PRECISION)
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT
+        LEAST(90.0, GREATEST(-90.0, zone_latitude + DEGREES(ofs.north / 6371008.8))),
+        -- Wrapped back into [-180, 180) when it crosses the antimeridian
+        lon.longitude + 180.0 - 360.0 * FLOOR((lon.longitude + 180.0) / 360.0) - 180.0
+    FROM (
+        SELECT dst.meters * COS(dst.bearing) AS north, dst.meters * SIN(dst.bearing) AS east
+        FROM (
+            SELECT radius_meters * SQRT(0.25 + 0.75 * rnd.first) AS meters,
+                2.0 * PI() * rnd.second AS bearing
+            FROM (
+                SELECT ('x' || SUBSTR(hsh.digest, 1, 8))::BIT(32)::BIGINT / 4294967296.0 AS first,
+                    ('x' || SUBSTR(hsh.digest, 9, 8))::BIT(32)::BIGINT / 4294967296.0 AS second
+                FROM (
+                    SELECT MD5(owner_id::TEXT || ':' || zone_latitude::TEXT || ':'
+                        || zone_longitude::TEXT || ':' || radius_meters::TEXT) AS digest
+                ) hsh
+            ) rnd
+        ) dst
+    ) ofs
+    CROSS JOIN LATERAL (
+        SELECT zone_longitude + DEGREES(ofs.east
+            / (6371008.8 * GREATEST(COS(RADIANS(zone_latitude)), 1e-6))) AS longitude
+    ) lon
+$$;
+
+ALTER TABLE user_private_zones
+    ADD COLUMN public_latitude DOUBLE PRECISION NULL,
+    ADD COLUMN public_longitude DOUBLE PRECISION NULL;
+
+UPDATE user_private_zones zon
+SET public_latitude = pub.latitude, public_longitude = pub.longitude
+FROM user_private_zones src
+CROSS JOIN LATERAL private_zone_public_location(
+    src.latitude, src.longitude, src.radius_meters, src.user_id) pub
+WHERE src.id = zon.id;
+
+ALTER TABLE user_private_zones
+    ALTER COLUMN public_latitude SET NOT NULL,
+    ALTER COLUMN public_longitude SET NOT NULL;
+
+-- As before, except that masked pinpoints are placed at the public location
+CREATE OR REPLACE FUNCTION pinpoint_private_zone(
+    pin_latitude DOUBLE PRECISION,
+    pin_longitude DOUBLE PRECISION,
+    owner_id uuid
+)
+RETURNS TABLE (latitude DOUBLE PRECISION, longitude DOUBLE PRECISION, mode TEXT)
+LANGUAGE sql STABLE AS $$
+    SELECT zon.public_latitude, zon.public_longitude, zon.mode
+    FROM user_private_zones zon
+    WHERE zon.user_id = owner_id
+    AND 2.0 * 6371008.8 * ASIN(LEAST(1.0, SQRT(
+        POWER(SIN(RADIANS(pin_latitude - zon.latitude) / 2.0), 2)
+        + COS(RADIANS(zon.latitude)) * COS(RADIANS(pin_latitude))
+        * POWER(SIN(RADIANS(pin_longitude - zon.longitude) / 2.0), 2)
+    ))) <= zon.radius_meters
+    ORDER BY zon.mode = 'hide' DESC, zon.radius_meters DESC
+    LIMIT 1
+$$;
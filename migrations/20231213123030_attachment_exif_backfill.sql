@@ -0,0 +1,12 @@
+-- Attachments stored before uploads were normalized may still carry the
+-- EXIF of the upload, location included. The EXIF backfill checks each of
+-- them once and strips it where there is any. Attachments stored from now on
+-- are normalized already, so they start out checked.
+ALTER TABLE contents ADD COLUMN attachment_exif_checked BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE contents SET attachment_exif_checked = TRUE WHERE attachment_key IS NULL;
+
+ALTER TABLE contents ALTER COLUMN attachment_exif_checked SET DEFAULT TRUE;
+
+CREATE INDEX contents_exif_unchecked_idx ON contents (id)
+WHERE NOT attachment_exif_checked;
@@ -0,0 +1,28 @@
+-- Export archives are kept in blob storage like attachments, data_exports only says where.
+ALTER TABLE data_exports ADD COLUMN archive_key TEXT;
+
+-- Archives built before are not moved, the export worker builds them again
+-- into blob storage.
+UPDATE data_exports
+SET status = 'pending', finished_at = NULL, expires_at = NULL
+WHERE archive IS NOT NULL;
+
+ALTER TABLE data_exports DROP COLUMN archive;
+
+-- Queues the archive of a deleted export, expired or gone with its user,
+-- for the expiry worker the same way as contents blobs.
+CREATE FUNCTION queue_data_export_blob_deletions() RETURNS TRIGGER AS $$
+BEGIN
+    IF OLD.archive_key IS NOT NULL
+        AND (TG_OP = 'DELETE' OR NEW.archive_key IS DISTINCT FROM OLD.archive_key) THEN
+        INSERT INTO blob_deletions (storage_key)
+        VALUES (OLD.archive_key)
+        ON CONFLICT (storage_key) DO NOTHING;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER data_exports_blob_deletions
+AFTER DELETE OR UPDATE OF archive_key ON data_exports
+FOR EACH ROW EXECUTE FUNCTION queue_data_export_blob_deletions();
@@ -58,6 +58,33 @@ done
 
 export DATABASE_URL=postgres://${DB_USER}:${DB_PASSWORD}@${DB_HOST}:${DB_PORT}/${DB_NAME}
 sqlx database create
+
+# Databases from before blob storage still hold their attachments in contents,
+# and the drop_attachment_bytea migration refuses to run until they are moved out.
+# Migrate up to attachment_blob_storage, move them, then run the rest.
+BLOB_STORAGE_MIGRATION=20231211103045
+HAS_BYTEA_ATTACHMENTS=$(PGPASSWORD="${DB_PASSWORD}" psql -h "${DB_HOST}" -U "${DB_USER}" -p "${DB_PORT}" -d "${DB_NAME}" -tAc \
+  "SELECT 1 FROM information_schema.columns WHERE table_name = 'contents' AND column_name = 'attachment'")
+if [[ -n "${HAS_BYTEA_ATTACHMENTS}" ]]
+then
+  STAGED_MIGRATIONS=$(mktemp -d)
+  trap 'rm -rf "${STAGED_MIGRATIONS}"' EXIT
+  for migration in migrations/*.sql; do
+    version=$(basename "${migration}" | cut -d_ -f1)
+    if [[ "${version}" -le "${BLOB_STORAGE_MIGRATION}" ]]; then
+      cp "${migration}" "${STAGED_MIGRATIONS}/"
+    fi
+  done
+  sqlx migrate run --source "${STAGED_MIGRATIONS}"
+  >&2 echo "Moving the attachments stored in the database to blob storage"
+  # Checked against sqlx-data.json, the half migrated database does not match the queries
+  SQLX_OFFLINE=true \
+  APP_DATABASE__HOST="${DB_HOST}" APP_DATABASE__PORT="${DB_PORT}" \
+  APP_DATABASE__USERNAME="${DB_USER}" APP_DATABASE__PASSWORD="${DB_PASSWORD}" \
+  APP_DATABASE__DATABASE_NAME="${DB_NAME}" \
+    cargo run --bin move_attachments
+fi
+
 sqlx migrate run
 
 >&2 echo "Postgres has been migrated, ready to go!"
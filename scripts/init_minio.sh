@@ -0,0 +1,30 @@
+#!/usr/bin/env bash
+set -x
+set -eo pipefail
+
+MINIO_USER="${MINIO_USER:=minioadmin}"
+MINIO_PASSWORD="${MINIO_PASSWORD:=minioadmin}"
+MINIO_PORT="${MINIO_PORT:=9000}"
+MINIO_BUCKET="${MINIO_BUCKET:=gvserver}"
+
+# if a minio container is running, print instructions to kill it and exit
+RUNNING_CONTAINER=$(docker ps --filter 'name=minio' --format '{{.ID}}')
+if [[ -n $RUNNING_CONTAINER ]]; then
+  echo >&2 "there is a minio container already running, kill it with"
+  echo >&2 "    docker kill ${RUNNING_CONTAINER}"
+  exit 1
+fi
+
+# Launch MinIO using Docker, the bucket is a directory of its data volume
+CONTAINER_NAME="minio_$(date '+%s')"
+docker run \
+    -p "${MINIO_PORT}:9000" \
+    -e MINIO_ROOT_USER="${MINIO_USER}" \
+    -e MINIO_ROOT_PASSWORD="${MINIO_PASSWORD}" \
+    -d \
+    --name "${CONTAINER_NAME}" \
+    --entrypoint sh \
+    minio/minio \
+    -c "mkdir -p /data/${MINIO_BUCKET} && minio server /data"
+
+>&2 echo "MinIO is ready to go! Use it with APP_BLOB_STORAGE__BACKEND=s3"
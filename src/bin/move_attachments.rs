@@ -0,0 +1,23 @@
+use gvserver::blob_storage::move_bytea_attachments;
+use gvserver::configuration::get_configuration;
+use gvserver::startup::{get_blob_storage, get_connection_pool};
+use gvserver::telemetry::{get_subscriber, init_subscriber};
+
+// How many contents rows are moved per transaction
+const BATCH_SIZE: i64 = 100;
+
+// Moves attachments still stored in the database to the configured blob storage.
+// Run it between the attachment_blob_storage and drop_attachment_bytea migrations.
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let subscriber = get_subscriber("move_attachments".into(), "info".into(), std::io::stdout);
+    init_subscriber(subscriber);
+
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    let pool = get_connection_pool(&configuration.database);
+    let storage = get_blob_storage(&configuration.blob_storage)?;
+    let moved = move_bytea_attachments(&pool, storage.as_ref(), BATCH_SIZE).await?;
+    tracing::info!("Moved the attachments of {} contents rows, none are left in the database", moved);
+
+    Ok(())
+}
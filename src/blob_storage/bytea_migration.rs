@@ -0,0 +1,113 @@
+use anyhow::anyhow;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, Row, Transaction};
+use uuid::Uuid;
+use crate::blob_storage::{discard_blobs, store_attachment, BlobStorage};
+use crate::domain::normalize_legacy_image_blocking;
+
+// Moves the attachments databases stored before blob storage, still in the
+// bytea columns of contents, into the storage a batch of rows at a time.
+// Blank attachments are only cleared. Those uploaded before normalization
+// may still carry EXIF, they are normalized on the way with new thumbnails.
+// Returns how many rows were moved.
+// The migration dropping those columns refuses to run until this is done,
+// so the queries are checked at runtime rather than against the schema.
+#[tracing::instrument(name = "move_bytea_attachments", skip(pool, storage))]
+pub async fn move_bytea_attachments(
+    pool: &PgPool,
+    storage: &dyn BlobStorage,
+    batch_size: i64,
+) -> Result<u64, anyhow::Error> {
+    let mut moved = 0;
+    loop {
+        let mut tran = pool.begin().await?;
+        let rows = sqlx::query(
+            r#"
+            SELECT id, attachment, attachment_small, attachment_medium, attachment_with_exif
+            FROM contents
+            WHERE attachment IS NOT NULL OR attachment_small IS NOT NULL
+                OR attachment_medium IS NOT NULL OR attachment_with_exif IS NOT NULL
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED;
+            "#
+        )
+            .bind(batch_size)
+            .fetch_all(&mut tran)
+            .await?;
+        if rows.is_empty() {
+            return Ok(moved);
+        }
+        let mut stored_keys: Vec<String> = Vec::new();
+        match move_rows(&mut tran, storage, rows, &mut stored_keys).await {
+            Ok(x) => moved += x,
+            Err(e) => {
+                discard_blobs(storage, &stored_keys.iter().map(String::as_str).collect::<Vec<&str>>()).await;
+                return Err(e);
+            }
+        }
+        // A failed commit may still have gone through, so its blobs are kept
+        tran.commit().await?;
+        tracing::info!("Moved the attachments of {} contents rows to blob storage", moved);
+    }
+}
+
+// The keys of every blob stored go to stored_keys, even when a later row fails
+async fn move_rows(
+    tran: &mut Transaction<'_, Postgres>,
+    storage: &dyn BlobStorage,
+    rows: Vec<PgRow>,
+    stored_keys: &mut Vec<String>,
+) -> Result<u64, anyhow::Error> {
+    let mut moved = 0;
+    for row in rows {
+        let id: Uuid = row.try_get("id")?;
+        let attachment: Option<Vec<u8>> = row.try_get("attachment")?;
+        let stored = match attachment.filter(|x| !x.is_empty()) {
+            None => None,
+            Some(x) => {
+                let normalized = match normalize_legacy_image_blocking(x.clone()).await
+                    .map_err(|_| anyhow!("Failed to run the image normalization."))? {
+                    Ok(y) => y,
+                    // Left for the EXIF backfill, which logs it again until it is dealt with
+                    Err(e) => {
+                        tracing::error!("Failed to strip the EXIF of the attachment of contents {}: {}", id, e);
+                        None
+                    }
+                };
+                Some(match normalized {
+                    Some((full, variants)) => store_attachment(
+                        storage, full, Some(variants.small), Some(variants.medium),
+                        row.try_get("attachment_with_exif")?).await?,
+                    None => store_attachment(
+                        storage, x,
+                        row.try_get("attachment_small")?,
+                        row.try_get("attachment_medium")?,
+                        row.try_get("attachment_with_exif")?).await?
+                })
+            }
+        };
+        stored_keys.extend(stored.iter().flat_map(|x| x.keys()).map(String::from));
+        sqlx::query(
+            r#"
+            UPDATE contents
+            SET attachment_key = $2, attachment_small_key = $3, attachment_medium_key = $4,
+                attachment_with_exif_key = $5, attachment_size = $6, attachment_sha256 = $7,
+                attachment_mime = $8, attachment = NULL, attachment_small = NULL,
+                attachment_medium = NULL, attachment_with_exif = NULL
+            WHERE id = $1;
+            "#
+        )
+            .bind(id)
+            .bind(stored.as_ref().map(|x| x.full.key.clone()))
+            .bind(stored.as_ref().and_then(|x| x.small_key.clone()))
+            .bind(stored.as_ref().and_then(|x| x.medium_key.clone()))
+            .bind(stored.as_ref().and_then(|x| x.with_exif_key.clone()))
+            .bind(stored.as_ref().map(|x| x.full.size))
+            .bind(stored.as_ref().map(|x| x.full.sha256.clone()))
+            .bind(stored.as_ref().map(|x| x.full.mime_type.clone()))
+            .execute(&mut *tran)
+            .await?;
+        moved += 1;
+    }
+    Ok(moved)
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+use anyhow::anyhow;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::blob_storage::{discard_blobs, store_attachment, BlobStorage, StoredAttachment};
+use crate::domain::normalize_legacy_image_blocking;
+
+// How many contents rows are checked per transaction
+const EXIF_BACKFILL_BATCH: i64 = 50;
+
+// Checks the attachments stored before uploads were normalized once,
+// in the background after the application starts.
+pub async fn run_exif_backfill(pool: PgPool, storage: Arc<dyn BlobStorage>) {
+    match strip_legacy_exif(&pool, storage.as_ref(), EXIF_BACKFILL_BATCH).await {
+        Ok(0) => {}
+        Ok(checked) => tracing::info!("Checked {} legacy attachments for EXIF", checked),
+        Err(e) => tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to strip the EXIF of legacy attachments"
+        ),
+    }
+}
+
+// Replaces every unchecked attachment carrying EXIF with a normalized one,
+// thumbnails included, and marks it checked. The old blobs are queued for
+// deletion by the contents trigger. An attachment that cannot be decoded
+// is logged and left unchecked for the next run.
+// Returns how many rows were checked.
+#[tracing::instrument(name = "strip_legacy_exif", skip(pool, storage))]
+pub async fn strip_legacy_exif(
+    pool: &PgPool,
+    storage: &dyn BlobStorage,
+    batch_size: i64,
+) -> Result<u64, anyhow::Error> {
+    let mut checked = 0;
+    // Rows left unchecked are passed over rather than tried again and again
+    let mut after = Uuid::nil();
+    loop {
+        let mut tran = pool.begin().await?;
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, attachment_key
+            FROM contents
+            WHERE NOT attachment_exif_checked AND id > $1
+            ORDER BY id
+            LIMIT $2
+            FOR UPDATE SKIP LOCKED;
+            "#,
+            after,
+            batch_size
+        )
+            .fetch_all(&mut tran)
+            .await?;
+        let last = match rows.last() {
+            Some(x) => x.id,
+            None => return Ok(checked)
+        };
+        let mut stored: Vec<StoredAttachment> = Vec::new();
+        for row in rows {
+            match check_row(&mut tran, storage, row.id, row.attachment_key, &mut stored).await {
+                Ok(true) => checked += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    discard_blobs(storage, &stored.iter().flat_map(StoredAttachment::keys)
+                        .collect::<Vec<&str>>()).await;
+                    return Err(e);
+                }
+            }
+        }
+        // A failed commit may still have gone through, so its blobs are kept
+        tran.commit().await?;
+        after = last;
+    }
+}
+
+// False when the attachment could not be decoded and stays unchecked
+async fn check_row(
+    tran: &mut Transaction<'_, Postgres>,
+    storage: &dyn BlobStorage,
+    id: Uuid,
+    attachment_key: Option<String>,
+    stored: &mut Vec<StoredAttachment>,
+) -> Result<bool, anyhow::Error> {
+    let normalized = match attachment_key {
+        None => None,
+        Some(key) => {
+            let bytes = storage.get(&key).await?;
+            match normalize_legacy_image_blocking(bytes).await
+                .map_err(|_| anyhow!("Failed to run the image normalization."))? {
+                Ok(x) => x,
+                Err(e) => {
+                    tracing::error!("Failed to strip the EXIF of attachment {}: {}", key, e);
+                    return Ok(false);
+                }
+            }
+        }
+    };
+    let attachment = match normalized {
+        None => None,
+        Some((full, variants)) => Some(store_attachment(
+            storage, full, Some(variants.small), Some(variants.medium), None).await?)
+    };
+    match &attachment {
+        None => sqlx::query!(
+            r#"
+            UPDATE contents SET attachment_exif_checked = TRUE WHERE id = $1;
+            "#,
+            id
+        )
+            .execute(&mut *tran)
+            .await?,
+        Some(x) => sqlx::query!(
+            r#"
+            UPDATE contents
+            SET attachment_key = $2, attachment_small_key = $3, attachment_medium_key = $4,
+                attachment_size = $5, attachment_sha256 = $6, attachment_mime = $7,
+                attachment_exif_checked = TRUE
+            WHERE id = $1;
+            "#,
+            id,
+            x.full.key,
+            x.small_key,
+            x.medium_key,
+            x.full.size,
+            x.full.sha256,
+            x.full.mime_type
+        )
+            .execute(&mut *tran)
+            .await?
+    };
+    stored.extend(attachment);
+    Ok(true)
+}
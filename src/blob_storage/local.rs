@@ -0,0 +1,92 @@
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use uuid::Uuid;
+use crate::blob_storage::BlobStorage;
+
+// Keeps every blob as a file under root, at the path of its key
+pub struct LocalBlobStorage {
+    root: PathBuf,
+}
+
+impl LocalBlobStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    // Keys are relative paths that stay inside root
+    fn path_of(&self, key: &str) -> Result<PathBuf, anyhow::Error> {
+        let path = Path::new(key);
+        if key.is_empty() || !path.components().all(|x| matches!(x, Component::Normal(_))) {
+            return Err(anyhow!("{:?} is not a valid blob key.", key));
+        }
+        Ok(self.root.join(path))
+    }
+}
+
+#[async_trait]
+impl BlobStorage for LocalBlobStorage {
+    // Written next to its final path first, so readers never see half a blob
+    async fn put(&self, key: &str, bytes: Vec<u8>, _mime_type: &str) -> Result<(), anyhow::Error> {
+        let path = self.path_of(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await
+                .with_context(|| format!("Failed to create the directory of blob {}", key))?;
+        }
+        let partial = path.with_extension(format!("partial-{}", Uuid::new_v4()));
+        tokio::fs::write(&partial, bytes).await
+            .with_context(|| format!("Failed to write blob {}", key))?;
+        if let Err(e) = tokio::fs::rename(&partial, &path).await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(anyhow!(e).context(format!("Failed to write blob {}", key)));
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, anyhow::Error> {
+        let path = self.path_of(key)?;
+        tokio::fs::read(path).await
+            .with_context(|| format!("Failed to read blob {}", key))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), anyhow::Error> {
+        let path = self.path_of(key)?;
+        match tokio::fs::remove_file(path).await {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(anyhow!(e).context(format!("Failed to delete blob {}", key)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_storage() -> LocalBlobStorage {
+        LocalBlobStorage::new(std::env::temp_dir().join(format!("gvserver-blobs-{}", Uuid::new_v4())))
+    }
+
+    #[tokio::test]
+    async fn blobs_can_be_read_back_and_deleted() {
+        let storage = temp_storage();
+        storage.put("attachments/a/full", vec![1, 2, 3], "image/png").await.unwrap();
+        storage.put("attachments/a/full", vec![4, 5], "image/png").await.unwrap();
+        assert_eq!(storage.get("attachments/a/full").await.unwrap(), vec![4, 5]);
+
+        storage.delete("attachments/a/full").await.unwrap();
+        assert!(storage.get("attachments/a/full").await.is_err());
+        // Already gone
+        storage.delete("attachments/a/full").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn keys_cannot_leave_the_root() {
+        let storage = temp_storage();
+        for key in ["", "../outside", "/etc/passwd", "attachments/../../outside", "./attachments"] {
+            assert!(storage.put(key, vec![1], "image/png").await.is_err(), "{} was accepted", key);
+            assert!(storage.get(key).await.is_err(), "{} was accepted", key);
+        }
+    }
+}
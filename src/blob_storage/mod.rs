@@ -0,0 +1,172 @@
+pub mod local;
+pub mod s3;
+pub mod bytea_migration;
+pub mod exif_backfill;
+
+pub use local::LocalBlobStorage;
+pub use s3::S3BlobStorage;
+pub use bytea_migration::move_bytea_attachments;
+pub use exif_backfill::{run_exif_backfill, strip_legacy_exif};
+
+use async_trait::async_trait;
+use futures::future::{join_all, try_join_all};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::domain::ImageVariants;
+
+// Where attachments are kept, shared with the handlers as Data<dyn BlobStorage>.
+// contents rows only hold the keys.
+#[async_trait]
+pub trait BlobStorage: Send + Sync {
+    // Replaces whatever was stored under the key
+    async fn put(&self, key: &str, bytes: Vec<u8>, mime_type: &str) -> Result<(), anyhow::Error>;
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>, anyhow::Error>;
+
+    // Deleting a key that holds nothing is not an error
+    async fn delete(&self, key: &str) -> Result<(), anyhow::Error>;
+}
+
+// A stored blob with what contents keeps about it
+#[derive(Debug, Clone)]
+pub struct StoredBlob {
+    pub key: String,
+    pub size: i64,
+    pub sha256: String,
+    pub mime_type: String,
+}
+
+// Every size of one attachment, stored next to each other under attachments/<id>/.
+// Only the full image has its size, hash and MIME type kept, the others
+// are made from it and share its MIME type.
+#[derive(Debug, Clone)]
+pub struct StoredAttachment {
+    pub full: StoredBlob,
+    pub small_key: Option<String>,
+    pub medium_key: Option<String>,
+    pub with_exif_key: Option<String>,
+}
+
+impl StoredAttachment {
+    pub fn keys(&self) -> Vec<&str> {
+        let mut keys = vec![self.full.key.as_str()];
+        keys.extend(self.small_key.as_deref());
+        keys.extend(self.medium_key.as_deref());
+        keys.extend(self.with_exif_key.as_deref());
+        keys
+    }
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+// Attachments are images, anything else is stored as plain bytes
+pub fn mime_type_of(bytes: &[u8]) -> &'static str {
+    match image::guess_format(bytes) {
+        Ok(x) => x.to_mime_type(),
+        Err(_) => "application/octet-stream"
+    }
+}
+
+pub async fn put_blob(
+    storage: &dyn BlobStorage,
+    key: String,
+    bytes: Vec<u8>,
+) -> Result<StoredBlob, anyhow::Error> {
+    let size = bytes.len() as i64;
+    let sha256 = sha256_hex(&bytes);
+    let mime_type = mime_type_of(&bytes).to_string();
+    storage.put(&key, bytes, &mime_type).await?;
+    Ok(StoredBlob { key, size, sha256, mime_type })
+}
+
+// The blobs in the order of their keys
+pub async fn get_blobs(
+    storage: &dyn BlobStorage,
+    keys: &[String],
+) -> Result<Vec<Vec<u8>>, anyhow::Error> {
+    try_join_all(keys.iter().map(|x| storage.get(x))).await
+}
+
+// For blobs stored ahead of a database change that did not go through.
+// Failures are only logged, the blobs are unreferenced either way.
+pub async fn discard_blobs(storage: &dyn BlobStorage, keys: &[&str]) {
+    for (key, result) in keys.iter().zip(join_all(keys.iter().map(|x| storage.delete(x))).await) {
+        if let Err(e) = result {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to discard blob {}", key
+            );
+        }
+    }
+}
+
+pub async fn discard_attachments(storage: &dyn BlobStorage, stored: &[StoredAttachment]) {
+    discard_blobs(storage, &stored.iter().flat_map(StoredAttachment::keys).collect::<Vec<&str>>()).await
+}
+
+// Stores the full image and whichever smaller sizes are given,
+// nothing is left behind when one of them fails.
+pub async fn store_attachment(
+    storage: &dyn BlobStorage,
+    full: Vec<u8>,
+    small: Option<Vec<u8>>,
+    medium: Option<Vec<u8>>,
+    with_exif: Option<Vec<u8>>,
+) -> Result<StoredAttachment, anyhow::Error> {
+    let id = Uuid::new_v4();
+    let sizes = [("full", Some(full)), ("small", small), ("medium", medium), ("with_exif", with_exif)];
+    let results = join_all(sizes.into_iter().map(|(size, bytes)| async move {
+        match bytes {
+            Some(x) => put_blob(storage, format!("attachments/{}/{}", id, size), x).await.map(Some),
+            None => Ok(None)
+        }
+    })).await;
+    if results.iter().any(Result::is_err) {
+        let stored: Vec<&str> = results.iter()
+            .filter_map(|x| x.as_ref().ok().and_then(Option::as_ref))
+            .map(|x| x.key.as_str())
+            .collect();
+        discard_blobs(storage, &stored).await;
+    }
+    let mut stored = results.into_iter().collect::<Result<Vec<Option<StoredBlob>>, _>>()?;
+    let with_exif_key = stored.pop().flatten().map(|x| x.key);
+    let medium_key = stored.pop().flatten().map(|x| x.key);
+    let small_key = stored.pop().flatten().map(|x| x.key);
+    let full = stored.pop().flatten().expect("The full image is always stored.");
+    Ok(StoredAttachment { full, small_key, medium_key, with_exif_key })
+}
+
+// The attachments of a pinpoint with their thumbnails, at the same positions.
+// Either all of them are stored or none.
+pub async fn store_attachments(
+    storage: &dyn BlobStorage,
+    attachments: Vec<Vec<u8>>,
+    variants: Vec<ImageVariants>,
+) -> Result<Vec<StoredAttachment>, anyhow::Error> {
+    let mut variants = variants.into_iter();
+    let results = join_all(attachments.into_iter().map(|full| {
+        let (small, medium, with_exif) = match variants.next() {
+            Some(x) => (Some(x.small), Some(x.medium), x.full_with_exif),
+            None => (None, None, None)
+        };
+        store_attachment(storage, full, small, medium, with_exif)
+    })).await;
+    let mut stored: Vec<StoredAttachment> = Vec::with_capacity(results.len());
+    let mut error = None;
+    for result in results {
+        match result {
+            Ok(x) => stored.push(x),
+            Err(e) => { error.get_or_insert(e); }
+        }
+    }
+    match error {
+        Some(e) => {
+            discard_attachments(storage, &stored).await;
+            Err(e)
+        },
+        None => Ok(stored)
+    }
+}
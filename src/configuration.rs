@@ -8,6 +8,7 @@ use std::convert::{TryFrom, TryInto};
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
+    pub blob_storage: BlobStorageSettings,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -17,6 +18,16 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub jwt_secret: Secret<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub pinpoint_purge_interval_seconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub pinpoint_max_attachments: usize,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub pinpoint_location_precision_meters: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub data_export_poll_interval_seconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub data_export_retention_seconds: u64,
 }
 
 #[derive(serde::Deserialize, Clone)]
@@ -30,6 +41,33 @@ pub struct DatabaseSettings {
     pub require_ssl: bool,
 }
 
+// Where attachments are stored
+#[derive(serde::Deserialize, Clone)]
+pub struct BlobStorageSettings {
+    pub backend: BlobStorageBackend,
+    // Used by the local backend, relative to the working directory unless absolute
+    pub local_directory: String,
+    // Used by the s3 backend
+    pub s3: S3Settings,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BlobStorageBackend {
+    Local,
+    S3,
+}
+
+// An S3 compatible bucket, such as the MinIO of scripts/init_minio.sh
+#[derive(serde::Deserialize, Clone)]
+pub struct S3Settings {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: Secret<String>,
+}
+
 impl DatabaseSettings {
     pub fn without_db(&self) -> PgConnectOptions {
         let ssl_mode = if self.require_ssl {
@@ -0,0 +1,152 @@
+// Validated WGS84 coordinates in decimal degrees.
+// Non-finite values such as NaN and infinity are always rejected.
+
+use std::f64::consts::FRAC_PI_2;
+use crate::domain::pinpoint::EARTH_MEAN_RADIUS_METERS;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Latitude(f64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Longitude(f64);
+
+impl Latitude {
+    pub fn parse(value: f64) -> Result<Latitude, String> {
+        if value.is_finite() && (-90.0..=90.0).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(format!("{} is not a valid latitude. \
+            Latitudes must be between -90 and 90 degrees.", value))
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+}
+
+impl Longitude {
+    pub fn parse(value: f64) -> Result<Longitude, String> {
+        if value.is_finite() && (-180.0..=180.0).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(format!("{} is not a valid longitude. \
+            Longitudes must be between -180 and 180 degrees.", value))
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+}
+
+// Latitude and longitude ranges covering every point within a distance of a
+// centre, for prefiltering radius searches on an index before the exact
+// haversine check. min_longitude is greater than max_longitude when the box
+// crosses the antimeridian. Boxes reaching a pole take in every longitude.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    pub min_longitude: f64,
+    pub max_longitude: f64,
+}
+
+impl BoundingBox {
+    pub fn around(latitude: f64, longitude: f64, meters: f64) -> Self {
+        let angle = meters / EARTH_MEAN_RADIUS_METERS;
+        let min_latitude = latitude - angle.to_degrees();
+        let max_latitude = latitude + angle.to_degrees();
+        if min_latitude <= -90.0 || max_latitude >= 90.0 || angle >= FRAC_PI_2 {
+            return Self {
+                min_latitude: min_latitude.max(-90.0),
+                max_latitude: max_latitude.min(90.0),
+                min_longitude: -180.0,
+                max_longitude: 180.0,
+            };
+        }
+        // The widest longitude difference on a circle of that angular radius
+        let longitude_delta = (angle.sin() / latitude.to_radians().cos()).asin().to_degrees();
+        if longitude_delta.is_nan() || longitude_delta >= 180.0 {
+            return Self { min_latitude, max_latitude, min_longitude: -180.0, max_longitude: 180.0 };
+        }
+        let wrap = |x: f64| if x < -180.0 { x + 360.0 } else if x > 180.0 { x - 360.0 } else { x };
+        Self {
+            min_latitude,
+            max_latitude,
+            min_longitude: wrap(longitude - longitude_delta),
+            max_longitude: wrap(longitude + longitude_delta),
+        }
+    }
+}
+
+impl std::fmt::Display for Latitude {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::fmt::Display for Longitude {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use super::{BoundingBox, Latitude, Longitude};
+
+    #[test]
+    fn boundary_values_are_accepted() {
+        assert_ok!(Latitude::parse(-90.0));
+        assert_ok!(Latitude::parse(90.0));
+        assert_ok!(Longitude::parse(-180.0));
+        assert_ok!(Longitude::parse(180.0));
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        assert_err!(Latitude::parse(90.000001));
+        assert_err!(Latitude::parse(-500.0));
+        assert_err!(Longitude::parse(180.000001));
+        assert_err!(Longitude::parse(500.0));
+    }
+
+    #[test]
+    fn non_finite_values_are_rejected() {
+        for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_err!(Latitude::parse(value));
+            assert_err!(Longitude::parse(value));
+        }
+    }
+
+    #[test]
+    fn bounding_boxes_cover_the_radius() {
+        // About 1 km is 0.009 degrees of latitude, and 0.018 of longitude at 60 degrees
+        let bbox = BoundingBox::around(60.0, 10.0, 1000.0);
+        assert!((bbox.max_latitude - 60.009).abs() < 1e-3);
+        assert!((bbox.min_latitude - 59.991).abs() < 1e-3);
+        assert!((bbox.max_longitude - 10.018).abs() < 1e-3);
+        assert!((bbox.min_longitude - 9.982).abs() < 1e-3);
+    }
+
+    #[test]
+    fn bounding_boxes_wrap_at_the_antimeridian_and_poles() {
+        let bbox = BoundingBox::around(0.0, 179.99, 5000.0);
+        assert!(bbox.min_longitude > bbox.max_longitude);
+        assert!(bbox.max_longitude < -179.9);
+        let bbox = BoundingBox::around(89.99, 0.0, 5000.0);
+        assert_eq!((bbox.min_longitude, bbox.max_longitude, bbox.max_latitude), (-180.0, 180.0, 90.0));
+        let bbox = BoundingBox::around(0.0, 0.0, 30_000_000.0);
+        assert_eq!((bbox.min_latitude, bbox.max_latitude), (-90.0, 90.0));
+    }
+
+    #[quickcheck_macros::quickcheck]
+    fn in_range_latitudes_keep_their_value(value: f64) -> bool {
+        match Latitude::parse(value) {
+            Ok(x) => x.value() == value,
+            Err(_) => !value.is_finite() || !(-90.0..=90.0).contains(&value)
+        }
+    }
+}
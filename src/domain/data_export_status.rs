@@ -0,0 +1,36 @@
+// Progress of a personal data export.
+// Stored as text in data_exports.status.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DataExportStatus {
+    // Waiting for the export worker
+    Pending,
+    // The archive can be downloaded
+    Done,
+    Failed,
+}
+
+impl DataExportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataExportStatus::Pending => "pending",
+            DataExportStatus::Done => "done",
+            DataExportStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<DataExportStatus, String> {
+        match s {
+            "pending" => Ok(DataExportStatus::Pending),
+            "done" => Ok(DataExportStatus::Done),
+            "failed" => Ok(DataExportStatus::Failed),
+            other => Err(format!("{} is not a valid data export status.", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for DataExportStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
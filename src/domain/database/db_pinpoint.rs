@@ -1,5 +1,4 @@
 use chrono::{DateTime, Utc};
-use sqlx::{Error, FromRow};
 use uuid::{Uuid};
 
 #[derive(sqlx::FromRow)]
@@ -7,21 +6,33 @@ pub struct DbPinpoint {
     #[sqlx]
     pub pinpoint_id: Uuid,
     #[sqlx]
-    pub latitude: Option<f64>,
+    pub latitude: f64,
     #[sqlx]
-    pub longitude: Option<f64>,
+    pub longitude: f64,
     #[sqlx]
     pub added_at: DateTime<Utc>,
     #[sqlx]
+    pub modified_at: Option<DateTime<Utc>>,
+    #[sqlx]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[sqlx]
     pub contents_id: Uuid,
     #[sqlx]
     pub description: Option<String>,
     #[sqlx]
-    pub attachment: Option<Vec<u8>>,
+    pub attachment_keys: Option<Vec<String>>,
     #[sqlx]
     pub user_id: Uuid,
     #[sqlx]
-    pub username: String
+    pub username: String,
+    #[sqlx]
+    pub visibility: String,
+    #[sqlx]
+    pub distance: Option<f64>,
+    #[sqlx]
+    pub rank: Option<f64>,
+    #[sqlx]
+    pub tags: Option<Vec<String>>
 }
 
 impl DbPinpoint {}
\ No newline at end of file
@@ -0,0 +1,9 @@
+use uuid::Uuid;
+
+#[derive(sqlx::FromRow)]
+pub struct DbPinpointCluster {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub count: Option<i64>,
+    pub sample_pinpoint_ids: Option<Vec<Uuid>>
+}
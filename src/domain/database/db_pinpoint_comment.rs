@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+#[derive(sqlx::FromRow)]
+pub struct DbPinpointComment {
+    pub comment_id: Uuid,
+    pub pinpoint_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub username: String,
+    pub body: Option<String>,
+    pub added_at: DateTime<Utc>,
+    pub modified_at: Option<DateTime<Utc>>,
+    pub reply_count: Option<i64>
+}
@@ -11,7 +11,8 @@ pub struct DbUser {
     pub role_title: String,
     pub contents_id: Option<Uuid>,
     pub contents_description: Option<String>,
-    pub contents_attachment: Option<Vec<u8>>
+    pub contents_attachment_key: Option<String>,
+    pub contents_attachment_sha256: Option<String>
 }
 
 impl DbUser {}
\ No newline at end of file
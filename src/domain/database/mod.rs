@@ -1,5 +1,9 @@
 pub mod db_pinpoint;
+pub mod db_pinpoint_cluster;
+pub mod db_pinpoint_comment;
 pub mod db_user;
 
 pub use db_pinpoint::DbPinpoint;
-pub use db_user::DbUser;
\ No newline at end of file
+pub use db_pinpoint_cluster::DbPinpointCluster;
+pub use db_pinpoint_comment::DbPinpointComment;
+pub use db_user::DbUser;
@@ -0,0 +1,476 @@
+use std::io::Cursor;
+use image::{DynamicImage, ImageFormat, ImageOutputFormat};
+use image::imageops::FilterType;
+use image::io::{Limits, Reader};
+use tokio::task::JoinError;
+use crate::telemetry::spawn_blocking_with_tracing;
+
+// Every pinpoint and profile attachment goes through normalize_image before
+// it is stored. Uploads are decoded, turned upright according to their EXIF
+// orientation, scaled down to fit MAX_IMAGE_DIMENSION and re-encoded, so
+// stored attachments are always a JPEG, or a PNG when they have transparency.
+// Re-encoding also leaves out whatever else the upload carried, EXIF included,
+// so nobody can read a location, device or time from a stored attachment.
+// Pinpoint attachments are stored along with smaller thumbnails of themselves,
+// and a copy keeping the upload's EXIF if the owner asked for one.
+
+pub const MAX_IMAGE_DIMENSION: u32 = 2048;
+// A phone photo is about 12 megapixels, a small compressed file claiming
+// far more than this is most likely a decompression bomb
+pub const MAX_SOURCE_PIXELS: u64 = 64_000_000;
+pub const MAX_SOURCE_DIMENSION: u32 = 16_384;
+const MAX_DECODER_ALLOC_BYTES: u64 = 512 * 1024 * 1024;
+const JPEG_QUALITY: u8 = 85;
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const ORIENTATION_TAG: u16 = 0x0112;
+
+const ACCEPTED_FORMATS: [ImageFormat; 5] = [
+    ImageFormat::Jpeg, ImageFormat::Png, ImageFormat::Gif, ImageFormat::WebP, ImageFormat::Bmp
+];
+
+// The sizes an attachment is stored in, by their longest side
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageSize {
+    // Previews on the map and in lists
+    #[default]
+    Small,
+    Medium,
+    Full,
+}
+
+impl ImageSize {
+    pub fn max_dimension(&self) -> u32 {
+        match self {
+            ImageSize::Small => 64,
+            ImageSize::Medium => 512,
+            ImageSize::Full => MAX_IMAGE_DIMENSION,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageSize::Small => "small",
+            ImageSize::Medium => "medium",
+            ImageSize::Full => "full",
+        }
+    }
+}
+
+impl std::fmt::Display for ImageSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+// Variants of a normalized image other than the full size one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageVariants {
+    pub small: Vec<u8>,
+    pub medium: Vec<u8>,
+    // The full size image with the upload's EXIF put back, for its owner only.
+    // None unless asked for, or when the upload had no EXIF or became a PNG.
+    pub full_with_exif: Option<Vec<u8>>,
+}
+
+pub fn normalize_image(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let image = fit_to_size(decode_upright(bytes)?, ImageSize::Full);
+    encode_image(&image)
+}
+
+pub fn normalize_image_with_variants(
+    bytes: &[u8],
+    keep_exif: bool,
+) -> Result<(Vec<u8>, ImageVariants), String> {
+    let full = fit_to_size(decode_upright(bytes)?, ImageSize::Full);
+    // Each size is scaled down from the next larger one
+    let medium = fit_to_size(full.clone(), ImageSize::Medium);
+    let small = fit_to_size(medium.clone(), ImageSize::Small);
+    let full = encode_image(&full)?;
+    let variants = ImageVariants {
+        small: encode_image(&small)?,
+        medium: encode_image(&medium)?,
+        full_with_exif: if keep_exif { with_exif_of(&full, bytes)? } else { None },
+    };
+    Ok((full, variants))
+}
+
+// For attachments stored before uploads were normalized. None when there is
+// no EXIF to strip, otherwise the attachment as it would be stored today.
+pub fn normalize_legacy_image(bytes: &[u8]) -> Result<Option<(Vec<u8>, ImageVariants)>, String> {
+    if read_exif(bytes).is_none() {
+        return Ok(None);
+    }
+    normalize_image_with_variants(bytes, false).map(Some)
+}
+
+// Keeps the order of the attachments, the error names the first bad one
+pub fn normalize_images(
+    images: Vec<Vec<u8>>,
+    keep_exif: bool,
+) -> Result<Vec<(Vec<u8>, ImageVariants)>, String> {
+    images.iter()
+        .enumerate()
+        .map(|(position, x)| normalize_image_with_variants(x, keep_exif)
+            .map_err(|e| format!("Attachment {}: {}", position, e)))
+        .collect()
+}
+
+// Decoding is CPU bound, so it runs on the blocking thread pool.
+// The outer error is the task failing to run, the inner one a rejected image.
+pub async fn normalize_image_blocking(
+    image: Vec<u8>,
+) -> Result<Result<Vec<u8>, String>, JoinError> {
+    spawn_blocking_with_tracing(move || normalize_image(&image)).await
+}
+
+pub async fn normalize_legacy_image_blocking(
+    image: Vec<u8>,
+) -> Result<Result<Option<(Vec<u8>, ImageVariants)>, String>, JoinError> {
+    spawn_blocking_with_tracing(move || normalize_legacy_image(&image)).await
+}
+
+pub async fn normalize_images_blocking(
+    images: Vec<Vec<u8>>,
+    keep_exif: bool,
+) -> Result<Result<Vec<(Vec<u8>, ImageVariants)>, String>, JoinError> {
+    spawn_blocking_with_tracing(move || normalize_images(images, keep_exif)).await
+}
+
+fn decode_upright(bytes: &[u8]) -> Result<DynamicImage, String> {
+    let format = image::guess_format(bytes)
+        .map_err(|_| String::from("The attachment is not an image."))?;
+    if !ACCEPTED_FORMATS.contains(&format) {
+        return Err(format!("{:?} images are not accepted.", format));
+    }
+    // The header is checked before anything gets decoded
+    let (width, height) = image_reader(bytes, format).into_dimensions()
+        .map_err(|_| String::from("The attachment is not a valid image."))?;
+    if u64::from(width) * u64::from(height) > MAX_SOURCE_PIXELS {
+        return Err(format!("The image can have at most {} pixels.", MAX_SOURCE_PIXELS));
+    }
+    let image = image_reader(bytes, format).decode()
+        .map_err(|_| String::from("The attachment is not a valid image."))?;
+    Ok(apply_orientation(image, exif_orientation(bytes)))
+}
+
+// Smaller images are left as they are rather than scaled up
+fn fit_to_size(image: DynamicImage, size: ImageSize) -> DynamicImage {
+    let max = size.max_dimension();
+    if image.width() > max || image.height() > max {
+        image.resize(max, max, FilterType::Lanczos3)
+    } else {
+        image
+    }
+}
+
+fn image_reader(bytes: &[u8], format: ImageFormat) -> Reader<Cursor<&[u8]>> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODER_ALLOC_BYTES);
+    let mut reader = Reader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    reader
+}
+
+fn encode_image(image: &DynamicImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Cursor::new(Vec::new());
+    let written = if image.color().has_alpha() {
+        image.write_to(&mut bytes, ImageOutputFormat::Png)
+    } else {
+        // JPEG has no room for 16 bit channels
+        DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_to(&mut bytes, ImageOutputFormat::Jpeg(JPEG_QUALITY))
+    };
+    written.map_err(|_| String::from("Failed to encode the image."))?;
+    Ok(bytes.into_inner())
+}
+
+fn read_exif(bytes: &[u8]) -> Option<exif::Exif> {
+    exif::Reader::new().read_from_container(&mut Cursor::new(bytes)).ok()
+}
+
+// Where a photo was taken, as latitude and longitude in degrees.
+// Read from the upload itself, since stored attachments have no EXIF left.
+pub fn exif_location(bytes: &[u8]) -> Option<(f64, f64)> {
+    let exif = read_exif(bytes)?;
+    let coordinate = |tag: exif::Tag, reference_tag: exif::Tag, negative: u8| {
+        let degrees = match &exif.get_field(tag, exif::In::PRIMARY)?.value {
+            exif::Value::Rational(x) if x.len() == 3 =>
+                x[0].to_f64() + x[1].to_f64() / 60.0 + x[2].to_f64() / 3600.0,
+            _ => return None
+        };
+        let reference = match &exif.get_field(reference_tag, exif::In::PRIMARY)?.value {
+            exif::Value::Ascii(x) => *x.first()?.first()?,
+            _ => return None
+        };
+        let coordinate = if reference == negative { -degrees } else { degrees };
+        coordinate.is_finite().then_some(coordinate)
+    };
+    Some((
+        coordinate(exif::Tag::GPSLatitude, exif::Tag::GPSLatitudeRef, b'S')?,
+        coordinate(exif::Tag::GPSLongitude, exif::Tag::GPSLongitudeRef, b'W')?,
+    ))
+}
+
+// 1 when the image has no orientation, which is already upright
+fn exif_orientation(bytes: &[u8]) -> u32 {
+    read_exif(bytes)
+        .and_then(|x| x.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+            .and_then(|x| x.value.get_uint(0)))
+        .unwrap_or(1)
+}
+
+// The JPEG with the EXIF of the original in an APP1 segment right after
+// its start of image marker. The orientation is reset since the pixels were
+// already turned upright. None when there is no JPEG or no EXIF to keep.
+fn with_exif_of(jpeg: &[u8], original: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    if image::guess_format(jpeg).ok() != Some(ImageFormat::Jpeg) {
+        return Ok(None);
+    }
+    let exif = match read_exif(original) {
+        Some(x) => x,
+        None => return Ok(None)
+    };
+    let mut tiff = exif.buf().to_vec();
+    reset_orientation(&mut tiff, exif.little_endian());
+    let segment = exif_segment(&tiff)?;
+    let mut result = Vec::with_capacity(jpeg.len() + segment.len());
+    result.extend_from_slice(&jpeg[..2]);
+    result.extend_from_slice(&segment);
+    result.extend_from_slice(&jpeg[2..]);
+    Ok(Some(result))
+}
+
+// The APP1 segment holding the TIFF data. Readers only look for EXIF in a
+// single segment, whose length has to fit in 16 bits, so more than that
+// cannot be kept. Only uploads in other formats carry that much.
+fn exif_segment(tiff: &[u8]) -> Result<Vec<u8>, String> {
+    let length = u16::try_from(2 + EXIF_HEADER.len() + tiff.len())
+        .map_err(|_| String::from("The attachment has too much EXIF to keep."))?;
+    let mut segment = Vec::with_capacity(usize::from(length) + 2);
+    segment.extend_from_slice(&[0xFF, 0xE1]);
+    segment.extend_from_slice(&length.to_be_bytes());
+    segment.extend_from_slice(EXIF_HEADER);
+    segment.extend_from_slice(tiff);
+    Ok(segment)
+}
+
+// Sets the orientation of the primary image to upright in the TIFF data,
+// where it is a SHORT held in the first bytes of its IFD entry
+fn reset_orientation(tiff: &mut [u8], little_endian: bool) -> Option<()> {
+    let read_u16 = |x: &[u8]| if little_endian {
+        u16::from_le_bytes([x[0], x[1]])
+    } else {
+        u16::from_be_bytes([x[0], x[1]])
+    };
+    let read_u32 = |x: &[u8]| if little_endian {
+        u32::from_le_bytes([x[0], x[1], x[2], x[3]])
+    } else {
+        u32::from_be_bytes([x[0], x[1], x[2], x[3]])
+    };
+    let ifd = usize::try_from(read_u32(tiff.get(4..8)?)).ok()?;
+    let count = usize::from(read_u16(tiff.get(ifd..ifd + 2)?));
+    for entry in (0..count).map(|x| ifd + 2 + x * 12) {
+        if read_u16(tiff.get(entry..entry + 2)?) == ORIENTATION_TAG {
+            let upright = if little_endian { 1u16.to_le_bytes() } else { 1u16.to_be_bytes() };
+            tiff.get_mut(entry + 8..entry + 10)?.copy_from_slice(&upright);
+        }
+    }
+    Some(())
+}
+
+fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use claim::{assert_err, assert_ok};
+    use image::{DynamicImage, ImageFormat, ImageOutputFormat, Rgba, RgbaImage, RgbImage};
+    use exif::{Field, In, Rational, Tag, Value};
+    use super::{apply_orientation, exif_location, exif_segment, normalize_image,
+                normalize_image_with_variants, normalize_images, normalize_legacy_image,
+                read_exif, ImageSize, MAX_IMAGE_DIMENSION};
+
+    fn encoded(image: DynamicImage, format: ImageOutputFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    // A JPEG taken by a phone held sideways at 51.5 degrees north, 0.125 degrees west
+    fn photo(width: u32, height: u32) -> Vec<u8> {
+        let fields = [
+            Field { tag: Tag::Orientation, ifd_num: In::PRIMARY, value: Value::Short(vec![6]) },
+            Field { tag: Tag::Model, ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![b"Phone".to_vec()]) },
+            Field { tag: Tag::GPSLatitudeRef, ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![b"N".to_vec()]) },
+            Field { tag: Tag::GPSLatitude, ifd_num: In::PRIMARY, value: Value::Rational(vec![
+                Rational { num: 51, denom: 1 }, Rational { num: 30, denom: 1 },
+                Rational { num: 0, denom: 1 }]) },
+            Field { tag: Tag::GPSLongitudeRef, ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![b"W".to_vec()]) },
+            Field { tag: Tag::GPSLongitude, ifd_num: In::PRIMARY, value: Value::Rational(vec![
+                Rational { num: 0, denom: 1 }, Rational { num: 7, denom: 1 },
+                Rational { num: 30, denom: 1 }]) },
+        ];
+        let mut writer = exif::experimental::Writer::new();
+        for field in fields.iter() {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+        let jpeg = encoded(DynamicImage::ImageRgb8(RgbImage::new(width, height)),
+                           ImageOutputFormat::Jpeg(90));
+        let mut bytes = jpeg[..2].to_vec();
+        bytes.extend_from_slice(&[0xFF, 0xE1]);
+        bytes.extend_from_slice(&(8 + tiff.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(b"Exif\0\0");
+        bytes.extend_from_slice(&tiff);
+        bytes.extend_from_slice(&jpeg[2..]);
+        bytes
+    }
+
+    #[test]
+    fn exif_is_stripped_unless_kept() {
+        let photo = photo(40, 10);
+        assert!(read_exif(&photo).is_some());
+        let (full, variants) = normalize_image_with_variants(&photo, false).unwrap();
+        assert!(read_exif(&full).is_none());
+        assert!(read_exif(&variants.small).is_none());
+        assert!(read_exif(&variants.medium).is_none());
+        assert_eq!(variants.full_with_exif, None);
+        assert_eq!(image::load_from_memory(&full).unwrap().width(), 10);
+    }
+
+    #[test]
+    fn kept_exif_is_put_back_upright() {
+        let (full, variants) = normalize_image_with_variants(&photo(40, 10), true).unwrap();
+        assert!(read_exif(&full).is_none());
+        let copy = variants.full_with_exif.unwrap();
+        let exif = read_exif(&copy).unwrap();
+        assert!(exif.get_field(Tag::GPSLatitude, In::PRIMARY).is_some());
+        assert!(exif.get_field(Tag::Model, In::PRIMARY).is_some());
+        let orientation = exif.get_field(Tag::Orientation, In::PRIMARY).unwrap();
+        assert_eq!(orientation.value.get_uint(0), Some(1));
+        let copy = image::load_from_memory(&copy).unwrap();
+        assert_eq!((copy.width(), copy.height()), (10, 40));
+        // Uploads without EXIF have nothing to keep
+        let image = DynamicImage::ImageRgb8(RgbImage::new(10, 10));
+        let (_, variants) = normalize_image_with_variants(
+            &encoded(image, ImageOutputFormat::Png), true).unwrap();
+        assert_eq!(variants.full_with_exif, None);
+    }
+
+    #[test]
+    fn legacy_images_are_only_normalized_when_they_have_exif() {
+        let (full, variants) = normalize_legacy_image(&photo(40, 10)).unwrap().unwrap();
+        assert!(read_exif(&full).is_none());
+        assert_eq!(variants.full_with_exif, None);
+        let image = encoded(DynamicImage::ImageRgb8(RgbImage::new(10, 10)), ImageOutputFormat::Png);
+        assert_eq!(normalize_legacy_image(&image), Ok(None));
+    }
+
+    #[test]
+    fn exif_too_large_for_a_segment_is_rejected() {
+        assert_ok!(exif_segment(&vec![0; 65_000]));
+        assert_err!(exif_segment(&vec![0; 70_000]));
+    }
+
+    #[test]
+    fn photo_locations_are_read_from_exif() {
+        let (latitude, longitude) = exif_location(&photo(10, 10)).unwrap();
+        assert!((latitude - 51.5).abs() < 1e-9);
+        assert!((longitude - -0.125).abs() < 1e-9);
+        let image = DynamicImage::ImageRgb8(RgbImage::new(10, 10));
+        assert_eq!(exif_location(&encoded(image, ImageOutputFormat::Jpeg(90))), None);
+        assert_eq!(exif_location(&[1, 1]), None);
+    }
+
+    #[test]
+    fn large_images_are_scaled_down_to_a_jpeg() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(MAX_IMAGE_DIMENSION * 2, 100));
+        let normalized = normalize_image(&encoded(image, ImageOutputFormat::Png)).unwrap();
+        assert_eq!(image::guess_format(&normalized).unwrap(), ImageFormat::Jpeg);
+        let normalized = image::load_from_memory(&normalized).unwrap();
+        assert_eq!((normalized.width(), normalized.height()), (MAX_IMAGE_DIMENSION, 50));
+    }
+
+    #[test]
+    fn thumbnails_fit_their_size() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(1000, 250));
+        let (full, thumbnails) = normalize_image_with_variants(
+            &encoded(image, ImageOutputFormat::Png), false).unwrap();
+        let dimensions = |x: &[u8]| {
+            let image = image::load_from_memory(x).unwrap();
+            (image.width(), image.height())
+        };
+        assert_eq!(dimensions(&full), (1000, 250));
+        assert_eq!(dimensions(&thumbnails.medium), (ImageSize::Medium.max_dimension(), 128));
+        assert_eq!(dimensions(&thumbnails.small), (ImageSize::Small.max_dimension(), 16));
+
+        // Nothing is scaled up
+        let image = DynamicImage::ImageRgb8(RgbImage::new(20, 10));
+        let (_, thumbnails) = normalize_image_with_variants(
+            &encoded(image, ImageOutputFormat::Png), false).unwrap();
+        assert_eq!(dimensions(&thumbnails.small), (20, 10));
+    }
+
+    #[test]
+    fn transparent_images_stay_png() {
+        let image = DynamicImage::ImageRgba8(
+            RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 0])));
+        let normalized = normalize_image(&encoded(image, ImageOutputFormat::Png)).unwrap();
+        assert_eq!(image::guess_format(&normalized).unwrap(), ImageFormat::Png);
+    }
+
+    #[test]
+    fn orientation_turns_images_upright() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(40, 10));
+        let upright = apply_orientation(image.clone(), 6);
+        assert_eq!((upright.width(), upright.height()), (10, 40));
+        let upright = apply_orientation(image, 1);
+        assert_eq!((upright.width(), upright.height()), (40, 10));
+    }
+
+    #[test]
+    fn non_images_are_rejected() {
+        assert_err!(normalize_image(&[1, 1]));
+        assert_err!(normalize_image(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"));
+        // A PNG header with a truncated body
+        let image = DynamicImage::ImageRgb8(RgbImage::new(10, 10));
+        let bytes = encoded(image, ImageOutputFormat::Png);
+        assert_err!(normalize_image(&bytes[..bytes.len() / 2]));
+        assert_err!(normalize_images(vec![bytes.clone(), vec![1, 1]], false));
+        assert_ok!(normalize_images(vec![bytes], false));
+    }
+
+    #[test]
+    fn decompression_bombs_are_rejected() {
+        // A bitmap header claiming 20000 by 20000 pixels, the pixels never come
+        let mut header = Vec::new();
+        header.extend_from_slice(b"BM");
+        for x in [0u32, 0, 54, 40, 20_000, 20_000] {
+            header.extend_from_slice(&x.to_le_bytes());
+        }
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&24u16.to_le_bytes());
+        header.extend_from_slice(&[0; 24]);
+        let error = normalize_image(&header).unwrap_err();
+        assert!(error.contains("at most"), "{}", error);
+    }
+}
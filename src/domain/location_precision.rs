@@ -0,0 +1,119 @@
+use std::f64::consts::PI;
+use crate::domain::pinpoint::EARTH_MEAN_RADIUS_METERS;
+
+// How precisely other users may learn where a pinpoint is.
+// Coordinates are snapped to the centre of a grid cell about this many
+// meters wide rather than jittered at random, so the same pinpoint always
+// lands on the same spot and repeated queries cannot average the noise out.
+// Pinpoints posted around the same place share a cell for the same reason.
+// Zero leaves coordinates exact.
+
+const METERS_PER_DEGREE_LATITUDE: f64 = EARTH_MEAN_RADIUS_METERS * PI / 180.0;
+// Past this the grid spans several degrees and stops being useful
+pub const MAX_LOCATION_PRECISION_METERS: f64 = 100_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocationPrecision(f64);
+
+impl LocationPrecision {
+    pub fn parse(meters: f64) -> Result<LocationPrecision, String> {
+        if meters.is_finite() && (0.0..=MAX_LOCATION_PRECISION_METERS).contains(&meters) {
+            Ok(Self(meters))
+        } else {
+            Err(format!("{} is not a valid location precision. \
+            It must be between 0 and {} meters.", meters, MAX_LOCATION_PRECISION_METERS))
+        }
+    }
+
+    pub fn meters(&self) -> f64 {
+        self.0
+    }
+
+    // The centre of the grid cell holding the coordinates
+    pub fn obscure(&self, latitude: f64, longitude: f64) -> (f64, f64) {
+        if self.0 == 0.0 {
+            return (latitude, longitude);
+        }
+        let latitude_step = self.0 / METERS_PER_DEGREE_LATITUDE;
+        let snapped_latitude = (((latitude / latitude_step).floor() + 0.5) * latitude_step)
+            .clamp(-90.0, 90.0);
+        // Cells keep about the same width in meters away from the equator
+        let longitude_step = (latitude_step / snapped_latitude.to_radians().cos().max(1e-6))
+            .min(360.0);
+        let snapped_longitude = ((((longitude + 180.0) / longitude_step).floor() + 0.5)
+            * longitude_step - 180.0)
+            .clamp(-180.0, 180.0);
+        (snapped_latitude, snapped_longitude)
+    }
+
+    // Distances to other users' pinpoints are rounded to the same precision
+    pub fn obscure_distance(&self, distance: f64) -> f64 {
+        if self.0 == 0.0 {
+            return distance;
+        }
+        (distance / self.0).round() * self.0
+    }
+}
+
+impl Default for LocationPrecision {
+    fn default() -> Self {
+        Self(100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use super::LocationPrecision;
+
+    // Equirectangular approximation, good enough over a few hundred meters
+    fn meters_between(a: (f64, f64), b: (f64, f64)) -> f64 {
+        let x = (b.1 - a.1).to_radians() * ((a.0 + b.0) / 2.0).to_radians().cos();
+        let y = (b.0 - a.0).to_radians();
+        (x * x + y * y).sqrt() * super::EARTH_MEAN_RADIUS_METERS
+    }
+
+    #[test]
+    fn obscured_coordinates_stay_within_the_precision() {
+        let precision = LocationPrecision::parse(100.0).unwrap();
+        for location in [(51.5007, -0.1246), (-33.8568, 151.2153), (0.0, 0.0), (69.6492, 18.9553)] {
+            let obscured = precision.obscure(location.0, location.1);
+            assert_ne!(obscured, location);
+            assert!(meters_between(location, obscured) <= 100.0);
+        }
+    }
+
+    #[test]
+    fn obscured_coordinates_are_deterministic_and_shared_by_neighbours() {
+        let precision = LocationPrecision::parse(100.0).unwrap();
+        let first = precision.obscure(51.50071, -0.12461);
+        assert_eq!(first, precision.obscure(51.50071, -0.12461));
+        assert_eq!(first, precision.obscure(51.50072, -0.12462));
+    }
+
+    #[test]
+    fn obscured_coordinates_stay_valid_near_the_poles_and_antimeridian() {
+        let precision = LocationPrecision::parse(1000.0).unwrap();
+        for location in [(90.0, 180.0), (-90.0, -180.0), (89.9999, 179.9999)] {
+            let (latitude, longitude) = precision.obscure(location.0, location.1);
+            assert!((-90.0..=90.0).contains(&latitude));
+            assert!((-180.0..=180.0).contains(&longitude));
+        }
+    }
+
+    #[test]
+    fn zero_precision_keeps_exact_values() {
+        let precision = LocationPrecision::parse(0.0).unwrap();
+        assert_eq!(precision.obscure(51.50071, -0.12461), (51.50071, -0.12461));
+        assert_eq!(precision.obscure_distance(1234.5), 1234.5);
+        assert_eq!(LocationPrecision::default().obscure_distance(1234.5), 1200.0);
+    }
+
+    #[test]
+    fn precision_must_be_sensible() {
+        assert_ok!(LocationPrecision::parse(100.0));
+        assert_err!(LocationPrecision::parse(-1.0));
+        assert_err!(LocationPrecision::parse(f64::NAN));
+        assert_err!(LocationPrecision::parse(1_000_000.0));
+    }
+}
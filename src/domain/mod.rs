@@ -1,13 +1,33 @@
 pub mod pinpoint;
 pub mod app_user;
 pub mod user_email;
+pub mod coordinates;
+pub mod pinpoint_visibility;
+pub mod pinpoint_reaction;
+pub mod pinpoint_tag;
+pub mod pinpoint_event;
+pub mod data_export_status;
+pub mod location_precision;
+pub mod private_zone;
+pub mod waypoint_file;
+pub mod image_handling;
 mod errors;
-mod image_handling;
 
 pub mod user_sign_up;
 pub mod database;
 
 pub use pinpoint::Pinpoint;
 pub use pinpoint::TempUsername;
-
-
+pub use coordinates::{BoundingBox, Latitude, Longitude};
+pub use pinpoint_visibility::PinpointVisibility;
+pub use pinpoint_reaction::PinpointReaction;
+pub use pinpoint_tag::PinpointTag;
+pub use pinpoint_event::{PinpointEvent, PinpointEventKind, PinpointEventPrevious};
+pub use data_export_status::DataExportStatus;
+pub use location_precision::LocationPrecision;
+pub use private_zone::{PrivateZone, PrivateZoneMode};
+pub use waypoint_file::{Waypoint, WaypointEntry, WaypointFile, WaypointFormat};
+pub use image_handling::{exif_location, normalize_image, normalize_image_blocking,
+                         normalize_image_with_variants, normalize_images, normalize_images_blocking,
+                         normalize_legacy_image, normalize_legacy_image_blocking,
+                         ImageSize, ImageVariants};
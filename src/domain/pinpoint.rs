@@ -1,43 +1,71 @@
-use std::fmt::{Display, Formatter};
 use chrono::{DateTime, Utc};
-use chrono::serde::ts_seconds;
 use uuid::Uuid;
 use crate::domain::database::DbPinpoint;
+use crate::domain::{ImageVariants, Latitude, Longitude, PinpointTag, PinpointVisibility};
+
+// Mean radius of the Earth used for great-circle distances
+pub const EARTH_MEAN_RADIUS_METERS: f64 = 6_371_008.8;
 
 pub struct Pinpoint {
     pub pinpoint_id: Uuid,
-    pub latitude: f64,
-    pub longitude: f64,
+    pub latitude: Latitude,
+    pub longitude: Longitude,
     pub added_at: DateTime<Utc>,
+    pub modified_at: Option<DateTime<Utc>>,
+    // Ephemeral pinpoints stop being shown after this
+    pub expires_at: Option<DateTime<Utc>>,
     pub contents_id: Uuid,
     pub description: String,
-    pub attachment: Option<Vec<u8>>,
+    // In order, the first one is stored alongside the description
+    pub attachments: Vec<Vec<u8>>,
+    // Thumbnails of the attachments at the same positions, made on upload.
+    // Empty for pinpoints read back, which hold the requested size in attachments.
+    pub variants: Vec<ImageVariants>,
     pub user_id: Option<Uuid>,
-    pub username: String
+    pub username: String,
+    pub visibility: PinpointVisibility,
+    // Distance in meters from the searched location, if there was one
+    pub distance: Option<f64>,
+    // Full-text search relevance, if a search was made
+    pub rank: Option<f64>,
+    pub tags: Vec<PinpointTag>
 }
 
 impl Pinpoint {
     pub fn new(
         pinpoint_id: Uuid,
-        latitude: f64,
-        longitude: f64,
+        latitude: Latitude,
+        longitude: Longitude,
         added_at: DateTime<Utc>,
+        modified_at: Option<DateTime<Utc>>,
+        expires_at: Option<DateTime<Utc>>,
         contents_id: Uuid,
         description: String,
-        attachment: Option<Vec<u8>>,
+        attachments: Vec<Vec<u8>>,
         user_id: Option<Uuid>,
-        username: String
+        username: String,
+        visibility: PinpointVisibility,
+        distance: Option<f64>,
+        rank: Option<f64>,
+        tags: Vec<PinpointTag>
     ) -> Self {
         Self {
             pinpoint_id,
             latitude,
             longitude,
             added_at,
+            modified_at,
+            expires_at,
             contents_id,
             description,
-            attachment,
+            attachments,
+            variants: Vec::new(),
             user_id,
-            username
+            username,
+            visibility,
+            distance,
+            rank,
+            tags
         }
     }
 }
@@ -47,16 +75,24 @@ impl TryFrom<&DbPinpoint> for Pinpoint {
     type Error = String;
     fn try_from(value: &DbPinpoint) -> Result<Self, Self::Error> {
         let pinpoint_id = value.pinpoint_id;
-        let latitude = value.latitude.unwrap_or(0.0);
-        let longitude = value.longitude.unwrap_or(0.0);
+        let latitude = Latitude::parse(value.latitude)?;
+        let longitude = Longitude::parse(value.longitude)?;
         let added_at = value.added_at;
+        let modified_at = value.modified_at;
+        let expires_at = value.expires_at;
         let contents_id = value.contents_id;
         let description = value.description.clone().unwrap_or(String::from(""));
-        let attachment = value.attachment.clone();
         let user_id = value.user_id;
         let username = value.username.clone();
-        Ok(Self { pinpoint_id, latitude, longitude, added_at, contents_id,
-            description, attachment, username, user_id: Some(user_id) })
+        let visibility = PinpointVisibility::parse(&value.visibility)?;
+        let distance = value.distance;
+        let rank = value.rank;
+        let tags = value.tags.iter().flatten()
+            .map(|x| PinpointTag::parse(x))
+            .collect::<Result<Vec<PinpointTag>, String>>()?;
+        Ok(Self { pinpoint_id, latitude, longitude, added_at, modified_at, expires_at, contents_id,
+            // Only the keys are in the row, the attachments are read from blob storage
+            description, attachments: Vec::new(), variants: Vec::new(), username, user_id: Some(user_id), visibility, distance, rank, tags })
     }
 }
 
@@ -0,0 +1,47 @@
+use uuid::Uuid;
+use crate::domain::PinpointVisibility;
+
+// Postgres NOTIFY channel every server instance listens on
+pub const PINPOINT_EVENTS_CHANNEL: &str = "pinpoint_events";
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PinpointEventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl PinpointEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PinpointEventKind::Created => "created",
+            PinpointEventKind::Updated => "updated",
+            PinpointEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+// Sent through NOTIFY whenever a pinpoint is created, edited or deleted.
+// owner and visibility decide who else may be told about it.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct PinpointEvent {
+    pub kind: PinpointEventKind,
+    pub pinpoint_id: Uuid,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub owner: String,
+    pub visibility: PinpointVisibility,
+    // Only for edits, so viewers who could see the pinpoint before are told
+    // when it is taken away from them
+    #[serde(default)]
+    pub previous: Option<PinpointEventPrevious>,
+}
+
+// Where an edited pinpoint was and its visibility before the edit
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PinpointEventPrevious {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub visibility: PinpointVisibility,
+}
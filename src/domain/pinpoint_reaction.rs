@@ -0,0 +1,56 @@
+// The fixed set of reactions a user can leave on a pinpoint.
+// Stored as text in pinpoint_reactions.reaction;
+// emoji() gives the character each one is shown as.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy,
+    PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PinpointReaction {
+    ThumbsUp,
+    Heart,
+    Laugh,
+    Wow,
+    Sad,
+    Fire,
+}
+
+impl PinpointReaction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PinpointReaction::ThumbsUp => "thumbs_up",
+            PinpointReaction::Heart => "heart",
+            PinpointReaction::Laugh => "laugh",
+            PinpointReaction::Wow => "wow",
+            PinpointReaction::Sad => "sad",
+            PinpointReaction::Fire => "fire",
+        }
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            PinpointReaction::ThumbsUp => "\u{1F44D}",
+            PinpointReaction::Heart => "\u{2764}\u{FE0F}",
+            PinpointReaction::Laugh => "\u{1F602}",
+            PinpointReaction::Wow => "\u{1F62E}",
+            PinpointReaction::Sad => "\u{1F622}",
+            PinpointReaction::Fire => "\u{1F525}",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<PinpointReaction, String> {
+        match s {
+            "thumbs_up" => Ok(PinpointReaction::ThumbsUp),
+            "heart" => Ok(PinpointReaction::Heart),
+            "laugh" => Ok(PinpointReaction::Laugh),
+            "wow" => Ok(PinpointReaction::Wow),
+            "sad" => Ok(PinpointReaction::Sad),
+            "fire" => Ok(PinpointReaction::Fire),
+            other => Err(format!("{} is not a valid pinpoint reaction.", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for PinpointReaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
@@ -0,0 +1,95 @@
+// A normalized pinpoint tag: lowercase letters, digits, '-' and '_',
+// with a leading '#' dropped and inner whitespace turned into '-'.
+// "  #Sunset Views " is stored as "sunset-views".
+
+pub const MAX_TAG_LENGTH: usize = 32;
+pub const MAX_TAGS_PER_PINPOINT: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PinpointTag(String);
+
+impl PinpointTag {
+    pub fn parse(s: &str) -> Result<PinpointTag, String> {
+        let trimmed = s.trim();
+        let trimmed = trimmed.strip_prefix('#').unwrap_or(trimmed);
+        let normalized = trimmed.split_whitespace()
+            .collect::<Vec<&str>>()
+            .join("-")
+            .to_lowercase();
+        if normalized.is_empty() {
+            return Err(String::from("A tag cannot be empty."));
+        }
+        if normalized.chars().count() > MAX_TAG_LENGTH {
+            return Err(format!("{} is too long for a tag. \
+            Tags can be at most {} characters.", s, MAX_TAG_LENGTH));
+        }
+        if !normalized.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("{} is not a valid tag. \
+            Tags can only contain letters, digits, '-' and '_'.", s));
+        }
+        Ok(Self(normalized))
+    }
+
+    // Normalizes every tag, dropping duplicates while keeping the first-seen order
+    pub fn parse_set<S: AsRef<str>>(values: &[S]) -> Result<Vec<PinpointTag>, String> {
+        let mut tags: Vec<PinpointTag> = Vec::new();
+        for value in values {
+            let tag = PinpointTag::parse(value.as_ref())?;
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        if tags.len() > MAX_TAGS_PER_PINPOINT {
+            return Err(format!("A pinpoint can have at most {} tags.", MAX_TAGS_PER_PINPOINT));
+        }
+        Ok(tags)
+    }
+}
+
+impl AsRef<str> for PinpointTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for PinpointTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use super::{PinpointTag, MAX_TAGS_PER_PINPOINT, MAX_TAG_LENGTH};
+
+    #[test]
+    fn tags_are_normalized() {
+        let tag = PinpointTag::parse("  #Sunset   Views ").unwrap();
+        assert_eq!(tag.as_ref(), "sunset-views");
+        assert_eq!(PinpointTag::parse("Café").unwrap().as_ref(), "café");
+    }
+
+    #[test]
+    fn empty_and_punctuated_tags_are_rejected() {
+        assert_err!(PinpointTag::parse(""));
+        assert_err!(PinpointTag::parse(" # "));
+        assert_err!(PinpointTag::parse("food!"));
+        assert_err!(PinpointTag::parse("a,b"));
+    }
+
+    #[test]
+    fn tag_length_is_limited() {
+        assert_ok!(PinpointTag::parse(&"a".repeat(MAX_TAG_LENGTH)));
+        assert_err!(PinpointTag::parse(&"a".repeat(MAX_TAG_LENGTH + 1)));
+    }
+
+    #[test]
+    fn tag_sets_drop_duplicates_and_are_limited() {
+        let tags = PinpointTag::parse_set(&["Food", "#food", "sunset"]).unwrap();
+        assert_eq!(tags, vec![PinpointTag::parse("food").unwrap(),
+                              PinpointTag::parse("sunset").unwrap()]);
+        let too_many: Vec<String> = (0..=MAX_TAGS_PER_PINPOINT).map(|x| x.to_string()).collect();
+        assert_err!(PinpointTag::parse_set(&too_many));
+    }
+}
@@ -0,0 +1,36 @@
+// Who besides its owner may see a pinpoint.
+// Stored as text in pinpoints.visibility.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PinpointVisibility {
+    #[default]
+    Public,
+    // Only users in user_follows following the owner
+    Followers,
+    Private,
+}
+
+impl PinpointVisibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PinpointVisibility::Public => "public",
+            PinpointVisibility::Followers => "followers",
+            PinpointVisibility::Private => "private",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<PinpointVisibility, String> {
+        match s {
+            "public" => Ok(PinpointVisibility::Public),
+            "followers" => Ok(PinpointVisibility::Followers),
+            "private" => Ok(PinpointVisibility::Private),
+            other => Err(format!("{} is not a valid pinpoint visibility.", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for PinpointVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
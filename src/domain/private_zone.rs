@@ -0,0 +1,109 @@
+use uuid::Uuid;
+use crate::domain::{Latitude, Longitude};
+
+// A place such as home or work, given as a centre and a radius.
+// Other users see the owner's pinpoints inside it at a fixed point
+// within the radius of the centre, or do not see them at all.
+
+pub const MAX_PRIVATE_ZONE_RADIUS_METERS: f64 = 50_000.0;
+pub const MAX_PRIVATE_ZONE_NAME_LENGTH: usize = 64;
+pub const MAX_PRIVATE_ZONES_PER_USER: i64 = 20;
+
+// Stored as text in user_private_zones.mode
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PrivateZoneMode {
+    // Pinpoints are shown at the zone's public location, never its centre
+    #[default]
+    Mask,
+    // Pinpoints are left out for other users
+    Hide,
+}
+
+impl PrivateZoneMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrivateZoneMode::Mask => "mask",
+            PrivateZoneMode::Hide => "hide",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<PrivateZoneMode, String> {
+        match s {
+            "mask" => Ok(PrivateZoneMode::Mask),
+            "hide" => Ok(PrivateZoneMode::Hide),
+            other => Err(format!("{} is not a valid private zone mode.", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for PrivateZoneMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrivateZone {
+    pub zone_id: Uuid,
+    pub name: String,
+    pub latitude: Latitude,
+    pub longitude: Longitude,
+    pub radius_meters: f64,
+    pub mode: PrivateZoneMode,
+}
+
+impl PrivateZone {
+    pub fn parse(
+        name: &str,
+        latitude: f64,
+        longitude: f64,
+        radius_meters: f64,
+        mode: PrivateZoneMode,
+    ) -> Result<PrivateZone, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(String::from("A private zone needs a name."));
+        }
+        if name.chars().count() > MAX_PRIVATE_ZONE_NAME_LENGTH {
+            return Err(format!("A private zone name can be at most {} characters.",
+                               MAX_PRIVATE_ZONE_NAME_LENGTH));
+        }
+        if !radius_meters.is_finite() || radius_meters <= 0.0
+            || radius_meters > MAX_PRIVATE_ZONE_RADIUS_METERS {
+            return Err(format!("The radius must be more than 0 and at most {} meters.",
+                               MAX_PRIVATE_ZONE_RADIUS_METERS));
+        }
+        Ok(Self {
+            zone_id: Uuid::new_v4(),
+            name: name.to_string(),
+            latitude: Latitude::parse(latitude)?,
+            longitude: Longitude::parse(longitude)?,
+            radius_meters,
+            mode,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use super::{PrivateZone, PrivateZoneMode, MAX_PRIVATE_ZONE_NAME_LENGTH,
+                MAX_PRIVATE_ZONE_RADIUS_METERS};
+
+    #[test]
+    fn zones_are_validated() {
+        let zone = PrivateZone::parse(" Home ", 51.5, -0.12, 200.0, PrivateZoneMode::Hide).unwrap();
+        assert_eq!(zone.name, "Home");
+        assert_ok!(PrivateZone::parse("Work", 51.5, -0.12,
+                                      MAX_PRIVATE_ZONE_RADIUS_METERS, PrivateZoneMode::Mask));
+        assert_err!(PrivateZone::parse(" ", 51.5, -0.12, 200.0, PrivateZoneMode::Mask));
+        assert_err!(PrivateZone::parse(&"a".repeat(MAX_PRIVATE_ZONE_NAME_LENGTH + 1),
+                                       51.5, -0.12, 200.0, PrivateZoneMode::Mask));
+        assert_err!(PrivateZone::parse("Home", 91.0, -0.12, 200.0, PrivateZoneMode::Mask));
+        assert_err!(PrivateZone::parse("Home", 51.5, -0.12, 0.0, PrivateZoneMode::Mask));
+        assert_err!(PrivateZone::parse("Home", 51.5, -0.12, f64::NAN, PrivateZoneMode::Mask));
+        assert_err!(PrivateZone::parse("Home", 51.5, -0.12,
+                                       MAX_PRIVATE_ZONE_RADIUS_METERS + 1.0, PrivateZoneMode::Mask));
+    }
+}
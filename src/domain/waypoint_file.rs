@@ -0,0 +1,193 @@
+use roxmltree::{Document, Node};
+use crate::domain::{Latitude, Longitude};
+
+// Waypoints read from GPX or KML files exported by other apps.
+// Every waypoint is checked on its own, so one broken entry
+// only rejects itself and not the rest of the file.
+
+pub const MAX_WAYPOINTS_PER_FILE: usize = 1000;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WaypointFormat {
+    Gpx,
+    Kml
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Waypoint {
+    pub latitude: Latitude,
+    pub longitude: Longitude,
+    // The waypoint's name and description, one per line
+    pub description: String
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaypointEntry {
+    pub name: Option<String>,
+    pub waypoint: Result<Waypoint, String>
+}
+
+#[derive(Debug)]
+pub struct WaypointFile {
+    pub format: WaypointFormat,
+    // In the order they appear in the file
+    pub entries: Vec<WaypointEntry>
+}
+
+impl WaypointFile {
+    pub fn parse(text: &str) -> Result<WaypointFile, String> {
+        let document = Document::parse(text)
+            .map_err(|e| format!("The file is not valid XML: {}.", e))?;
+        let root = document.root_element();
+        let format = match root.tag_name().name() {
+            "gpx" => WaypointFormat::Gpx,
+            "kml" => WaypointFormat::Kml,
+            _ => return Err(String::from("Only GPX and KML files can be imported."))
+        };
+        let entries: Vec<WaypointEntry> = match format {
+            WaypointFormat::Gpx => root.descendants()
+                .filter(|x| x.tag_name().name() == "wpt")
+                .map(parse_gpx_waypoint)
+                .collect(),
+            WaypointFormat::Kml => root.descendants()
+                .filter(|x| x.tag_name().name() == "Placemark")
+                .map(parse_kml_placemark)
+                .collect()
+        };
+        if entries.is_empty() {
+            return Err(String::from("The file has no waypoints."));
+        }
+        if entries.len() > MAX_WAYPOINTS_PER_FILE {
+            return Err(format!("A file can have at most {} waypoints.", MAX_WAYPOINTS_PER_FILE));
+        }
+        Ok(Self { format, entries })
+    }
+}
+
+// <wpt lat=".." lon=".."><name>..</name><desc>..</desc></wpt>
+fn parse_gpx_waypoint(node: Node) -> WaypointEntry {
+    let name = child_text(node, "name");
+    let description = child_text(node, "desc");
+    let waypoint = match (node.attribute("lat"), node.attribute("lon")) {
+        (Some(lat), Some(lon)) => parse_waypoint(lat, lon, &name, &description),
+        _ => Err(String::from("The waypoint is missing lat or lon."))
+    };
+    WaypointEntry { name, waypoint }
+}
+
+// <Placemark><name>..</name><description>..</description>
+// <Point><coordinates>lon,lat[,alt]</coordinates></Point></Placemark>
+fn parse_kml_placemark(node: Node) -> WaypointEntry {
+    let name = child_text(node, "name");
+    let description = child_text(node, "description");
+    let coordinates = node.descendants()
+        .find(|x| x.tag_name().name() == "Point")
+        .and_then(|x| child_text(x, "coordinates"));
+    let waypoint = match coordinates {
+        None => Err(String::from("Only placemarks with a point can be imported.")),
+        Some(x) => {
+            let mut values = x.split(',').map(str::trim);
+            match (values.next(), values.next()) {
+                (Some(lon), Some(lat)) => parse_waypoint(lat, lon, &name, &description),
+                _ => Err(format!("{} are not valid coordinates.", x))
+            }
+        }
+    };
+    WaypointEntry { name, waypoint }
+}
+
+fn parse_waypoint(
+    latitude: &str,
+    longitude: &str,
+    name: &Option<String>,
+    description: &Option<String>,
+) -> Result<Waypoint, String> {
+    let latitude = latitude.trim().parse::<f64>()
+        .map_err(|_| format!("{} is not a valid latitude.", latitude))?;
+    let longitude = longitude.trim().parse::<f64>()
+        .map_err(|_| format!("{} is not a valid longitude.", longitude))?;
+    let latitude = Latitude::parse(latitude)?;
+    let longitude = Longitude::parse(longitude)?;
+    let description = [name, description].iter()
+        .filter_map(|x| x.as_deref())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    Ok(Waypoint { latitude, longitude, description })
+}
+
+// Text of the first child element with that name, including CDATA sections
+fn child_text(node: Node, name: &str) -> Option<String> {
+    let child = node.children().find(|x| x.tag_name().name() == name)?;
+    let text: String = child.descendants()
+        .filter(|x| x.is_text())
+        .filter_map(|x| x.text())
+        .collect();
+    let text = text.trim();
+    match text.is_empty() {
+        true => None,
+        false => Some(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use super::{WaypointFile, WaypointFormat, MAX_WAYPOINTS_PER_FILE};
+
+    #[test]
+    fn gpx_waypoints_are_parsed_in_order() {
+        let gpx = r#"<?xml version="1.0"?>
+        <gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+            <wpt lat="46.5" lon="7.9"><name>Summit</name><desc>Windy</desc></wpt>
+            <wpt lat="46.4" lon="7.8"><name>Hut</name></wpt>
+            <wpt lat="146.4" lon="7.8"><name>Nowhere</name></wpt>
+            <wpt lon="7.8"/>
+        </gpx>"#;
+        let file = WaypointFile::parse(gpx).unwrap();
+        assert_eq!(file.format, WaypointFormat::Gpx);
+        assert_eq!(file.entries.len(), 4);
+        let summit = file.entries[0].waypoint.as_ref().unwrap();
+        assert_eq!((summit.latitude.value(), summit.longitude.value()), (46.5, 7.9));
+        assert_eq!(summit.description, "Summit\nWindy");
+        assert_eq!(file.entries[1].waypoint.as_ref().unwrap().description, "Hut");
+        assert_eq!(file.entries[2].name, Some(String::from("Nowhere")));
+        assert_err!(&file.entries[2].waypoint);
+        assert_err!(&file.entries[3].waypoint);
+    }
+
+    #[test]
+    fn kml_point_placemarks_are_parsed() {
+        let kml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <kml xmlns="http://www.opengis.net/kml/2.2"><Document>
+            <Placemark>
+                <name>Lake</name>
+                <description><![CDATA[Good <b>swim</b>]]></description>
+                <Point><coordinates> 8.1,46.2,1200 </coordinates></Point>
+            </Placemark>
+            <Placemark>
+                <name>Trail</name>
+                <LineString><coordinates>8.1,46.2 8.2,46.3</coordinates></LineString>
+            </Placemark>
+        </Document></kml>"#;
+        let file = WaypointFile::parse(kml).unwrap();
+        assert_eq!(file.format, WaypointFormat::Kml);
+        let lake = file.entries[0].waypoint.as_ref().unwrap();
+        assert_eq!((lake.latitude.value(), lake.longitude.value()), (46.2, 8.1));
+        assert_eq!(lake.description, "Lake\nGood <b>swim</b>");
+        assert_err!(&file.entries[1].waypoint);
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        assert_err!(WaypointFile::parse("not xml"));
+        assert_err!(WaypointFile::parse("<html><body/></html>"));
+        assert_err!(WaypointFile::parse("<gpx></gpx>"));
+        let too_many = format!("<gpx>{}</gpx>",
+                               r#"<wpt lat="1" lon="1"/>"#.repeat(MAX_WAYPOINTS_PER_FILE + 1));
+        assert_err!(WaypointFile::parse(&too_many));
+        let enough = format!("<gpx>{}</gpx>",
+                             r#"<wpt lat="1" lon="1"/>"#.repeat(MAX_WAYPOINTS_PER_FILE));
+        assert_ok!(WaypointFile::parse(&enough));
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::Utc;
+use sqlx::PgPool;
+use crate::blob_storage::BlobStorage;
+
+// How many queued blobs are deleted per transaction
+const BLOB_DELETION_BATCH: i64 = 100;
+
+// Periodically deletes expired pinpoints and data exports, then the blobs
+// no contents row or data export refers to any more, for as long as the application runs.
+// Failures are logged and retried on the next tick.
+pub async fn run_expiry_worker_until_stopped(
+    pool: PgPool,
+    storage: Arc<dyn BlobStorage>,
+    interval: Duration,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match purge_expired_pinpoints(&pool).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!("Purged {} expired pinpoints", purged),
+            Err(e) => tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to purge expired pinpoints"
+            ),
+        }
+        match purge_expired_exports(&pool).await {
+            Ok(0) => {}
+            Ok(purged) => tracing::info!("Purged {} expired data exports", purged),
+            Err(e) => tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to purge expired data exports"
+            ),
+        }
+        loop {
+            match delete_queued_blobs(&pool, storage.as_ref()).await {
+                Ok(deleted) if deleted < BLOB_DELETION_BATCH as u64 => break,
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to delete queued blobs"
+                    );
+                    break;
+                }
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+// Deletes every pinpoint past its expires_at, then any contents rows
+// no longer referenced by a pinpoint, a user or a comment.
+// Returns how many pinpoints were deleted.
+#[tracing::instrument(name = "purge_expired_pinpoints", skip(pool))]
+pub async fn purge_expired_pinpoints(pool: &PgPool) -> Result<u64, anyhow::Error> {
+    let now = Utc::now();
+    let mut tran = pool.begin().await?;
+    let purged = sqlx::query!(
+        r#"
+        DELETE FROM pinpoints
+        WHERE expires_at <= $1;
+        "#,
+        now
+    )
+        .execute(&mut tran)
+        .await?
+        .rows_affected();
+
+    sqlx::query!(
+        r#"
+        DELETE FROM contents con
+        WHERE NOT EXISTS (
+            SELECT 1 FROM pinpoint_contents pin_con WHERE pin_con.content_id = con.id
+        )
+        AND NOT EXISTS (
+            SELECT 1 FROM user_contents usr_con WHERE usr_con.contents_id = con.id
+        )
+        AND NOT EXISTS (
+            SELECT 1 FROM pinpoint_comments cmt WHERE cmt.content_id = con.id
+        );
+        "#
+    )
+        .execute(&mut tran)
+        .await?;
+    tran.commit().await?;
+    Ok(purged)
+}
+
+// Deletes every finished data export past its expires_at, its archive is queued
+// for deletion from blob storage.
+// Returns how many were deleted.
+#[tracing::instrument(name = "purge_expired_exports", skip(pool))]
+pub async fn purge_expired_exports(pool: &PgPool) -> Result<u64, anyhow::Error> {
+    let purged = sqlx::query!(
+        r#"
+        DELETE FROM data_exports
+        WHERE expires_at <= $1;
+        "#,
+        Utc::now()
+    )
+        .execute(pool)
+        .await?
+        .rows_affected();
+    Ok(purged)
+}
+
+// Deletes the oldest blobs queued in blob_deletions from storage.
+// A blob that fails to delete stays queued for the next tick.
+// Returns how many were deleted.
+#[tracing::instrument(name = "delete_queued_blobs", skip(pool, storage))]
+pub async fn delete_queued_blobs(
+    pool: &PgPool,
+    storage: &dyn BlobStorage,
+) -> Result<u64, anyhow::Error> {
+    let mut tran = pool.begin().await?;
+    let rows = sqlx::query!(
+        r#"
+        SELECT storage_key
+        FROM blob_deletions
+        ORDER BY queued_at
+        FOR UPDATE SKIP LOCKED
+        LIMIT $1;
+        "#,
+        BLOB_DELETION_BATCH
+    )
+        .fetch_all(&mut tran)
+        .await?;
+    let mut deleted: Vec<String> = Vec::new();
+    for row in rows {
+        match storage.delete(&row.storage_key).await {
+            Ok(_) => deleted.push(row.storage_key),
+            Err(e) => tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to delete blob {}", row.storage_key
+            )
+        }
+    }
+    sqlx::query!(
+        r#"
+        DELETE FROM blob_deletions
+        WHERE storage_key = ANY($1);
+        "#,
+        &deleted
+    )
+        .execute(&mut tran)
+        .await?;
+    tran.commit().await?;
+    Ok(deleted.len() as u64)
+}
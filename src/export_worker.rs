@@ -0,0 +1,237 @@
+use std::io::{Cursor, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::anyhow;
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+use crate::blob_storage::{discard_blobs, get_blobs, BlobStorage};
+use crate::domain::database::DbPinpoint;
+use crate::domain::{DataExportStatus, Pinpoint};
+use crate::routes::pinpoints::get::PinpointFeatureCollection;
+use crate::routes::pinpoints::get::get_routing::{add_db_reactions, convert_to_pinpoint_response};
+use crate::routes::users::export::export_routing::EXPORT_ARCHIVE_MIME;
+use crate::routes::users::get::{get_db_user_with_id, UserResponse};
+
+pub enum ExecutionOutcome {
+    ExportCompleted,
+    EmptyQueue,
+}
+
+// Builds queued personal data exports for as long as the application runs.
+// Sleeps only once the queue is empty or a query failed.
+// Finished exports are kept for retention, then the expiry worker deletes them.
+pub async fn run_export_worker_until_stopped(
+    pool: PgPool,
+    storage: Arc<dyn BlobStorage>,
+    interval: Duration,
+    retention: chrono::Duration,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_execute_export(&pool, storage.as_ref(), retention).await {
+            Ok(ExecutionOutcome::EmptyQueue) => tokio::time::sleep(interval).await,
+            Ok(ExecutionOutcome::ExportCompleted) => {}
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to run a data export"
+                );
+                tokio::time::sleep(interval).await;
+            }
+        }
+    }
+}
+
+// Builds the oldest pending export. Its row stays locked until the archive
+// is saved, so other workers skip it instead of building it twice.
+// The archive goes to blob storage under exports/<id>/, a failure to store
+// it leaves the export pending for the next run.
+#[tracing::instrument(name = "try_execute_export", skip(pool, storage))]
+pub async fn try_execute_export(
+    pool: &PgPool,
+    storage: &dyn BlobStorage,
+    retention: chrono::Duration,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut tran = pool.begin().await?;
+    let export = sqlx::query!(
+        r#"
+        SELECT id, user_id
+        FROM data_exports
+        WHERE status = 'pending'
+        ORDER BY requested_at
+        FOR UPDATE SKIP LOCKED
+        LIMIT 1;
+        "#
+    )
+        .fetch_optional(&mut tran)
+        .await?;
+    let export = match export {
+        Some(x) => x,
+        None => return Ok(ExecutionOutcome::EmptyQueue)
+    };
+    let (status, archive_key) = match build_export_archive(pool, storage, export.user_id).await {
+        Ok(x) => {
+            let key = format!("exports/{}/archive.zip", export.id);
+            storage.put(&key, x, EXPORT_ARCHIVE_MIME).await?;
+            (DataExportStatus::Done, Some(key))
+        },
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to build data export {}", export.id
+            );
+            (DataExportStatus::Failed, None)
+        }
+    };
+    let finished_at = Utc::now();
+    let expires_at = finished_at.checked_add_signed(retention)
+        .ok_or(anyhow!("The data export retention is out of range."))?;
+    let updated = sqlx::query!(
+        r#"
+        UPDATE data_exports
+        SET status = $2, archive_key = $3, finished_at = $4, expires_at = $5
+        WHERE id = $1;
+        "#,
+        export.id,
+        status.as_str(),
+        archive_key,
+        finished_at,
+        expires_at
+    )
+        .execute(&mut tran)
+        .await;
+    if let Err(e) = updated {
+        discard_blobs(storage, &archive_key.iter().map(String::as_str).collect::<Vec<&str>>()).await;
+        return Err(e.into());
+    }
+    // A failed commit may still have gone through, so the archive is kept
+    tran.commit().await?;
+    Ok(ExecutionOutcome::ExportCompleted)
+}
+
+// A zip archive holding:
+// profile.json, the user without their password hash and salt,
+// profile/attachment.<ext>, the profile attachment if there is one,
+// pinpoints.geojson, every pinpoint of the user including expired ones,
+// attachments/<pinpoint_id>/<position>.<ext>, the attachments at full size,
+// with their EXIF where the user chose to keep it.
+pub async fn build_export_archive(
+    pool: &PgPool,
+    storage: &dyn BlobStorage,
+    user_id: Uuid,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let user = get_db_user_with_id(pool, user_id).await?
+        .ok_or(anyhow!("The user to export no longer exists."))?;
+    let profile_attachment = match &user.contents_attachment_key {
+        Some(x) => Some(storage.get(x).await?),
+        None => None
+    };
+    let profile = UserResponse {
+        unique_id: Some(user.unique_id),
+        email: Some(user.email),
+        username: Some(user.username.clone()),
+        role_id: Some(user.role_id),
+        role_title: Some(user.role_title),
+        contents_id: user.contents_id,
+        contents_description: user.contents_description,
+        contents_attachment: None
+    };
+
+    let pinpoints = get_db_user_pinpoints(pool, storage, user_id).await?;
+    let mut response_pinpoints = convert_to_pinpoint_response(pinpoints)?;
+    add_db_reactions(pool, &mut response_pinpoints, &user.username).await?;
+    let mut attachments: Vec<(String, Vec<u8>)> = Vec::new();
+    for pinpoint in response_pinpoints.iter_mut() {
+        let pinpoint_id = pinpoint.pinpoint_id.unwrap_or_default();
+        for (position, attachment) in pinpoint.attachments.drain(..).enumerate() {
+            let name = format!("attachments/{}/{}.{}",
+                               pinpoint_id, position, attachment_extension(&attachment));
+            attachments.push((name, attachment));
+        }
+        // The files are in the archive, the GeoJSON would only repeat them
+        pinpoint.attachment = Vec::new();
+    }
+    let collection = PinpointFeatureCollection::from(response_pinpoints);
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default();
+    zip.start_file("profile.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&profile)?)?;
+    if let Some(x) = profile_attachment {
+        zip.start_file(format!("profile/attachment.{}", attachment_extension(&x)), options)?;
+        zip.write_all(&x)?;
+    }
+    zip.start_file("pinpoints.geojson", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&collection)?)?;
+    for (name, attachment) in attachments {
+        zip.start_file(name, options)?;
+        zip.write_all(&attachment)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+// Known image formats keep their usual extension
+fn attachment_extension(attachment: &[u8]) -> &'static str {
+    match image::guess_format(attachment) {
+        Ok(x) => x.extensions_str().first().copied().unwrap_or("bin"),
+        Err(_) => "bin"
+    }
+}
+
+// Every pinpoint of the user, whatever its visibility or expiry
+pub async fn get_db_user_pinpoints(
+    pool: &PgPool,
+    storage: &dyn BlobStorage,
+    user_id: Uuid,
+) -> Result<Vec<Pinpoint>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        DbPinpoint,
+       r#"SELECT pin.id AS pinpoint_id, pin.latitude AS latitude, pin.longitude as longitude,
+        pin.added_at AS added_at,
+        pin.modified_at AS modified_at,
+        pin.expires_at AS expires_at,
+        con.id AS contents_id,
+        con.description AS description,
+        ARRAY(
+            SELECT COALESCE(att.attachment_with_exif_key, att.attachment_key) FROM pinpoint_contents pin_att
+            INNER JOIN contents att ON att.id = pin_att.content_id
+            WHERE pin_att.pinpoint_id = pin.id AND att.attachment_key IS NOT NULL
+            ORDER BY pin_att.position
+        ) AS attachment_keys,
+        usr.id AS user_id,
+        usr.username AS username,
+        pin.visibility AS visibility,
+        NULL::DOUBLE PRECISION AS distance,
+        NULL::DOUBLE PRECISION AS rank,
+        ARRAY(
+            SELECT tag.name FROM pinpoint_tags pin_tag
+            INNER JOIN tags tag ON tag.id = pin_tag.tag_id
+            WHERE pin_tag.pinpoint_id = pin.id
+            ORDER BY tag.name
+        ) AS tags
+        FROM pinpoints pin
+        INNER JOIN pinpoint_contents pin_con on pin_con.pinpoint_id = pin.id AND pin_con.position = 0
+        INNER JOIN contents con ON con.id = pin_con.content_id
+        INNER JOIN user_pinpoints usr_pin ON usr_pin.pinpoint_id = pin.id
+        INNER JOIN users usr ON usr_pin.user_id = usr.id
+        WHERE usr.id = $1
+        ORDER BY pin.added_at, pin.id "#
+        , user_id).fetch_all(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            anyhow!("Failed to perform a query to retrieve pinpoints.")
+        })?;
+    let mut results: Vec<Pinpoint> = Vec::new();
+    for row in rows.iter() {
+        let mut pinpoint = Pinpoint::try_from(row)
+            .map_err(|_| anyhow!("Conversion failure"))?;
+        pinpoint.attachments = get_blobs(storage, row.attachment_keys.as_deref().unwrap_or_default()).await?;
+        results.push(pinpoint);
+    }
+    Ok(results)
+}
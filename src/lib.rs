@@ -5,3 +5,7 @@ pub mod telemetry;
 pub mod utils;
 pub mod routes;
 pub mod authentication;
+pub mod expiry_worker;
+pub mod export_worker;
+pub mod pinpoint_events;
+pub mod blob_storage;
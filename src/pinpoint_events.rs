@@ -0,0 +1,69 @@
+use std::time::Duration;
+use sqlx::postgres::PgListener;
+use sqlx::{Executor, PgPool, Postgres};
+use tokio::sync::broadcast;
+use crate::domain::PinpointEvent;
+use crate::domain::pinpoint_event::PINPOINT_EVENTS_CHANNEL;
+
+// How many events a slow subscriber may fall behind before it skips ahead
+pub const PINPOINT_EVENT_BUFFER: usize = 1024;
+
+// Shared with the handlers so each stream can subscribe
+pub struct PinpointEvents(pub broadcast::Sender<PinpointEvent>);
+
+// Publishes the event to every server instance listening on PINPOINT_EVENTS_CHANNEL.
+// Inside a transaction it is only delivered once the transaction commits.
+pub async fn notify_pinpoint_event<'c, E>(
+    executor: E,
+    event: &PinpointEvent,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c, Database = Postgres>,
+{
+    let payload = serde_json::to_string(event)
+        .expect("Failed to serialize a pinpoint event.");
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(PINPOINT_EVENTS_CHANNEL)
+        .bind(payload)
+        .execute(executor)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(())
+}
+
+// Forwards pinpoint events from Postgres to the local subscribers
+// for as long as the application runs, reconnecting after failures.
+pub async fn run_pinpoint_event_listener_until_stopped(
+    pool: PgPool,
+    sender: broadcast::Sender<PinpointEvent>,
+) -> Result<(), anyhow::Error> {
+    loop {
+        if let Err(e) = listen_for_pinpoint_events(&pool, &sender).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Stopped listening for pinpoint events"
+            );
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+async fn listen_for_pinpoint_events(
+    pool: &PgPool,
+    sender: &broadcast::Sender<PinpointEvent>,
+) -> Result<(), anyhow::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(PINPOINT_EVENTS_CHANNEL).await?;
+    loop {
+        let notification = listener.recv().await?;
+        match serde_json::from_str::<PinpointEvent>(notification.payload()) {
+            // Nobody streaming right now is not an error
+            Ok(event) => { let _ = sender.send(event); },
+            Err(e) => tracing::error!("Failed to parse a pinpoint event: {:?}", e)
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::domain::database::DbPinpointComment;
+
+// Keyset position of the last comment on a page, oldest comments come first.
+// Clients only ever see it as an opaque base64 string.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct CommentCursor {
+    pub added_at: DateTime<Utc>,
+    pub comment_id: Uuid,
+}
+
+impl CommentCursor {
+    pub fn after(comment: &DbPinpointComment) -> Self {
+        Self {
+            added_at: comment.added_at,
+            comment_id: comment.comment_id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("Failed to serialize a comment cursor.");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(s: &str) -> Result<Self, String> {
+        let bytes = URL_SAFE_NO_PAD.decode(s)
+            .map_err(|_| String::from("The cursor is not valid."))?;
+        serde_json::from_slice(&bytes)
+            .map_err(|_| String::from("The cursor is not valid."))
+    }
+}
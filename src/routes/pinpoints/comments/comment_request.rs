@@ -0,0 +1,46 @@
+use uuid::Uuid;
+
+// Default and largest page sizes for comment listings
+pub const DEFAULT_COMMENT_LIMIT: i64 = 50;
+pub const MAX_COMMENT_LIMIT: i64 = 200;
+// Longest comment body accepted, in characters
+pub const MAX_COMMENT_LENGTH: usize = 2000;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct GetCommentsRequest {
+    // Lists the replies to this comment instead of the top-level comments
+    pub parent_id: Option<Uuid>,
+    // Page size, defaulting to DEFAULT_COMMENT_LIMIT
+    pub limit: Option<i64>,
+    // Opaque cursor handed back in the X-Next-Cursor header of the previous page
+    pub cursor: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct PostCommentRequest {
+    pub body: String,
+    // Makes the comment a reply to a top-level comment
+    pub parent_id: Option<Uuid>,
+}
+
+impl PostCommentRequest {
+    pub fn new(body: String, parent_id: Option<Uuid>) -> Self {
+        Self { body, parent_id }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct PutCommentRequest {
+    pub body: String,
+}
+
+// Comment bodies must have some text and fit in MAX_COMMENT_LENGTH
+pub fn validate_comment_body(body: &str) -> Result<(), String> {
+    if body.trim().is_empty() {
+        return Err(String::from("A comment cannot be empty."));
+    }
+    if body.chars().count() > MAX_COMMENT_LENGTH {
+        return Err(format!("A comment cannot be longer than {} characters.", MAX_COMMENT_LENGTH));
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use chrono::serde::{ts_seconds, ts_seconds_option};
+use uuid::Uuid;
+use crate::domain::database::DbPinpointComment;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct GetCommentResponse {
+    pub comment_id: Uuid,
+    pub pinpoint_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub username: String,
+    pub body: String,
+    #[serde(with = "ts_seconds")]
+    pub added_at: DateTime<Utc>,
+    #[serde(with = "ts_seconds_option")]
+    pub modified_at: Option<DateTime<Utc>>,
+    // Always 0 for replies, as replies cannot be replied to
+    pub reply_count: i64,
+}
+
+impl From<&DbPinpointComment> for GetCommentResponse {
+    fn from(value: &DbPinpointComment) -> Self {
+        Self {
+            comment_id: value.comment_id,
+            pinpoint_id: value.pinpoint_id,
+            parent_id: value.parent_id,
+            username: value.username.clone(),
+            body: value.body.clone().unwrap_or_default(),
+            added_at: value.added_at,
+            modified_at: value.modified_at,
+            reply_count: value.reply_count.unwrap_or(0),
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct PostCommentResponse {
+    pub comment_id: Uuid,
+}
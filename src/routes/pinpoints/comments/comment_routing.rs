@@ -0,0 +1,346 @@
+use actix_web::{delete, get, HttpMessage, HttpRequest, HttpResponse, post, put, web};
+use actix_web::http::header::ContentType;
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::authentication::AuthPermissions;
+use crate::domain::database::DbPinpointComment;
+use crate::routes::pinpoints::comments::comment_cursor::CommentCursor;
+use crate::routes::pinpoints::comments::comment_request::{
+    GetCommentsRequest, PostCommentRequest, PutCommentRequest, validate_comment_body,
+    DEFAULT_COMMENT_LIMIT, MAX_COMMENT_LIMIT};
+use crate::routes::pinpoints::comments::comment_response::{GetCommentResponse, PostCommentResponse};
+use crate::routes::pinpoints::get::get_routing::is_db_pinpoint_visible;
+use crate::routes::pinpoints::get::NEXT_CURSOR_HEADER;
+
+#[tracing::instrument(
+name = "handle_get_comments",
+skip(pool, path, args)
+)]
+#[get("/{pinpoint_id}/comments")]
+pub async fn handle_get_comments(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    args: web::Query<GetCommentsRequest>
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let viewer = &auth_permissions.username;
+    let pinpoint_id = path.into_inner();
+    let limit = args.limit.unwrap_or(DEFAULT_COMMENT_LIMIT);
+    if !(1..=MAX_COMMENT_LIMIT).contains(&limit) {
+        return HttpResponse::BadRequest().body(
+            format!("The limit must be between 1 and {}.", MAX_COMMENT_LIMIT));
+    }
+    let cursor = match args.cursor.as_deref().map(CommentCursor::decode).transpose() {
+        Ok(x) => x,
+        Err(e) => return HttpResponse::BadRequest().body(e)
+    };
+    match is_db_pinpoint_visible(&pool, pinpoint_id, viewer).await {
+        Ok(true) => {},
+        Ok(false) => return HttpResponse::NotFound().finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+
+    let mut comments = match get_db_comments(
+        &pool, pinpoint_id, args.parent_id, cursor.as_ref(), limit + 1).await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    // One extra row was fetched to learn whether another page exists
+    let next_cursor = if comments.len() as i64 > limit {
+        comments.truncate(limit as usize);
+        comments.last().map(|x| CommentCursor::after(x).encode())
+    } else {
+        None
+    };
+    let response_comments: Vec<GetCommentResponse> = comments.iter()
+        .map(GetCommentResponse::from)
+        .collect();
+    let json = serde_json::to_string(&response_comments).unwrap();
+
+    let mut response = HttpResponse::Ok();
+    if let Some(x) = next_cursor {
+        response.insert_header((NEXT_CURSOR_HEADER, x));
+    }
+    response
+        .content_type(ContentType::json())
+        .body(json)
+}
+
+#[tracing::instrument(
+name = "handle_add_comment",
+skip(pool, path, args)
+)]
+#[post("/{pinpoint_id}/comments")]
+pub async fn handle_add_comment(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    args: web::Json<PostCommentRequest>
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let username = &auth_permissions.username;
+    let pinpoint_id = path.into_inner();
+    if let Err(e) = validate_comment_body(&args.body) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    match is_db_pinpoint_visible(&pool, pinpoint_id, username).await {
+        Ok(true) => {},
+        Ok(false) => return HttpResponse::NotFound().finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    // Only top-level comments on the same pinpoint can be replied to
+    if let Some(parent_id) = args.parent_id {
+        match get_db_comment_parent(&pool, parent_id).await {
+            Ok(Some((parent_pinpoint_id, None))) if parent_pinpoint_id == pinpoint_id => {},
+            Ok(Some((parent_pinpoint_id, Some(_)))) if parent_pinpoint_id == pinpoint_id =>
+                return HttpResponse::BadRequest().body("Replies cannot be replied to."),
+            Ok(_) => return HttpResponse::BadRequest().body(
+                "The parent comment does not exist on this pinpoint."),
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        }
+    }
+
+    let comment_id = Uuid::new_v4();
+    match insert_db_comment(&pool, comment_id, pinpoint_id, username, &args.0).await {
+        Ok(_) => {
+            let json = serde_json::to_string(&PostCommentResponse { comment_id }).unwrap();
+            HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .body(json)
+        },
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[tracing::instrument(
+name = "handle_put_comment",
+skip(pool, path, args)
+)]
+#[put("/{pinpoint_id}/comments/{comment_id}")]
+pub async fn handle_put_comment(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    args: web::Json<PutCommentRequest>
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let username = &auth_permissions.username;
+    let (pinpoint_id, comment_id) = path.into_inner();
+    if let Err(e) = validate_comment_body(&args.body) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    match get_db_comment_owner(&pool, pinpoint_id, comment_id).await {
+        Ok(Some(x)) if &x == username => {},
+        Ok(Some(_)) => return HttpResponse::Unauthorized().finish(),
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    match modify_db_comment(&pool, comment_id, &args.body).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[tracing::instrument(
+name = "handle_delete_comment",
+skip(pool, path)
+)]
+#[delete("/{pinpoint_id}/comments/{comment_id}")]
+pub async fn handle_delete_comment(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let username = &auth_permissions.username;
+    let (pinpoint_id, comment_id) = path.into_inner();
+    match get_db_comment_owner(&pool, pinpoint_id, comment_id).await {
+        Ok(Some(x)) if &x == username => {},
+        Ok(Some(_)) => return HttpResponse::Unauthorized().finish(),
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    match delete_db_comment(&pool, comment_id).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+// Top-level comments when parent_id is None, otherwise the replies to parent_id.
+// Oldest first, continuing after the cursor if one is given.
+pub async fn get_db_comments(
+    pool: &PgPool,
+    pinpoint_id: Uuid,
+    parent_id: Option<Uuid>,
+    cursor: Option<&CommentCursor>,
+    limit: i64,
+) -> Result<Vec<DbPinpointComment>, sqlx::Error> {
+    sqlx::query_as!(
+        DbPinpointComment,
+        r#"
+        SELECT cmt.id AS comment_id,
+        cmt.pinpoint_id AS pinpoint_id,
+        cmt.parent_id AS parent_id,
+        usr.username AS username,
+        con.description AS body,
+        cmt.added_at AS added_at,
+        cmt.modified_at AS modified_at,
+        (SELECT COUNT(*) FROM pinpoint_comments rpl WHERE rpl.parent_id = cmt.id) AS reply_count
+        FROM pinpoint_comments cmt
+        INNER JOIN users usr ON usr.id = cmt.user_id
+        INNER JOIN contents con ON con.id = cmt.content_id
+        WHERE cmt.pinpoint_id = $1
+        AND cmt.parent_id IS NOT DISTINCT FROM $2
+        AND ($3::UUID IS NULL OR (cmt.added_at, cmt.id) > ($4::TIMESTAMPTZ, $3))
+        ORDER BY cmt.added_at ASC, cmt.id ASC
+        LIMIT $5;
+        "#,
+        pinpoint_id,
+        parent_id,
+        cursor.map(|x| x.comment_id),
+        cursor.map(|x| x.added_at),
+        limit
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })
+}
+
+// The pinpoint a comment belongs to and its own parent, if the comment exists
+pub async fn get_db_comment_parent(
+    pool: &PgPool,
+    comment_id: Uuid,
+) -> Result<Option<(Uuid, Option<Uuid>)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT pinpoint_id, parent_id
+        FROM pinpoint_comments
+        WHERE id = $1;
+        "#,
+        comment_id
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(row.map(|x| (x.pinpoint_id, x.parent_id)))
+}
+
+// Username of the user who wrote the comment, if it exists on the pinpoint
+pub async fn get_db_comment_owner(
+    pool: &PgPool,
+    pinpoint_id: Uuid,
+    comment_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT usr.username
+        FROM pinpoint_comments cmt
+        INNER JOIN users usr ON usr.id = cmt.user_id
+        WHERE cmt.id = $1 AND cmt.pinpoint_id = $2;
+        "#,
+        comment_id,
+        pinpoint_id
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(row.map(|x| x.username))
+}
+
+pub async fn insert_db_comment(
+    pool: &PgPool,
+    comment_id: Uuid,
+    pinpoint_id: Uuid,
+    username: &str,
+    args: &PostCommentRequest,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        WITH con AS (
+            INSERT INTO contents (id, description)
+            VALUES ($2, $3)
+            RETURNING id
+        )
+        INSERT INTO pinpoint_comments (id, pinpoint_id, user_id, parent_id, content_id)
+        SELECT $1, $4, usr.id, $5, con.id
+        FROM con, users usr
+        WHERE usr.username = $6;
+        "#,
+        comment_id,
+        Uuid::new_v4(),
+        args.body,
+        pinpoint_id,
+        args.parent_id,
+        username
+    )
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(())
+}
+
+pub async fn modify_db_comment(
+    pool: &PgPool,
+    comment_id: Uuid,
+    body: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        WITH cmt AS (
+            UPDATE pinpoint_comments
+            SET modified_at = clock_timestamp()
+            WHERE id = $1
+            RETURNING content_id
+        )
+        UPDATE contents
+        SET description = $2
+        WHERE id IN (SELECT content_id FROM cmt);
+        "#,
+        comment_id,
+        body
+    )
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(())
+}
+
+// Deleting the contents rows cascades to the comment and its replies
+pub async fn delete_db_comment(
+    pool: &PgPool,
+    comment_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM contents
+        WHERE id IN (
+            SELECT content_id FROM pinpoint_comments
+            WHERE id = $1 OR parent_id = $1
+        );
+        "#,
+        comment_id
+    )
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(())
+}
@@ -0,0 +1,10 @@
+pub mod comment_routing;
+mod comment_request;
+mod comment_response;
+mod comment_cursor;
+
+pub use comment_request::{GetCommentsRequest, PostCommentRequest, PutCommentRequest};
+pub use comment_response::{GetCommentResponse, PostCommentResponse};
+pub use comment_cursor::CommentCursor;
+pub use comment_routing::{handle_get_comments, handle_add_comment, handle_put_comment,
+                          handle_delete_comment};
@@ -2,6 +2,8 @@ use uuid::Uuid;
 use actix_web::{HttpResponse, web};
 use sqlx::{PgPool, Postgres, Transaction};
 use crate::authentication::{AuthParameters, AuthService};
+use crate::domain::{PinpointEvent, PinpointEventKind, PinpointVisibility};
+use crate::pinpoint_events::notify_pinpoint_event;
 use crate::routes::pinpoints::delete::delete_pinpoint_request::DeletePinpointRequest;
 
 #[tracing::instrument(
@@ -70,6 +72,7 @@ pub async fn handle_delete_pinpoints(
     }
 }
 
+// Live streams are told about the deletion once the transaction commits
 pub async fn delete_db_pinpoint(
     tran: &mut Transaction<'_, Postgres>,
     pinpoint_id: Uuid,
@@ -90,14 +93,18 @@ pub async fn delete_db_pinpoint(
             // if the function failed, returning a sqlx::Error
         })?;
 
-    sqlx::query!(
+    let row = sqlx::query!(
         r#"
         DELETE FROM pinpoints
-        WHERE id = $1;
+        WHERE id = $1
+        RETURNING latitude, longitude, visibility,
+        (SELECT usr.username FROM user_pinpoints usr_pin
+            INNER JOIN users usr ON usr.id = usr_pin.user_id
+            WHERE usr_pin.pinpoint_id = $1) AS owner;
         "#
         , pinpoint_id
     )
-        .execute(&mut *tran)
+        .fetch_optional(&mut *tran)
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -106,6 +113,19 @@ pub async fn delete_db_pinpoint(
             // if the function failed, returning a sqlx::Error
         })?;
 
+    if let Some(row) = row {
+        let visibility = PinpointVisibility::parse(&row.visibility)
+            .map_err(|e| sqlx::Error::Decode(e.into()))?;
+        notify_pinpoint_event(&mut *tran, &PinpointEvent {
+            kind: PinpointEventKind::Deleted,
+            pinpoint_id,
+            latitude: row.latitude,
+            longitude: row.longitude,
+            owner: row.owner.unwrap_or_default(),
+            visibility,
+            previous: None,
+        }).await?;
+    }
     Ok(())
 }
 
@@ -113,7 +133,7 @@ pub async fn delete_user_db_pinpoints(
     tran: &mut Transaction<'_, Postgres>,
     username: &String
 ) -> Result<(), sqlx::Error> {
-    sqlx::query!(
+    let rows = sqlx::query!(
         r#"
         WITH usr_pin(pinpoint_id) AS
         (
@@ -122,11 +142,12 @@ pub async fn delete_user_db_pinpoints(
             WHERE user_id IN (SELECT id FROM users WHERE username = $1)
         )
         DELETE FROM pinpoints
-        WHERE id IN (SELECT pinpoint_id FROM usr_pin);
+        WHERE id IN (SELECT pinpoint_id FROM usr_pin)
+        RETURNING id, latitude, longitude, visibility;
         "#
         , username
     )
-        .execute(tran)
+        .fetch_all(&mut *tran)
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -135,5 +156,18 @@ pub async fn delete_user_db_pinpoints(
             // if the function failed, returning a sqlx::Error
         })?;
 
+    for row in rows {
+        let visibility = PinpointVisibility::parse(&row.visibility)
+            .map_err(|e| sqlx::Error::Decode(e.into()))?;
+        notify_pinpoint_event(&mut *tran, &PinpointEvent {
+            kind: PinpointEventKind::Deleted,
+            pinpoint_id: row.id,
+            latitude: row.latitude,
+            longitude: row.longitude,
+            owner: username.clone(),
+            visibility,
+            previous: None,
+        }).await?;
+    }
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,32 @@
+use uuid::Uuid;
+use crate::domain::database::DbPinpointCluster;
+use crate::domain::LocationPrecision;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct GetClusterResponse {
+    // Centroid of the pinpoints in the cluster
+    pub latitude: f64,
+    pub longitude: f64,
+    pub count: i64,
+    // The most recent pinpoints in the cluster, newest first
+    pub sample_pinpoint_ids: Vec<Uuid>,
+}
+
+impl GetClusterResponse {
+    // The centroid snapped like other users' pinpoint coordinates
+    pub fn obscured(self, precision: LocationPrecision) -> Self {
+        let (latitude, longitude) = precision.obscure(self.latitude, self.longitude);
+        Self { latitude, longitude, ..self }
+    }
+}
+
+impl From<&DbPinpointCluster> for GetClusterResponse {
+    fn from(value: &DbPinpointCluster) -> Self {
+        Self {
+            latitude: value.latitude.unwrap_or(0.0),
+            longitude: value.longitude.unwrap_or(0.0),
+            count: value.count.unwrap_or(0),
+            sample_pinpoint_ids: value.sample_pinpoint_ids.clone().unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::{Display, Formatter};
+use crate::domain::{Latitude, Longitude};
+
+// Bounding box of the visible map plus the map's zoom level.
+// A min_longitude greater than max_longitude means the box
+// crosses the antimeridian.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct GetClustersRequest {
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64,
+    pub zoom: u8
+}
+
+impl GetClustersRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        Latitude::parse(self.min_latitude)?;
+        Latitude::parse(self.max_latitude)?;
+        Longitude::parse(self.min_longitude)?;
+        Longitude::parse(self.max_longitude)?;
+        if self.min_latitude > self.max_latitude {
+            return Err(String::from("min_latitude must not be greater than max_latitude."));
+        }
+        if self.zoom > MAX_CLUSTER_ZOOM {
+            return Err(format!("The zoom level must be between 0 and {}.", MAX_CLUSTER_ZOOM));
+        }
+        Ok(())
+    }
+
+    // Width and height in degrees of the grid cells pinpoints are grouped by.
+    // Each web map tile at this zoom level is split into
+    // CLUSTER_CELLS_PER_TILE cells along each axis.
+    pub fn cell_size(&self) -> f64 {
+        360.0 / 2f64.powi(self.zoom as i32) / CLUSTER_CELLS_PER_TILE
+    }
+}
+
+pub const MAX_CLUSTER_ZOOM: u8 = 22;
+pub const CLUSTER_CELLS_PER_TILE: f64 = 4.0;
+
+impl Display for GetClustersRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GetClustersRequest(lat is {} to {}, lng is {} to {}, zoom is {}).",
+               self.min_latitude, self.max_latitude,
+               self.min_longitude, self.max_longitude, self.zoom)
+    }
+}
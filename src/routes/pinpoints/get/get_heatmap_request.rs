@@ -0,0 +1,82 @@
+use std::fmt::{Display, Formatter};
+use chrono::{DateTime, Utc};
+use chrono::serde::ts_seconds_option;
+use crate::domain::{Latitude, Longitude, PinpointTag};
+
+// Most grid cells a single heatmap may cover
+pub const MAX_HEATMAP_CELLS: f64 = 65_536.0;
+
+// Bounding box to grid, the same way as GetClustersRequest,
+// with square cells cell_size degrees wide.
+// Only pinpoints added within [added_after, added_before) are counted when given.
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct GetHeatmapRequest {
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64,
+    pub cell_size: f64,
+    #[serde(default, with = "ts_seconds_option")]
+    pub added_after: Option<DateTime<Utc>>,
+    #[serde(default, with = "ts_seconds_option")]
+    pub added_before: Option<DateTime<Utc>>,
+    pub tag: Option<String>
+}
+
+impl GetHeatmapRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        Latitude::parse(self.min_latitude)?;
+        Latitude::parse(self.max_latitude)?;
+        Longitude::parse(self.min_longitude)?;
+        Longitude::parse(self.max_longitude)?;
+        if self.min_latitude > self.max_latitude {
+            return Err(String::from("min_latitude must not be greater than max_latitude."));
+        }
+        if !self.cell_size.is_finite() || self.cell_size <= 0.0 {
+            return Err(String::from("The cell size must be a positive number of degrees."));
+        }
+        let cells = (self.latitude_span() / self.cell_size).ceil().max(1.0)
+            * (self.longitude_span() / self.cell_size).ceil().max(1.0);
+        if cells > MAX_HEATMAP_CELLS {
+            return Err(format!("The heatmap would have more than {} cells. \
+            Use a larger cell size or a smaller bounding box.", MAX_HEATMAP_CELLS));
+        }
+        if let (Some(after), Some(before)) = (self.added_after, self.added_before) {
+            if after >= before {
+                return Err(String::from("added_after must be earlier than added_before."));
+            }
+        }
+        self.parse_tag()?;
+        Ok(())
+    }
+
+    pub fn parse_tag(&self) -> Result<Option<PinpointTag>, String> {
+        self.tag.as_deref().map(PinpointTag::parse).transpose()
+    }
+
+    fn latitude_span(&self) -> f64 {
+        self.max_latitude - self.min_latitude
+    }
+
+    // Boxes crossing the antimeridian wrap around
+    fn longitude_span(&self) -> f64 {
+        if self.min_longitude <= self.max_longitude {
+            self.max_longitude - self.min_longitude
+        }
+        else {
+            360.0 - (self.min_longitude - self.max_longitude)
+        }
+    }
+}
+
+impl Display for GetHeatmapRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let tag = match &self.tag {
+            None => String::from("NONE"),
+            Some(x) => x.to_string()
+        };
+        write!(f, "GetHeatmapRequest(lat is {} to {}, lng is {} to {}, cell_size is {}, tag is {}).",
+               self.min_latitude, self.max_latitude,
+               self.min_longitude, self.max_longitude, self.cell_size, tag)
+    }
+}
@@ -0,0 +1,7 @@
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct GetHeatmapCell {
+    // Center of the grid cell
+    pub latitude: f64,
+    pub longitude: f64,
+    pub count: i64,
+}
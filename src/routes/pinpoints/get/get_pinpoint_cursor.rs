@@ -0,0 +1,74 @@
+use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::domain::Pinpoint;
+use crate::routes::pinpoints::get::get_pinpoint_request::PinpointSort;
+
+pub const NEXT_CURSOR_HEADER: &str = "X-Next-Cursor";
+
+// Keyset position of the last pinpoint on a page.
+// Clients only ever see it as an opaque base64 string. Distances to other
+// users' pinpoints are measured from where they are shown, so it gives away
+// nothing the page itself does not.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct PinpointCursor {
+    pub sort: PinpointSort,
+    pub added_at: DateTime<Utc>,
+    pub distance: Option<f64>,
+    #[serde(default)]
+    pub rank: Option<f64>,
+    pub pinpoint_id: Uuid,
+}
+
+impl PinpointCursor {
+    pub fn after(pinpoint: &Pinpoint, sort: PinpointSort) -> Self {
+        Self {
+            sort,
+            added_at: pinpoint.added_at,
+            distance: pinpoint.distance,
+            rank: pinpoint.rank,
+            pinpoint_id: pinpoint.pinpoint_id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("Failed to serialize a pinpoint cursor.");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub fn decode(s: &str) -> Result<Self, String> {
+        let bytes = URL_SAFE_NO_PAD.decode(s)
+            .map_err(|_| String::from("The cursor is not valid."))?;
+        serde_json::from_slice(&bytes)
+            .map_err(|_| String::from("The cursor is not valid."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use claim::assert_err;
+    use uuid::Uuid;
+    use super::PinpointCursor;
+    use crate::routes::pinpoints::get::PinpointSort;
+
+    #[test]
+    fn cursor_survives_a_round_trip() {
+        let cursor = PinpointCursor {
+            sort: PinpointSort::Nearest,
+            added_at: Utc::now(),
+            distance: Some(1234.5),
+            rank: None,
+            pinpoint_id: Uuid::new_v4(),
+        };
+        let decoded = PinpointCursor::decode(&cursor.encode()).unwrap();
+        assert_eq!(cursor, decoded);
+    }
+
+    #[test]
+    fn garbage_cursor_is_rejected() {
+        assert_err!(PinpointCursor::decode("definitely not a cursor"));
+        assert_err!(PinpointCursor::decode("eyJub3QiOiJhIGN1cnNvciJ9"));
+    }
+}
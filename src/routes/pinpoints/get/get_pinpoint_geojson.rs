@@ -0,0 +1,81 @@
+use actix_web::http::header::ACCEPT;
+use actix_web::HttpRequest;
+use uuid::Uuid;
+use crate::routes::pinpoints::get::get_pinpoint_response::GetPinpointResponse;
+
+pub const GEOJSON_MIME: &str = "application/geo+json";
+
+// The body formats pinpoint listings can be sent back in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinpointsFormat {
+    Json,
+    GeoJson,
+}
+
+impl PinpointsFormat {
+    // GeoJSON is only sent when the client asks for it in the Accept header
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let accepts_geojson = req.headers().get_all(ACCEPT)
+            .filter_map(|x| x.to_str().ok())
+            .flat_map(|x| x.split(','))
+            .any(|x| x.split(';').next().unwrap_or("").trim()
+                .eq_ignore_ascii_case(GEOJSON_MIME));
+        if accepts_geojson {
+            PinpointsFormat::GeoJson
+        }
+        else {
+            PinpointsFormat::Json
+        }
+    }
+}
+
+// RFC 7946 FeatureCollection of Point features.
+// Each feature's properties are the pinpoint as it would appear
+// in the plain JSON response.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct PinpointFeatureCollection {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub features: Vec<PinpointFeature>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct PinpointFeature {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub geometry: PointGeometry,
+    pub properties: GetPinpointResponse,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct PointGeometry {
+    #[serde(rename = "type")]
+    pub kind: String,
+    // Longitude first, as RFC 7946 requires
+    pub coordinates: [f64; 2],
+}
+
+impl From<GetPinpointResponse> for PinpointFeature {
+    fn from(value: GetPinpointResponse) -> Self {
+        Self {
+            kind: String::from("Feature"),
+            id: value.pinpoint_id,
+            geometry: PointGeometry {
+                kind: String::from("Point"),
+                coordinates: [value.longitude, value.latitude],
+            },
+            properties: value,
+        }
+    }
+}
+
+impl From<Vec<GetPinpointResponse>> for PinpointFeatureCollection {
+    fn from(value: Vec<GetPinpointResponse>) -> Self {
+        Self {
+            kind: String::from("FeatureCollection"),
+            features: value.into_iter().map(PinpointFeature::from).collect(),
+        }
+    }
+}
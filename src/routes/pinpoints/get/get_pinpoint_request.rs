@@ -1,13 +1,87 @@
 use std::fmt::{Display, Formatter};
 use uuid::Uuid;
+use crate::domain::{ImageSize, PinpointTag};
 
-#[derive(serde::Serialize, serde::Deserialize)]
+// Default and largest page sizes for pinpoint listings
+pub const DEFAULT_PINPOINT_LIMIT: i64 = 100;
+pub const MAX_PINPOINT_LIMIT: i64 = 500;
+// Longest full-text search accepted, in characters
+pub const MAX_SEARCH_LENGTH: usize = 200;
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
 pub struct GetPinpointRequest {
     pub latitude: Option<f64>,
     pub longitude: Option<f64>,
-    pub proximity: Option<f64>,
+    // Great-circle search radius around latitude/longitude, in meters
+    pub radius: Option<f64>,
     pub pinpoint_id: Option<Uuid>,
-    pub username: Option<String>
+    pub username: Option<String>,
+    // Page size, defaulting to DEFAULT_PINPOINT_LIMIT
+    pub limit: Option<i64>,
+    // Opaque cursor handed back in the X-Next-Cursor header of the previous page
+    pub cursor: Option<String>,
+    // Defaults to relevance when searching, nearest when a location is given,
+    // newest otherwise
+    pub sort: Option<PinpointSort>,
+    // Full-text search over descriptions, in web search syntax:
+    // quoted phrases, "or" and "-excluded" words
+    pub search: Option<String>,
+    // Comma-separated tags, e.g. "food,sunset"
+    pub tags: Option<String>,
+    // Whether pinpoints need any or all of the tags, defaulting to any
+    pub tag_match: Option<TagMatch>,
+    // Size of the attachments sent back, defaulting to the small thumbnails
+    pub attachment_size: Option<ImageSize>,
+}
+
+impl GetPinpointRequest {
+    pub fn parse_tags(&self) -> Result<Option<Vec<PinpointTag>>, String> {
+        match &self.tags {
+            None => Ok(None),
+            Some(x) => {
+                let values: Vec<&str> = x.split(',').collect();
+                PinpointTag::parse_set(&values).map(Some)
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PinpointSort {
+    Newest,
+    Oldest,
+    Nearest,
+    // Best full-text search matches first
+    Relevance,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+impl TagMatch {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TagMatch::Any => "any",
+            TagMatch::All => "all",
+        }
+    }
+}
+
+impl PinpointSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PinpointSort::Newest => "newest",
+            PinpointSort::Oldest => "oldest",
+            PinpointSort::Nearest => "nearest",
+            PinpointSort::Relevance => "relevance",
+        }
+    }
 }
 
 impl Display for GetPinpointRequest {
@@ -20,7 +94,7 @@ impl Display for GetPinpointRequest {
             None => String::from("NONE"),
             Some(x) => x.to_string()
         };
-        let prox = match &self.proximity {
+        let radius = match &self.radius {
             None => String::from("NONE"),
             Some(x) => x.to_string()
         };
@@ -32,7 +106,24 @@ impl Display for GetPinpointRequest {
             None => String::from("NONE"),
             Some(x) => x.to_string()
         };
-        write!(f, "GetPinpointRequest(lat is {}, lng is {}, prox is {}, pinpoint_id is {}, usrn is {}).",
-               lat, lng, prox, ppid, usrn)
+        let limit = match &self.limit {
+            None => String::from("NONE"),
+            Some(x) => x.to_string()
+        };
+        let sort = match &self.sort {
+            None => String::from("NONE"),
+            Some(x) => x.as_str().to_string()
+        };
+        let search = match &self.search {
+            None => String::from("NONE"),
+            Some(x) => x.to_string()
+        };
+        let tags = match &self.tags {
+            None => String::from("NONE"),
+            Some(x) => x.to_string()
+        };
+        write!(f, "GetPinpointRequest(lat is {}, lng is {}, radius is {}, pinpoint_id is {}, usrn is {}, \
+        limit is {}, sort is {}, search is {}, tags are {}).",
+               lat, lng, radius, ppid, usrn, limit, sort, search, tags)
     }
-}
\ No newline at end of file
+}
@@ -1,19 +1,36 @@
+use std::collections::BTreeMap;
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
-use crate::domain::Pinpoint;
-use chrono::serde::ts_seconds;
+use crate::domain::{LocationPrecision, Pinpoint, PinpointReaction, PinpointVisibility};
+use chrono::serde::{ts_seconds, ts_seconds_option};
 
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct GetPinpointResponse {
     pub latitude: f64,
     pub longitude: f64,
     pub description: String,
     #[serde(with = "ts_seconds")]
     pub added_at: DateTime<Utc>,
+    #[serde(with = "ts_seconds_option")]
+    pub modified_at: Option<DateTime<Utc>>,
+    #[serde(with = "ts_seconds_option")]
+    pub expires_at: Option<DateTime<Utc>>,
+    // The first attachment, kept for older clients
     pub attachment: Vec<u8>,
+    pub attachments: Vec<Vec<u8>>,
     pub pinpoint_id: Option<Uuid>,
     pub pinpoint_user_id: Option<Uuid>,
     pub pinpoint_username: Option<String>,
+    pub visibility: PinpointVisibility,
+    // Meters from the searched location when one was given
+    pub distance: Option<f64>,
+    // Full-text search relevance when searching, higher is better
+    pub rank: Option<f64>,
+    // How many users left each kind of reaction
+    pub reactions: BTreeMap<PinpointReaction, i64>,
+    // The requesting user's own reaction, if they reacted
+    pub viewer_reaction: Option<PinpointReaction>,
+    pub tags: Vec<String>,
 }
 
 impl GetPinpointResponse {
@@ -32,30 +49,34 @@ impl GetPinpointResponse {
             longitude,
             description,
             added_at,
+            modified_at: None,
+            expires_at: None,
+            attachments: if attachment.is_empty() { Vec::new() } else { vec![attachment.clone()] },
             attachment,
             pinpoint_id,
             pinpoint_user_id,
-            pinpoint_username
+            pinpoint_username,
+            visibility: PinpointVisibility::default(),
+            distance: None,
+            rank: None,
+            reactions: BTreeMap::new(),
+            viewer_reaction: None,
+            tags: Vec::new()
         }
     }
 
-    pub fn clone_as_censored(&self) -> Self {
-        let cloned = Self::new(
-            self.latitude, self.longitude, self.description.clone(),
-            self.added_at.clone(), self.attachment.clone(),
-            None, None, None);
-        cloned
-    }
-}
-
-impl Clone for GetPinpointResponse {
-    fn clone(&self) -> Self {
-        let cloned = Self::new(
-            self.latitude, self.longitude, self.description.clone(),
-            self.added_at.clone(), self.attachment.clone(),
-            self.pinpoint_id.clone(), self.pinpoint_user_id.clone(),
-            self.pinpoint_username.clone());
-        cloned
+    // For users other than the owner: no owner, and coordinates
+    // and distance only as precise as the configured precision
+    pub fn clone_as_censored(&self, precision: LocationPrecision) -> Self {
+        let (latitude, longitude) = precision.obscure(self.latitude, self.longitude);
+        Self {
+            latitude,
+            longitude,
+            distance: self.distance.map(|x| precision.obscure_distance(x)),
+            pinpoint_user_id: None,
+            pinpoint_username: None,
+            ..self.clone()
+        }
     }
 }
 
@@ -63,15 +84,24 @@ impl Clone for GetPinpointResponse {
 impl TryFrom<&Pinpoint> for GetPinpointResponse {
     type Error = String;
     fn try_from(value: &Pinpoint) -> Result<Self, Self::Error> {
-        let attachment = value.attachment.clone().unwrap_or(Vec::new());
-        let latitude = value.latitude;
-        let longitude = value.longitude;
+        let attachments = value.attachments.clone();
+        let attachment = attachments.first().cloned().unwrap_or_default();
+        let latitude = value.latitude.value();
+        let longitude = value.longitude.value();
         let description = value.description.clone();
-        let added_at = Utc::now();
-        Ok(Self { latitude, longitude, added_at,
-            description, attachment,
+        let added_at = value.added_at;
+        let modified_at = value.modified_at;
+        let expires_at = value.expires_at;
+        Ok(Self { latitude, longitude, added_at, modified_at, expires_at,
+            description, attachment, attachments,
             pinpoint_id: Some(value.pinpoint_id.clone()),
             pinpoint_user_id: value.user_id,
-            pinpoint_username: Some(value.username.clone()) })
+            pinpoint_username: Some(value.username.clone()),
+            visibility: value.visibility,
+            distance: value.distance,
+            rank: value.rank,
+            reactions: BTreeMap::new(),
+            viewer_reaction: None,
+            tags: value.tags.iter().map(|x| x.to_string()).collect() })
     }
 }
\ No newline at end of file
@@ -1,137 +1,589 @@
+use std::collections::HashMap;
 use actix_web::{get, HttpMessage, HttpRequest, HttpResponse, web};
 use actix_web::http::header::ContentType;
 use anyhow::{anyhow};
+use futures::future::try_join_all;
 use sqlx::{PgPool};
-use crate::authentication::{AuthService, AuthParameters, AuthPermissions};
-use crate::domain::database::DbPinpoint;
-use crate::domain::{Pinpoint};
-use crate::routes::pinpoints::get::get_pinpoint_request::GetPinpointRequest;
+use uuid::Uuid;
+use crate::authentication::AuthPermissions;
+use crate::blob_storage::{get_blobs, BlobStorage};
+use crate::domain::database::{DbPinpoint, DbPinpointCluster};
+use crate::domain::{BoundingBox, Latitude, LocationPrecision, Longitude, Pinpoint, PinpointReaction,
+                    PinpointVisibility, PrivateZoneMode};
+use crate::domain::pinpoint::EARTH_MEAN_RADIUS_METERS;
+use crate::domain::private_zone::MAX_PRIVATE_ZONE_RADIUS_METERS;
+use crate::routes::pinpoints::get::get_pinpoint_request::{
+    GetPinpointRequest, PinpointSort, DEFAULT_PINPOINT_LIMIT, MAX_PINPOINT_LIMIT, MAX_SEARCH_LENGTH};
+use crate::routes::pinpoints::get::get_pinpoint_cursor::{PinpointCursor, NEXT_CURSOR_HEADER};
 use crate::routes::pinpoints::get::get_pinpoint_response::GetPinpointResponse;
+use crate::routes::pinpoints::get::get_clusters_request::GetClustersRequest;
+use crate::routes::pinpoints::get::get_cluster_response::GetClusterResponse;
+use crate::routes::pinpoints::get::get_tags_request::GetPopularTagsRequest;
+use crate::routes::pinpoints::get::get_tag_response::GetTagResponse;
+use crate::routes::pinpoints::get::get_heatmap_request::GetHeatmapRequest;
+use crate::routes::pinpoints::get::get_heatmap_response::GetHeatmapCell;
+use crate::routes::pinpoints::get::get_pinpoint_geojson::{
+    PinpointFeatureCollection, PinpointsFormat, GEOJSON_MIME};
+
+// How many pinpoint ids are sampled into each cluster
+pub const CLUSTER_SAMPLE_SIZE: i32 = 5;
 
 #[tracing::instrument(
 name = "handle_get_pinpoints",
-skip(pool, path, args),
+skip(pool, storage, path, args, precision),
 )]
 #[get("/{username}")]
 pub async fn handle_get_pinpoints(
     req: HttpRequest,
     pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
     path: web::Path<String>,
     args: web::Query<GetPinpointRequest>,
+    precision: web::Data<LocationPrecision>,
 ) -> HttpResponse {
-    let req_ext = req.extensions_mut();
-    let auth_permissions: &AuthPermissions = req_ext.get::<AuthPermissions>().unwrap();
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
     println!("AuthPermissions found as {:?}", auth_permissions);
     let user_requesting = path.into_inner();
     println!("GetPinpointRequest to handler: {}", args.0);
     if auth_permissions.username != user_requesting {
         return HttpResponse::Unauthorized().finish();
     }
-    get_pinpoints(pool, Some(user_requesting), args).await
+    let format = PinpointsFormat::from_request(&req);
+    get_pinpoints(pool, storage, Some(user_requesting), args, format, **precision).await
+}
+
+#[tracing::instrument(
+name = "handle_get_pinpoint_clusters",
+skip(pool, path, args, precision),
+)]
+#[get("/{username}/clusters")]
+pub async fn handle_get_pinpoint_clusters(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    args: web::Query<GetClustersRequest>,
+    precision: web::Data<LocationPrecision>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let user_requesting = path.into_inner();
+    if auth_permissions.username != user_requesting {
+        return HttpResponse::Unauthorized().finish();
+    }
+    if let Err(e) = args.validate() {
+        return HttpResponse::BadRequest().body(e);
+    }
+    let clusters = match get_db_pinpoint_clusters(
+        &pool, &args.0, &user_requesting, **precision).await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    // A cluster of one or two pinpoints would otherwise give away where they are
+    let response_clusters: Vec<GetClusterResponse> = clusters.iter()
+        .map(GetClusterResponse::from)
+        .map(|x| x.obscured(*precision.get_ref()))
+        .collect();
+    let json = serde_json::to_string(&response_clusters).unwrap();
+
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(json)
+}
+
+#[tracing::instrument(
+name = "handle_get_popular_tags",
+skip(pool, path, args, precision),
+)]
+#[get("/{username}/tags")]
+pub async fn handle_get_popular_tags(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    args: web::Query<GetPopularTagsRequest>,
+    precision: web::Data<LocationPrecision>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let user_requesting = path.into_inner();
+    if auth_permissions.username != user_requesting {
+        return HttpResponse::Unauthorized().finish();
+    }
+    if let Err(e) = args.validate() {
+        return HttpResponse::BadRequest().body(e);
+    }
+    let tags = match get_db_popular_tags(
+        &pool, &args.0, &user_requesting, **precision).await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let json = serde_json::to_string(&tags).unwrap();
+
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(json)
+}
+
+#[tracing::instrument(
+name = "handle_get_pinpoint_heatmap",
+skip(pool, path, args, precision),
+)]
+#[get("/{username}/heatmap")]
+pub async fn handle_get_pinpoint_heatmap(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    args: web::Query<GetHeatmapRequest>,
+    precision: web::Data<LocationPrecision>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let user_requesting = path.into_inner();
+    if auth_permissions.username != user_requesting {
+        return HttpResponse::Unauthorized().finish();
+    }
+    if let Err(e) = args.validate() {
+        return HttpResponse::BadRequest().body(e);
+    }
+    let cells = match get_db_pinpoint_heatmap(
+        &pool, &args.0, &user_requesting, **precision).await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let json = serde_json::to_string(&cells).unwrap();
+
+    HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .body(json)
 }
 
 pub async fn get_pinpoints(
     pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
     user_requesting: Option<String>,
     args: web::Query<GetPinpointRequest>,
+    format: PinpointsFormat,
+    precision: LocationPrecision,
 ) -> HttpResponse {
-    let user_filter: Option<String> = args.0.username;
-    let latitude = args.0.latitude.unwrap_or(0.0);
-    let longitude = args.0.longitude.unwrap_or(0.0);
-    let proximity = args.0.proximity.unwrap_or(9999.0);
+    if let Some(Err(e)) = args.latitude.map(Latitude::parse) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    if let Some(Err(e)) = args.longitude.map(Longitude::parse) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    let latitude = args.latitude;
+    let longitude = args.longitude;
+    let radius = args.radius;
+    let has_location = latitude.is_some() && longitude.is_some();
+    if radius.is_some() && !has_location {
+        return HttpResponse::BadRequest().body(
+            "A radius requires both a latitude and a longitude.");
+    }
+    if let Some(r) = radius {
+        if !r.is_finite() || r < 0.0 {
+            return HttpResponse::BadRequest().body(
+                "The radius must be a non-negative number of meters.");
+        }
+    }
+    if let Some(search) = &args.search {
+        if search.trim().is_empty() {
+            return HttpResponse::BadRequest().body("The search cannot be empty.");
+        }
+        if search.chars().count() > MAX_SEARCH_LENGTH {
+            return HttpResponse::BadRequest().body(
+                format!("The search cannot be longer than {} characters.", MAX_SEARCH_LENGTH));
+        }
+    }
+    let has_search = args.search.is_some();
+    let sort = match args.sort {
+        Some(x) => x,
+        None if has_search => PinpointSort::Relevance,
+        None if has_location => PinpointSort::Nearest,
+        None => PinpointSort::Newest
+    };
+    if sort == PinpointSort::Nearest && !has_location {
+        return HttpResponse::BadRequest().body(
+            "Sorting by nearest requires both a latitude and a longitude.");
+    }
+    if sort == PinpointSort::Relevance && !has_search {
+        return HttpResponse::BadRequest().body(
+            "Sorting by relevance requires a search.");
+    }
+    let limit = args.limit.unwrap_or(DEFAULT_PINPOINT_LIMIT);
+    if !(1..=MAX_PINPOINT_LIMIT).contains(&limit) {
+        return HttpResponse::BadRequest().body(
+            format!("The limit must be between 1 and {}.", MAX_PINPOINT_LIMIT));
+    }
+    let cursor = match &args.cursor {
+        None => None,
+        Some(x) => match PinpointCursor::decode(x) {
+            Ok(c) if c.sort == sort => Some(c),
+            Ok(_) => return HttpResponse::BadRequest().body(
+                "The cursor belongs to a different sort order."),
+            Err(e) => return HttpResponse::BadRequest().body(e)
+        }
+    };
+
+    if let Err(e) = args.parse_tags() {
+        return HttpResponse::BadRequest().body(e);
+    }
 
-    let pinpoints = match get_db_pinpoints(
-        &pool, user_filter, latitude, longitude, proximity).await {
+    let viewer = user_requesting.clone().unwrap_or_default();
+    let rows = match get_db_pinpoints(
+        &pool, &args, &viewer, sort, cursor.as_ref(), limit + 1, precision).await {
+        Ok(x) => x,
+        Err(_) => {
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let mut pinpoints = match read_db_pinpoints(storage.get_ref(), &rows).await {
         Ok(x) => x,
         Err(_) => {
             return HttpResponse::InternalServerError().finish();
         }
     };
+    // One extra row was fetched to learn whether another page exists
+    let next_cursor = if pinpoints.len() as i64 > limit {
+        pinpoints.truncate(limit as usize);
+        pinpoints.last().map(|x| PinpointCursor::after(x, sort).encode())
+    } else {
+        None
+    };
 
-    let response_pinpoints =
+    let mut response_pinpoints =
     match convert_to_pinpoint_response(pinpoints) {
         Ok(x) => x,
         Err(_) => return HttpResponse::InternalServerError().finish()
     };
+    if add_db_reactions(&pool, &mut response_pinpoints, &viewer).await.is_err() {
+        return HttpResponse::InternalServerError().finish();
+    }
 
     let filtered_pinpoints: Vec<GetPinpointResponse>
         = censor_pinpoints_by_username(
         &response_pinpoints,
-        user_requesting.unwrap_or(String::from("")).as_str());
+        user_requesting.unwrap_or(String::from("")).as_str(),
+        precision);
 
     let vec_len = filtered_pinpoints.len();
     println!("Sending {} pinpoints back from handler", vec_len);
-    let json = serde_json::to_string(&filtered_pinpoints).unwrap();
-
-    HttpResponse::Ok()
-        .content_type(ContentType::json())
-        .body(json)
+    let mut response = HttpResponse::Ok();
+    if let Some(x) = next_cursor {
+        response.insert_header((NEXT_CURSOR_HEADER, x));
+    }
+    match format {
+        PinpointsFormat::Json => {
+            let json = serde_json::to_string(&filtered_pinpoints).unwrap();
+            response
+                .content_type(ContentType::json())
+                .body(json)
+        },
+        PinpointsFormat::GeoJson => {
+            let collection = PinpointFeatureCollection::from(filtered_pinpoints);
+            let json = serde_json::to_string(&collection).unwrap();
+            response
+                .content_type(GEOJSON_MIME)
+                .body(json)
+        }
+    }
 }
 
 
-// Filters by username after the query
-// if a username is present
+// Only pinpoints the viewer may see are returned: public ones, their own,
+// and followers-only ones from users they follow. Expired pinpoints are skipped.
+// Distances are great-circle (haversine) distances in meters from
+// latitude/longitude when both are given. A radius search first narrows the
+// pinpoints down to a bounding box on pinpoints_location_idx.
+// Pages are keyset based: (added_at, id) for newest and oldest,
+// (distance, id) for nearest, continuing after the cursor if one is given.
+// (rank, id) for relevance.
+// When tags are given, pinpoints need any or all of them depending on tag_match.
+// A search only keeps pinpoints whose description matches it, ranked by ts_rank.
+// Other users' pinpoints inside one of their private zones are placed at the
+// zone's public location or skipped for hiding zones, then snapped to the precision.
+// Their distances, radius and sort order all use that obscured location,
+// so neither the rows nor the cursor reveal more than the response does.
+// Rows carry the keys of their attachments at the requested size.
 pub async fn get_db_pinpoints(
     pool: &PgPool,
-    user_filter: Option<String>,
-    latitude: f64,
-    longitude: f64,
-    proximity: f64,
-) -> Result<Vec<Pinpoint>, anyhow::Error> {
-    let lat_lower = latitude.clone() - proximity.clone();
-    let lat_upper = latitude.clone() + proximity.clone();
-    let long_lower = longitude.clone() - proximity.clone();
-    let long_upper = longitude + proximity;
-
+    args: &GetPinpointRequest,
+    viewer: &str,
+    sort: PinpointSort,
+    cursor: Option<&PinpointCursor>,
+    limit: i64,
+    precision: LocationPrecision,
+) -> Result<Vec<DbPinpoint>, anyhow::Error> {
+    let tag_names: Option<Vec<String>> = args.parse_tags()
+        .map_err(|e| anyhow!(e))?
+        .map(|x| x.iter().map(|t| t.to_string()).collect());
+    // Obscured pinpoints are measured from where they are shown, which may be
+    // up to a zone diameter and a grid cell away from where they are
+    let bbox = match (args.latitude, args.longitude, args.radius) {
+        (Some(latitude), Some(longitude), Some(radius)) => Some(BoundingBox::around(
+            latitude, longitude, radius + 2.0 * MAX_PRIVATE_ZONE_RADIUS_METERS + precision.meters())),
+        _ => None
+    };
     let rows = sqlx::query_as!(
         DbPinpoint,
-       r#"SELECT pin.id AS pinpoint_id, pin.latitude AS latitude, pin.longitude as longitude,
+       r#"SELECT pin.id AS pinpoint_id,
+        loc.latitude AS "latitude!",
+        loc.longitude AS "longitude!",
         pin.added_at AS added_at,
+        pin.modified_at AS modified_at,
+        pin.expires_at AS expires_at,
         con.id AS contents_id,
         con.description AS description,
-        con.attachment AS attachment,
+        ARRAY(
+            SELECT CASE $17::TEXT
+                WHEN 'small' THEN COALESCE(att.attachment_small_key, att.attachment_key)
+                WHEN 'medium' THEN COALESCE(att.attachment_medium_key, att.attachment_key)
+                WHEN 'full' THEN CASE WHEN usr.username = $11
+                    THEN COALESCE(att.attachment_with_exif_key, att.attachment_key)
+                    ELSE att.attachment_key END
+                ELSE att.attachment_key END
+            FROM pinpoint_contents pin_att
+            INNER JOIN contents att ON att.id = pin_att.content_id
+            WHERE pin_att.pinpoint_id = pin.id AND att.attachment_key IS NOT NULL
+            ORDER BY pin_att.position
+        ) AS attachment_keys,
         usr.id AS user_id,
-        usr.username AS username
+        usr.username AS username,
+        pin.visibility AS visibility,
+        dst.distance AS distance,
+        rnk.rank AS rank,
+        ARRAY(
+            SELECT tag.name FROM pinpoint_tags pin_tag
+            INNER JOIN tags tag ON tag.id = pin_tag.tag_id
+            WHERE pin_tag.pinpoint_id = pin.id
+            ORDER BY tag.name
+        ) AS tags
         FROM pinpoints pin
-        INNER JOIN pinpoint_contents pin_con on pin_con.pinpoint_id = pin.id
+        INNER JOIN pinpoint_contents pin_con on pin_con.pinpoint_id = pin.id AND pin_con.position = 0
         INNER JOIN contents con ON con.id = pin_con.content_id
         INNER JOIN user_pinpoints usr_pin ON usr_pin.pinpoint_id = pin.id
         INNER JOIN users usr ON usr_pin.user_id = usr.id
-        WHERE pin.latitude > $1 AND pin.latitude < $2
-        AND pin.longitude > $3 AND pin.longitude < $4 "#
-        , lat_lower, lat_upper, long_lower, long_upper).fetch_all(pool)
+        LEFT JOIN LATERAL (
+            SELECT * FROM pinpoint_private_zone(pin.latitude, pin.longitude, usr.id)
+            WHERE usr.username IS DISTINCT FROM $11
+        ) zon ON TRUE
+        CROSS JOIN LATERAL obscured_location(
+            COALESCE(zon.latitude, pin.latitude), COALESCE(zon.longitude, pin.longitude),
+            CASE WHEN usr.username = $11 THEN 0 ELSE $22::DOUBLE PRECISION END
+        ) loc
+        CROSS JOIN LATERAL (
+            SELECT 2.0 * $4::DOUBLE PRECISION * ASIN(LEAST(1.0, SQRT(
+                POWER(SIN(RADIANS(loc.latitude - $1::DOUBLE PRECISION) / 2.0), 2)
+                + COS(RADIANS($1::DOUBLE PRECISION)) * COS(RADIANS(loc.latitude))
+                * POWER(SIN(RADIANS(loc.longitude - $2::DOUBLE PRECISION) / 2.0), 2)
+            ))) AS distance
+        ) dst
+        CROSS JOIN LATERAL (
+            SELECT CASE WHEN $14::TEXT IS NULL THEN NULL
+            ELSE ts_rank(con.description_tsv, websearch_to_tsquery('english', $14))::DOUBLE PRECISION
+            END AS rank
+        ) rnk
+        WHERE ($18::DOUBLE PRECISION IS NULL OR (pin.latitude BETWEEN $18 AND $19
+            AND CASE WHEN $20::DOUBLE PRECISION <= $21::DOUBLE PRECISION
+                THEN pin.longitude BETWEEN $20 AND $21
+                ELSE pin.longitude >= $20 OR pin.longitude <= $21 END))
+        AND ($3::DOUBLE PRECISION IS NULL OR dst.distance <= $3)
+        AND ($5::TEXT IS NULL OR usr.username = $5)
+        AND ($16::UUID IS NULL OR pin.id = $16)
+        AND ($14::TEXT IS NULL OR con.description_tsv @@ websearch_to_tsquery('english', $14))
+        AND (pin.expires_at IS NULL OR pin.expires_at > NOW())
+        AND zon.mode IS DISTINCT FROM 'hide'
+        AND pinpoint_visible_to(pin.visibility, usr.id, $11)
+        AND ($12::TEXT[] IS NULL OR (
+            SELECT COUNT(*) FROM pinpoint_tags pin_tag
+            INNER JOIN tags tag ON tag.id = pin_tag.tag_id
+            WHERE pin_tag.pinpoint_id = pin.id AND tag.name = ANY($12)
+        ) >= CASE WHEN $13 = 'all' THEN CARDINALITY($12) ELSE 1 END)
+        AND ($7::UUID IS NULL OR CASE $6::TEXT
+            WHEN 'newest' THEN (pin.added_at, pin.id) < ($8::TIMESTAMPTZ, $7)
+            WHEN 'oldest' THEN (pin.added_at, pin.id) > ($8::TIMESTAMPTZ, $7)
+            WHEN 'relevance' THEN rnk.rank < $15::DOUBLE PRECISION
+                OR (rnk.rank = $15 AND pin.id > $7)
+            ELSE (dst.distance, pin.id) > ($9::DOUBLE PRECISION, $7) END)
+        ORDER BY
+            CASE WHEN $6 = 'relevance' THEN rnk.rank END DESC,
+            CASE WHEN $6 = 'nearest' THEN dst.distance END ASC,
+            CASE WHEN $6 = 'oldest' THEN pin.added_at END ASC,
+            CASE WHEN $6 = 'newest' THEN pin.added_at END DESC,
+            CASE WHEN $6 = 'newest' THEN pin.id END DESC,
+            pin.id ASC
+        LIMIT $10 "#
+        , args.latitude, args.longitude, args.radius, EARTH_MEAN_RADIUS_METERS,
+        args.username, sort.as_str(),
+        cursor.map(|x| x.pinpoint_id), cursor.map(|x| x.added_at),
+        cursor.and_then(|x| x.distance), limit, viewer,
+        tag_names.as_deref(),
+        args.tag_match.unwrap_or_default().as_str(),
+        args.search, cursor.and_then(|x| x.rank), args.pinpoint_id,
+        args.attachment_size.unwrap_or_default().as_str(),
+        bbox.map(|x| x.min_latitude), bbox.map(|x| x.max_latitude),
+        bbox.map(|x| x.min_longitude), bbox.map(|x| x.max_longitude),
+        precision.meters()).fetch_all(pool)
         .await
-        .expect("Failed to perform a query to retrieve pinpoints.");
-    let mut results: Vec<Pinpoint> = Vec::new();
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            anyhow!("Failed to perform a query to retrieve pinpoints.")
+        })?;
+    Ok(rows)
+}
 
-    if user_filter.is_some() {
-        let user_inner = user_filter.unwrap();
-        for row in rows.iter() {
-            let pinpoint = Pinpoint::try_from(row)
-                .map_err(|_| anyhow!("Conversion failure"))?;
-            if pinpoint.username == user_inner {
-                results.push(pinpoint);
-            }
-        }
-    }
-    else {
-        for row in rows.iter() {
-            let pinpoint = Pinpoint::try_from(row)
-                .map_err(|_| anyhow!("Conversion failure"))?;
-            results.push(pinpoint);
-        }
+// Pinpoints of the rows, with their attachments read from blob storage
+pub async fn read_db_pinpoints(
+    storage: &dyn BlobStorage,
+    rows: &[DbPinpoint],
+) -> Result<Vec<Pinpoint>, anyhow::Error> {
+    let attachments = try_join_all(rows.iter()
+        .map(|x| get_blobs(storage, x.attachment_keys.as_deref().unwrap_or_default()))).await?;
+    let mut results: Vec<Pinpoint> = Vec::new();
+    for (row, attachments) in rows.iter().zip(attachments) {
+        let mut pinpoint = Pinpoint::try_from(row)
+            .map_err(|_| anyhow!("Conversion failure"))?;
+        pinpoint.attachments = attachments;
+        results.push(pinpoint);
     }
+    Ok(results)
+}
+
+// Whether the pinpoint exists, has not expired and may be seen by the viewer,
+// which a hiding private zone of its owner rules out
+pub async fn is_db_pinpoint_visible(
+    pool: &PgPool,
+    pinpoint_id: Uuid,
+    viewer: &str,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT pin.id
+        FROM pinpoints pin
+        INNER JOIN user_pinpoints usr_pin ON usr_pin.pinpoint_id = pin.id
+        INNER JOIN users usr ON usr_pin.user_id = usr.id
+        LEFT JOIN LATERAL (
+            SELECT * FROM pinpoint_private_zone(pin.latitude, pin.longitude, usr.id)
+            WHERE usr.username IS DISTINCT FROM $2
+        ) zon ON TRUE
+        WHERE pin.id = $1
+        AND (pin.expires_at IS NULL OR pin.expires_at > NOW())
+        AND zon.mode IS DISTINCT FROM 'hide'
+        AND pinpoint_visible_to(pin.visibility, usr.id, $2);
+        "#,
+        pinpoint_id,
+        viewer
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(row.is_some())
+}
+
+// Whether the viewer may see pinpoints posted by owner with the given visibility,
+// for when the pinpoint itself may already be gone
+pub async fn is_db_visible_by_owner(
+    pool: &PgPool,
+    owner: &str,
+    visibility: PinpointVisibility,
+    viewer: &str,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT pinpoint_visible_to($2, usr.id, $3) AS "visible!"
+        FROM users usr
+        WHERE usr.username = $1;
+        "#,
+        owner,
+        visibility.as_str(),
+        viewer
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(row.map(|x| x.visible).unwrap_or(false))
+}
 
-    for r in results.as_slice() {
-        let lat = r.latitude.clone();
-        let log = r.longitude.clone();
-        let desc = r.description.clone();
-        let usr = r.username.clone();
+// Public location and mode of the owner's private zone covering the location,
+// never set when the viewer is the owner
+pub async fn get_db_private_zone(
+    pool: &PgPool,
+    owner: &str,
+    latitude: f64,
+    longitude: f64,
+    viewer: &str,
+) -> Result<Option<(f64, f64, PrivateZoneMode)>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT zon.latitude AS "latitude!", zon.longitude AS "longitude!", zon.mode AS "mode!"
+        FROM users usr
+        CROSS JOIN LATERAL pinpoint_private_zone($2, $3, usr.id) zon
+        WHERE usr.username = $1 AND usr.username IS DISTINCT FROM $4;
+        "#,
+        owner,
+        latitude,
+        longitude,
+        viewer
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    // An unknown mode is treated as the safer one
+    Ok(row.map(|x| (x.latitude, x.longitude,
+                    PrivateZoneMode::parse(&x.mode).unwrap_or(PrivateZoneMode::Hide))))
+}
 
-        // println!("Pinpoint converted from DB: latitude {}, longitude {}, \
-        // description {}, username {}", lat, log, desc, usr);
+// Fills in the reaction counts of each pinpoint
+// and which reaction, if any, the viewer left on it
+pub async fn add_db_reactions(
+    pool: &PgPool,
+    response_pinpoints: &mut [GetPinpointResponse],
+    viewer: &str,
+) -> Result<(), anyhow::Error> {
+    let pinpoint_ids: Vec<Uuid> = response_pinpoints.iter()
+        .filter_map(|x| x.pinpoint_id)
+        .collect();
+    if pinpoint_ids.is_empty() {
+        return Ok(());
     }
-    Ok(results)
+    let rows = sqlx::query!(
+        r#"
+        SELECT rct.pinpoint_id, rct.reaction,
+        COUNT(*) AS "count!",
+        BOOL_OR(usr.username = $2) AS "viewer_reacted!"
+        FROM pinpoint_reactions rct
+        INNER JOIN users usr ON usr.id = rct.user_id
+        WHERE rct.pinpoint_id = ANY($1)
+        GROUP BY rct.pinpoint_id, rct.reaction;
+        "#,
+        &pinpoint_ids,
+        viewer
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            anyhow!("Failed to perform a query to retrieve pinpoint reactions.")
+        })?;
+    let positions: HashMap<Uuid, usize> = response_pinpoints.iter().enumerate()
+        .filter_map(|(i, x)| x.pinpoint_id.map(|id| (id, i)))
+        .collect();
+    for row in rows {
+        let reaction = PinpointReaction::parse(&row.reaction)
+            .map_err(|e| anyhow!(e))?;
+        if let Some(i) = positions.get(&row.pinpoint_id) {
+            let response_pinpoint = &mut response_pinpoints[*i];
+            response_pinpoint.reactions.insert(reaction, row.count);
+            if row.viewer_reacted {
+                response_pinpoint.viewer_reaction = Some(reaction);
+            }
+        }
+    }
+    Ok(())
 }
 
 pub fn convert_to_pinpoint_response(
@@ -150,7 +602,8 @@ pub fn convert_to_pinpoint_response(
 
 pub fn censor_pinpoints_by_username(
     response_pinpoints: &Vec<GetPinpointResponse>,
-    username: &str
+    username: &str,
+    precision: LocationPrecision,
 ) -> Vec<GetPinpointResponse> {
     let mut filtered_pinpoints: Vec<GetPinpointResponse> = Vec::new();
     // Remove more sensitive data about the pinpoint objects
@@ -158,7 +611,7 @@ pub fn censor_pinpoints_by_username(
     for response_pinpoint in response_pinpoints {
         if response_pinpoint.pinpoint_username.is_some() {
             if response_pinpoint.pinpoint_username.clone().unwrap() != username {
-                filtered_pinpoints.push(response_pinpoint.clone_as_censored());
+                filtered_pinpoints.push(response_pinpoint.clone_as_censored(precision));
             }
             else {
                 filtered_pinpoints.push(response_pinpoint.clone());
@@ -166,4 +619,152 @@ pub fn censor_pinpoints_by_username(
         }
     }
     filtered_pinpoints
-}
\ No newline at end of file
+}
+
+// Groups the pinpoints inside the bounding box into grid cells
+// sized by the zoom level, so only one row per cell leaves the database.
+// Pinpoints the viewer may not see and expired pinpoints are left out of the counts.
+// Private zones and the location precision apply as they do in get_db_pinpoints.
+pub async fn get_db_pinpoint_clusters(
+    pool: &PgPool,
+    args: &GetClustersRequest,
+    viewer: &str,
+    precision: LocationPrecision,
+) -> Result<Vec<DbPinpointCluster>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        DbPinpointCluster,
+       r#"SELECT AVG(loc.latitude) AS latitude,
+        AVG(loc.longitude) AS longitude,
+        COUNT(*) AS count,
+        (ARRAY_AGG(pin.id ORDER BY pin.added_at DESC))[1:$6::INTEGER] AS sample_pinpoint_ids
+        FROM pinpoints pin
+        INNER JOIN user_pinpoints usr_pin ON usr_pin.pinpoint_id = pin.id
+        INNER JOIN users usr ON usr_pin.user_id = usr.id
+        LEFT JOIN LATERAL (
+            SELECT * FROM pinpoint_private_zone(pin.latitude, pin.longitude, usr.id)
+            WHERE usr.username IS DISTINCT FROM $7
+        ) zon ON TRUE
+        CROSS JOIN LATERAL obscured_location(
+            COALESCE(zon.latitude, pin.latitude), COALESCE(zon.longitude, pin.longitude),
+            CASE WHEN usr.username = $7 THEN 0 ELSE $8::DOUBLE PRECISION END
+        ) loc
+        WHERE loc.latitude >= $1 AND loc.latitude <= $2
+        AND (pin.expires_at IS NULL OR pin.expires_at > NOW())
+        AND zon.mode IS DISTINCT FROM 'hide'
+        AND pinpoint_visible_to(pin.visibility, usr.id, $7)
+        AND (CASE WHEN $3::DOUBLE PRECISION <= $4::DOUBLE PRECISION
+            THEN loc.longitude >= $3 AND loc.longitude <= $4
+            ELSE loc.longitude >= $3 OR loc.longitude <= $4 END)
+        GROUP BY FLOOR(loc.latitude / $5), FLOOR(loc.longitude / $5)
+        ORDER BY count DESC "#
+        , args.min_latitude, args.max_latitude, args.min_longitude, args.max_longitude,
+        args.cell_size(), CLUSTER_SAMPLE_SIZE, viewer, precision.meters()).fetch_all(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            anyhow!("Failed to perform a query to retrieve pinpoint clusters.")
+        })?;
+    Ok(rows)
+}
+
+// Most used tags among the pinpoints inside the bounding box, most used first.
+// Only pinpoints the viewer may see and that have not expired are counted.
+// Private zones and the location precision apply as they do in get_db_pinpoints.
+pub async fn get_db_popular_tags(
+    pool: &PgPool,
+    args: &GetPopularTagsRequest,
+    viewer: &str,
+    precision: LocationPrecision,
+) -> Result<Vec<GetTagResponse>, anyhow::Error> {
+    let rows = sqlx::query_as!(
+        GetTagResponse,
+       r#"SELECT tag.name AS tag,
+        COUNT(*) AS "count!"
+        FROM pinpoint_tags pin_tag
+        INNER JOIN tags tag ON tag.id = pin_tag.tag_id
+        INNER JOIN pinpoints pin ON pin.id = pin_tag.pinpoint_id
+        INNER JOIN user_pinpoints usr_pin ON usr_pin.pinpoint_id = pin.id
+        INNER JOIN users usr ON usr_pin.user_id = usr.id
+        LEFT JOIN LATERAL (
+            SELECT * FROM pinpoint_private_zone(pin.latitude, pin.longitude, usr.id)
+            WHERE usr.username IS DISTINCT FROM $6
+        ) zon ON TRUE
+        CROSS JOIN LATERAL obscured_location(
+            COALESCE(zon.latitude, pin.latitude), COALESCE(zon.longitude, pin.longitude),
+            CASE WHEN usr.username = $6 THEN 0 ELSE $7::DOUBLE PRECISION END
+        ) loc
+        WHERE loc.latitude >= $1 AND loc.latitude <= $2
+        AND (pin.expires_at IS NULL OR pin.expires_at > NOW())
+        AND zon.mode IS DISTINCT FROM 'hide'
+        AND pinpoint_visible_to(pin.visibility, usr.id, $6)
+        AND (CASE WHEN $3::DOUBLE PRECISION <= $4::DOUBLE PRECISION
+            THEN loc.longitude >= $3 AND loc.longitude <= $4
+            ELSE loc.longitude >= $3 OR loc.longitude <= $4 END)
+        GROUP BY tag.name
+        ORDER BY "count!" DESC, tag.name ASC
+        LIMIT $5 "#
+        , args.min_latitude, args.max_latitude, args.min_longitude, args.max_longitude,
+        args.limit(), viewer, precision.meters()).fetch_all(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            anyhow!("Failed to perform a query to retrieve popular tags.")
+        })?;
+    Ok(rows)
+}
+
+// Counts the pinpoints inside the bounding box per grid cell.
+// Only non-empty cells are returned, densest first.
+// Pinpoints the viewer may not see and expired pinpoints are left out of the counts.
+// Private zones and the location precision apply as they do in get_db_pinpoints,
+// so cells smaller than the precision cannot tell pinpoints apart any better.
+pub async fn get_db_pinpoint_heatmap(
+    pool: &PgPool,
+    args: &GetHeatmapRequest,
+    viewer: &str,
+    precision: LocationPrecision,
+) -> Result<Vec<GetHeatmapCell>, anyhow::Error> {
+    let tag = args.parse_tag()
+        .map_err(|e| anyhow!(e))?
+        .map(|x| x.to_string());
+    let rows = sqlx::query_as!(
+        GetHeatmapCell,
+       r#"SELECT (FLOOR(loc.latitude / $5) + 0.5) * $5 AS "latitude!",
+        (FLOOR(loc.longitude / $5) + 0.5) * $5 AS "longitude!",
+        COUNT(*) AS "count!"
+        FROM pinpoints pin
+        INNER JOIN user_pinpoints usr_pin ON usr_pin.pinpoint_id = pin.id
+        INNER JOIN users usr ON usr_pin.user_id = usr.id
+        LEFT JOIN LATERAL (
+            SELECT * FROM pinpoint_private_zone(pin.latitude, pin.longitude, usr.id)
+            WHERE usr.username IS DISTINCT FROM $6
+        ) zon ON TRUE
+        CROSS JOIN LATERAL obscured_location(
+            COALESCE(zon.latitude, pin.latitude), COALESCE(zon.longitude, pin.longitude),
+            CASE WHEN usr.username = $6 THEN 0 ELSE $10::DOUBLE PRECISION END
+        ) loc
+        WHERE loc.latitude >= $1 AND loc.latitude <= $2
+        AND ($7::TIMESTAMPTZ IS NULL OR pin.added_at >= $7)
+        AND ($8::TIMESTAMPTZ IS NULL OR pin.added_at < $8)
+        AND ($9::TEXT IS NULL OR EXISTS (
+            SELECT 1 FROM pinpoint_tags pin_tag
+            INNER JOIN tags tag ON tag.id = pin_tag.tag_id
+            WHERE pin_tag.pinpoint_id = pin.id AND tag.name = $9))
+        AND (pin.expires_at IS NULL OR pin.expires_at > NOW())
+        AND zon.mode IS DISTINCT FROM 'hide'
+        AND pinpoint_visible_to(pin.visibility, usr.id, $6)
+        AND (CASE WHEN $3::DOUBLE PRECISION <= $4::DOUBLE PRECISION
+            THEN loc.longitude >= $3 AND loc.longitude <= $4
+            ELSE loc.longitude >= $3 OR loc.longitude <= $4 END)
+        GROUP BY FLOOR(loc.latitude / $5), FLOOR(loc.longitude / $5)
+        ORDER BY "count!" DESC "#
+        , args.min_latitude, args.max_latitude, args.min_longitude, args.max_longitude,
+        args.cell_size, viewer, args.added_after, args.added_before, tag,
+        precision.meters()).fetch_all(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            anyhow!("Failed to perform a query to retrieve the pinpoint heatmap.")
+        })?;
+    Ok(rows)
+}
@@ -0,0 +1,6 @@
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct GetTagResponse {
+    pub tag: String,
+    // How many visible pinpoints in the bounding box carry the tag
+    pub count: i64,
+}
@@ -0,0 +1,47 @@
+use std::fmt::{Display, Formatter};
+use crate::domain::{Latitude, Longitude};
+
+// Default and largest number of popular tags sent back
+pub const DEFAULT_TAG_LIMIT: i64 = 20;
+pub const MAX_TAG_LIMIT: i64 = 100;
+
+// Bounding box to count tags in, the same way as GetClustersRequest.
+// A min_longitude greater than max_longitude means the box
+// crosses the antimeridian.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct GetPopularTagsRequest {
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64,
+    // Defaults to DEFAULT_TAG_LIMIT
+    pub limit: Option<i64>
+}
+
+impl GetPopularTagsRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        Latitude::parse(self.min_latitude)?;
+        Latitude::parse(self.max_latitude)?;
+        Longitude::parse(self.min_longitude)?;
+        Longitude::parse(self.max_longitude)?;
+        if self.min_latitude > self.max_latitude {
+            return Err(String::from("min_latitude must not be greater than max_latitude."));
+        }
+        if !(1..=MAX_TAG_LIMIT).contains(&self.limit()) {
+            return Err(format!("The limit must be between 1 and {}.", MAX_TAG_LIMIT));
+        }
+        Ok(())
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_TAG_LIMIT)
+    }
+}
+
+impl Display for GetPopularTagsRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GetPopularTagsRequest(lat is {} to {}, lng is {} to {}, limit is {}).",
+               self.min_latitude, self.max_latitude,
+               self.min_longitude, self.max_longitude, self.limit())
+    }
+}
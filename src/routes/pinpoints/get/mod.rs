@@ -1,6 +1,22 @@
 pub mod get_routing;
 mod get_pinpoint_request;
 mod get_pinpoint_response;
+mod get_clusters_request;
+mod get_cluster_response;
+mod get_pinpoint_geojson;
+mod get_pinpoint_cursor;
+mod get_tags_request;
+mod get_tag_response;
+mod get_heatmap_request;
+mod get_heatmap_response;
 
-pub use get_pinpoint_request::GetPinpointRequest;
-pub use get_pinpoint_response::GetPinpointResponse;
\ No newline at end of file
+pub use get_pinpoint_request::{GetPinpointRequest, PinpointSort, TagMatch};
+pub use get_pinpoint_response::GetPinpointResponse;
+pub use get_clusters_request::GetClustersRequest;
+pub use get_cluster_response::GetClusterResponse;
+pub use get_pinpoint_geojson::{PinpointFeature, PinpointFeatureCollection, PinpointsFormat, GEOJSON_MIME};
+pub use get_pinpoint_cursor::{PinpointCursor, NEXT_CURSOR_HEADER};
+pub use get_tags_request::GetPopularTagsRequest;
+pub use get_tag_response::GetTagResponse;
+pub use get_heatmap_request::GetHeatmapRequest;
+pub use get_heatmap_response::GetHeatmapCell;
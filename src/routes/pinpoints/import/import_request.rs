@@ -0,0 +1,8 @@
+use crate::domain::PinpointVisibility;
+
+// The GPX or KML file itself is the request body
+#[derive(serde::Serialize, serde::Deserialize, Debug, Default)]
+pub struct ImportPinpointsRequest {
+    // Applied to every imported pinpoint, defaults to public
+    pub visibility: Option<PinpointVisibility>
+}
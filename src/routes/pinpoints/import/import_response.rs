@@ -0,0 +1,30 @@
+use uuid::Uuid;
+use crate::domain::WaypointFormat;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ImportPinpointsResponse {
+    pub format: WaypointFormat,
+    pub imported: usize,
+    pub rejected: usize,
+    // One per waypoint, in the order of the file
+    pub items: Vec<ImportedWaypoint>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ImportedWaypoint {
+    // Position of the waypoint in the file, starting at 0
+    pub index: usize,
+    pub name: Option<String>,
+    // Set when the waypoint was imported
+    pub pinpoint_id: Option<Uuid>,
+    // Why the waypoint was rejected
+    pub error: Option<String>,
+}
+
+impl ImportPinpointsResponse {
+    pub fn new(format: WaypointFormat, items: Vec<ImportedWaypoint>) -> Self {
+        let imported = items.iter().filter(|x| x.pinpoint_id.is_some()).count();
+        let rejected = items.len() - imported;
+        Self { format, imported, rejected, items }
+    }
+}
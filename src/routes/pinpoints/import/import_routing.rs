@@ -0,0 +1,69 @@
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, post, web};
+use sqlx::PgPool;
+use crate::authentication::AuthPermissions;
+use crate::domain::{Pinpoint, WaypointFile};
+use crate::routes::pinpoints::import::import_request::ImportPinpointsRequest;
+use crate::routes::pinpoints::import::import_response::{ImportPinpointsResponse, ImportedWaypoint};
+use crate::routes::pinpoints::post::PostPinpointRequest;
+use crate::routes::pinpoints::post::post_routing::insert_pinpoint;
+
+// Largest GPX or KML file accepted, in bytes
+pub const MAX_IMPORT_FILE_BYTES: usize = 5_000_000;
+
+// Creates a pinpoint for every valid waypoint of a GPX or KML file.
+// Invalid waypoints are reported back and skipped, the valid ones
+// are saved together in one transaction.
+#[tracing::instrument(
+name = "handle_import_pinpoints",
+skip(pool, path, body),
+)]
+#[post("/{username}/import")]
+pub async fn handle_import_pinpoints(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    args: web::Query<ImportPinpointsRequest>,
+    body: String,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let username = path.into_inner();
+    if auth_permissions.username != username {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let file = match WaypointFile::parse(&body) {
+        Ok(x) => x,
+        Err(e) => return HttpResponse::BadRequest().body(e)
+    };
+    let mut tran = match pool.begin().await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let mut items: Vec<ImportedWaypoint> = Vec::with_capacity(file.entries.len());
+    for (index, entry) in file.entries.into_iter().enumerate() {
+        let new_pinpoint: Result<Pinpoint, String> = entry.waypoint.and_then(|x| {
+            let mut request = PostPinpointRequest::new(
+                x.latitude.value(), x.longitude.value(), x.description, None, username.clone());
+            request.visibility = args.visibility;
+            request.try_into()
+        });
+        let new_pinpoint = match new_pinpoint {
+            Ok(x) => x,
+            Err(e) => {
+                items.push(ImportedWaypoint {
+                    index, name: entry.name, pinpoint_id: None, error: Some(e) });
+                continue;
+            }
+        };
+        // Dropping the transaction rolls back the waypoints saved so far.
+        // Waypoints have no attachments.
+        if insert_pinpoint(&mut tran, &new_pinpoint, &[]).await.is_err() {
+            return HttpResponse::InternalServerError().finish();
+        }
+        items.push(ImportedWaypoint {
+            index, name: entry.name, pinpoint_id: Some(new_pinpoint.pinpoint_id), error: None });
+    }
+    match tran.commit().await {
+        Ok(_) => HttpResponse::Ok().json(ImportPinpointsResponse::new(file.format, items)),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
@@ -0,0 +1,7 @@
+pub mod import_routing;
+mod import_request;
+mod import_response;
+
+pub use import_request::ImportPinpointsRequest;
+pub use import_response::{ImportPinpointsResponse, ImportedWaypoint};
+pub use import_routing::handle_import_pinpoints;
@@ -1,6 +1,18 @@
+pub mod comments;
 pub mod delete;
 pub mod get;
+pub mod import;
 pub mod post;
+pub mod put;
+pub mod reactions;
+pub mod stream;
 
-pub use get::get_routing::handle_get_pinpoints;
-pub use post::post_routing::handle_add_pinpoint;
\ No newline at end of file
+pub use get::get_routing::{handle_get_pinpoints, handle_get_pinpoint_clusters, handle_get_popular_tags,
+                           handle_get_pinpoint_heatmap};
+pub use post::post_routing::{handle_add_pinpoint, handle_add_pinpoint_batch};
+pub use import::import_routing::handle_import_pinpoints;
+pub use put::put_routing::handle_put_pinpoint;
+pub use stream::stream_routing::handle_get_pinpoint_stream;
+pub use reactions::reaction_routing::{handle_put_reaction, handle_delete_reaction};
+pub use comments::comment_routing::{handle_get_comments, handle_add_comment, handle_put_comment,
+                                    handle_delete_comment};
\ No newline at end of file
@@ -1,4 +1,8 @@
 pub mod post_routing;
 mod post_pinpoint_request;
+mod post_pinpoint_batch_request;
+mod post_pinpoint_batch_response;
 
 pub use post_pinpoint_request::PostPinpointRequest;
+pub use post_pinpoint_batch_request::{BatchMode, PostPinpointBatchRequest, MAX_BATCH_PINPOINTS};
+pub use post_pinpoint_batch_response::{BatchItemResult, PostPinpointBatchResponse};
@@ -0,0 +1,20 @@
+use crate::routes::pinpoints::post::post_pinpoint_request::PostPinpointRequest;
+
+pub const MAX_BATCH_PINPOINTS: usize = 100;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    // Nothing is created unless every pinpoint is valid
+    #[default]
+    AllOrNothing,
+    // Valid pinpoints are created, the others are reported back
+    BestEffort,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct PostPinpointBatchRequest {
+    #[serde(default)]
+    pub mode: BatchMode,
+    pub pinpoints: Vec<PostPinpointRequest>,
+}
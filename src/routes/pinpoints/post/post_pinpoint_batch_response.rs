@@ -0,0 +1,29 @@
+use uuid::Uuid;
+use crate::routes::pinpoints::post::post_pinpoint_batch_request::BatchMode;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct PostPinpointBatchResponse {
+    pub mode: BatchMode,
+    pub created: usize,
+    pub rejected: usize,
+    // One per requested pinpoint, in the order of the request
+    pub items: Vec<BatchItemResult>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct BatchItemResult {
+    // Position of the pinpoint in the request, starting at 0
+    pub index: usize,
+    // Set when the pinpoint was created
+    pub pinpoint_id: Option<Uuid>,
+    // Why the pinpoint was rejected
+    pub error: Option<String>,
+}
+
+impl PostPinpointBatchResponse {
+    pub fn new(mode: BatchMode, items: Vec<BatchItemResult>) -> Self {
+        let created = items.iter().filter(|x| x.pinpoint_id.is_some()).count();
+        let rejected = items.len() - created;
+        Self { mode, created, rejected, items }
+    }
+}
@@ -1,15 +1,41 @@
 use std::fmt::{Display, Formatter};
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
+use chrono::serde::ts_seconds_option;
 use uuid::Uuid;
-use crate::domain::Pinpoint;
+use crate::domain::{exif_location, Latitude, Longitude, Pinpoint, PinpointTag, PinpointVisibility};
+
+// A year, long enough for any ephemeral pinpoint and far from chrono's limits
+pub const MAX_PINPOINT_TTL_SECONDS: i64 = 365 * 24 * 60 * 60;
 
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct PostPinpointRequest {
-    pub latitude: f64,
-    pub longitude: f64,
+    // Only optional with use_photo_location, where they are the fallback
+    // for attachments without a GPS location
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
     pub description: String,
+    // Kept for older clients, the same as a single entry in attachments
     pub attachment: Option<Vec<u8>>,
-    pub username: String
+    #[serde(default)]
+    pub attachments: Vec<Vec<u8>>,
+    pub username: String,
+    // Defaults to public
+    pub visibility: Option<PinpointVisibility>,
+    // Ephemeral pinpoints give either an expiry time or a time to live, not both
+    #[serde(default, with = "ts_seconds_option")]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub ttl_seconds: Option<i64>,
+    // Normalized into PinpointTags, duplicates are dropped
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Keeps the EXIF of the attachments in a copy only the owner gets back.
+    // Everyone else always gets them without.
+    #[serde(default)]
+    pub keep_exif: bool,
+    // Places the pinpoint where the first attachment with a GPS location
+    // was taken, instead of at latitude and longitude
+    #[serde(default)]
+    pub use_photo_location: bool
 }
 
 impl PostPinpointRequest {
@@ -21,23 +47,37 @@ impl PostPinpointRequest {
         username: String
     ) -> Self {
         Self {
-            latitude,
-            longitude,
+            latitude: Some(latitude),
+            longitude: Some(longitude),
             description,
             attachment,
-            username
+            attachments: Vec::new(),
+            username,
+            visibility: None,
+            expires_at: None,
+            ttl_seconds: None,
+            tags: Vec::new(),
+            keep_exif: false,
+            use_photo_location: false
         }
     }
 }
 
 impl Display for PostPinpointRequest {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let lat = &self.latitude;
-        let lng = &self.latitude;
+        let lat = match &self.latitude {
+            Some(x) => x.to_string(),
+            None => String::from("NONE")
+        };
+        let lng = match &self.longitude {
+            Some(x) => x.to_string(),
+            None => String::from("NONE")
+        };
         let desc = &self.description;
-        let attachment = match &self.attachment {
-            None => String::from("NONE"),
-            Some(_) => String::from("SOMETHING")
+        let attachment = match (&self.attachment, self.attachments.len()) {
+            (None, 0) => String::from("NONE"),
+            (Some(_), _) => String::from("SOMETHING"),
+            (None, x) => format!("{} ATTACHMENTS", x)
         };
         let usrn = &self.username;
         write!(f, "PostPinpointRequest(lat is {}, lng is {}, desc is {}, attachment is {}, usrn is {}).",
@@ -52,13 +92,46 @@ impl TryFrom<PostPinpointRequest> for Pinpoint {
         let pinpoint_id = Uuid::new_v4();
         let contents_id = Uuid::new_v4();
         let user_id = None;
-        let attachment = value.attachment;
-        let latitude = value.latitude;
-        let longitude = value.longitude;
+        let attachments = match (value.attachment, value.attachments) {
+            (Some(_), x) if !x.is_empty() => return Err(String::from(
+                "Give either attachment or attachments, not both.")),
+            (Some(x), _) => vec![x],
+            (None, x) => x
+        };
+        // Read from the uploads as they came, before their EXIF is stripped
+        let photo_location = match value.use_photo_location {
+            true => attachments.iter().find_map(|x| exif_location(x)),
+            false => None
+        };
+        let (latitude, longitude) = match (photo_location, value.latitude, value.longitude) {
+            (Some(x), _, _) => x,
+            (None, Some(lat), Some(lng)) => (lat, lng),
+            (None, _, _) if value.use_photo_location => return Err(String::from(
+                "None of the attachments has a photo location, give latitude and longitude.")),
+            (None, _, _) => return Err(String::from("Give both latitude and longitude."))
+        };
+        let latitude = Latitude::parse(latitude)?;
+        let longitude = Longitude::parse(longitude)?;
         let description = value.description;
         let username = value.username;
         let added_at = Utc::now();
-        Ok(Self { pinpoint_id, latitude, longitude, added_at, contents_id,
-            description, attachment, username, user_id })
+        let visibility = value.visibility.unwrap_or_default();
+        let expires_at = match (value.expires_at, value.ttl_seconds) {
+            (Some(_), Some(_)) => return Err(String::from(
+                "Give either expires_at or ttl_seconds, not both.")),
+            (Some(x), None) if x > added_at => Some(x),
+            (Some(_), None) => return Err(String::from(
+                "expires_at must be in the future.")),
+            (None, Some(x)) if (1..=MAX_PINPOINT_TTL_SECONDS).contains(&x) => Some(
+                added_at.checked_add_signed(Duration::seconds(x))
+                    .ok_or_else(|| String::from("ttl_seconds is out of range."))?),
+            (None, Some(_)) => return Err(format!(
+                "ttl_seconds must be between 1 and {} seconds.", MAX_PINPOINT_TTL_SECONDS)),
+            (None, None) => None
+        };
+        let tags = PinpointTag::parse_set(&value.tags)?;
+        Ok(Self { pinpoint_id, latitude, longitude, added_at, modified_at: None, expires_at,
+            contents_id, description, attachments, variants: Vec::new(), username, user_id, visibility, distance: None,
+            rank: None, tags })
     }
 }
\ No newline at end of file
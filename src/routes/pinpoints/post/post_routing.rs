@@ -1,71 +1,298 @@
-use std::string::FromUtf8Error;
 use actix_web::{HttpMessage, HttpRequest, HttpResponse, web};
-use sqlx::PgPool;
-use crate::domain::Pinpoint;
-use crate::authentication::{AuthParameters, AuthPermissions, AuthService};
+use sqlx::{Connection, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::domain::{normalize_images, normalize_images_blocking, Pinpoint, PinpointEvent,
+                    PinpointEventKind};
+use crate::authentication::AuthPermissions;
+use crate::blob_storage::{discard_attachments, store_attachments, BlobStorage, StoredAttachment};
 use crate::routes::pinpoints::post::post_pinpoint_request::PostPinpointRequest;
+use crate::routes::pinpoints::post::post_pinpoint_batch_request::{
+    BatchMode, PostPinpointBatchRequest, MAX_BATCH_PINPOINTS};
+use crate::routes::pinpoints::post::post_pinpoint_batch_response::{
+    BatchItemResult, PostPinpointBatchResponse};
+use crate::pinpoint_events::notify_pinpoint_event;
+use crate::startup::MaxPinpointAttachments;
+use crate::telemetry::spawn_blocking_with_tracing;
 
 #[tracing::instrument(
 name = "handle_add_pinpoint",
-skip(pool),
+skip(pool, storage, max_attachments),
 )]
 pub async fn handle_add_pinpoint(
     req: HttpRequest,
     pinpoint: web::Json<PostPinpointRequest>,
     // Retrieving a connection from the application state
     pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
+    max_attachments: web::Data<MaxPinpointAttachments>,
 ) -> HttpResponse {
     // 'web::Json' is a wrapper around 'PostPinpointRequest'
     // 'pinpoint.0' gives us access to the underlying 'PostPinpointRequest'
     // You can use e.g. PostPinpointRequest::try_from(pinpoint.0);
-    let new_pinpoint: Pinpoint = match pinpoint.0.try_into() {
-        Ok(pinpoint) => pinpoint,
-        Err(_) => return HttpResponse::BadRequest().finish(),
-    };
-    let req_ext = req.extensions_mut();
-    let auth_permissions: &AuthPermissions = req_ext.get::<AuthPermissions>().unwrap();
+    // Checked before the attachments are decoded, so others cannot make us do the work
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
     println!("AuthPermissions found as {:?}", auth_permissions);
-    if auth_permissions.username != new_pinpoint.username {
+    if auth_permissions.username != pinpoint.username {
         return HttpResponse::Unauthorized().finish();
     }
-    match insert_pinpoint(&pool, &new_pinpoint).await {
-        Ok(_) => HttpResponse::Ok().finish(),
+    let keep_exif = pinpoint.keep_exif;
+    let mut new_pinpoint: Pinpoint = match pinpoint.0.try_into() {
+        Ok(pinpoint) => pinpoint,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    if new_pinpoint.attachments.len() > max_attachments.0 {
+        return HttpResponse::BadRequest().body(
+            format!("A pinpoint can have at most {} attachments.", max_attachments.0));
+    }
+    let attachments = std::mem::take(&mut new_pinpoint.attachments);
+    (new_pinpoint.attachments, new_pinpoint.variants) = match normalize_images_blocking(attachments, keep_exif).await {
+        Ok(Ok(x)) => x.into_iter().unzip(),
+        Ok(Err(e)) => return HttpResponse::BadRequest().body(e),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let mut tran = match pool.begin().await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let stored = match store_attachments(storage.get_ref(),
+                                         std::mem::take(&mut new_pinpoint.attachments),
+                                         std::mem::take(&mut new_pinpoint.variants)).await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    match insert_pinpoint(&mut tran, &new_pinpoint, &stored).await {
+        Ok(_) => {
+            match tran.commit().await {
+                Ok(_) => HttpResponse::Ok().finish(),
+                Err(_) => HttpResponse::InternalServerError().finish()
+            }
+        },
+        Err(_) => {
+            discard_attachments(storage.get_ref(), &stored).await;
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// Creates several pinpoints of the signed in user in one transaction,
+// for clients replaying pinpoints queued while offline.
+#[tracing::instrument(
+name = "handle_add_pinpoint_batch",
+skip(batch, pool, storage, max_attachments),
+)]
+pub async fn handle_add_pinpoint_batch(
+    req: HttpRequest,
+    batch: web::Json<PostPinpointBatchRequest>,
+    pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
+    max_attachments: web::Data<MaxPinpointAttachments>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let username = auth_permissions.username.clone();
+    let batch = batch.into_inner();
+    if batch.pinpoints.is_empty() {
+        return HttpResponse::BadRequest().body("A batch needs at least one pinpoint.");
+    }
+    if batch.pinpoints.len() > MAX_BATCH_PINPOINTS {
+        return HttpResponse::BadRequest().body(
+            format!("A batch can have at most {} pinpoints.", MAX_BATCH_PINPOINTS));
+    }
+    let mode = batch.mode;
+    let max_attachments = max_attachments.0;
+    // Attachments are decoded, so the whole batch is parsed on the blocking thread pool
+    let parsed = spawn_blocking_with_tracing(move || {
+        let mut items: Vec<BatchItemResult> = Vec::with_capacity(batch.pinpoints.len());
+        let mut new_pinpoints: Vec<(usize, Pinpoint)> = Vec::with_capacity(batch.pinpoints.len());
+        for (index, pinpoint) in batch.pinpoints.into_iter().enumerate() {
+            match parse_batch_pinpoint(pinpoint, &username, max_attachments) {
+                Ok(x) => new_pinpoints.push((index, x)),
+                Err(e) => items.push(BatchItemResult { index, pinpoint_id: None, error: Some(e) })
+            }
+        }
+        (items, new_pinpoints)
+    }).await;
+    let (mut items, new_pinpoints) = match parsed {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    if mode == BatchMode::AllOrNothing && !items.is_empty() {
+        for (index, _) in new_pinpoints {
+            items.push(BatchItemResult { index, pinpoint_id: None, error: Some(String::from(
+                "Not created because other pinpoints of the batch were rejected.")) });
+        }
+        items.sort_by_key(|x| x.index);
+        return HttpResponse::BadRequest().json(PostPinpointBatchResponse::new(mode, items));
+    }
+
+    let mut tran = match pool.begin().await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    // Every attachment stored for the pinpoints inserted so far
+    let mut batch_stored: Vec<StoredAttachment> = Vec::new();
+    for (index, mut new_pinpoint) in new_pinpoints {
+        let stored = match store_attachments(storage.get_ref(),
+                                             std::mem::take(&mut new_pinpoint.attachments),
+                                             std::mem::take(&mut new_pinpoint.variants)).await {
+            Ok(x) => x,
+            Err(_) if mode == BatchMode::BestEffort => {
+                items.push(BatchItemResult {
+                    index, pinpoint_id: None, error: Some(String::from("Failed to save the pinpoint.")) });
+                continue;
+            },
+            Err(_) => {
+                discard_attachments(storage.get_ref(), &batch_stored).await;
+                return HttpResponse::InternalServerError().finish();
+            }
+        };
+        let inserted = match mode {
+            BatchMode::AllOrNothing => insert_pinpoint(&mut tran, &new_pinpoint, &stored).await,
+            // A savepoint keeps one failed insert from aborting the others
+            BatchMode::BestEffort => match tran.begin().await {
+                Ok(mut savepoint) => match insert_pinpoint(&mut savepoint, &new_pinpoint, &stored).await {
+                    Ok(_) => savepoint.commit().await,
+                    Err(e) => Err(e)
+                },
+                Err(e) => Err(e)
+            }
+        };
+        match inserted {
+            Ok(_) => {
+                items.push(BatchItemResult {
+                    index, pinpoint_id: Some(new_pinpoint.pinpoint_id), error: None });
+                batch_stored.extend(stored);
+            },
+            Err(_) if mode == BatchMode::BestEffort => {
+                discard_attachments(storage.get_ref(), &stored).await;
+                items.push(BatchItemResult {
+                    index, pinpoint_id: None, error: Some(String::from("Failed to save the pinpoint.")) });
+            },
+            Err(_) => {
+                batch_stored.extend(stored);
+                discard_attachments(storage.get_ref(), &batch_stored).await;
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    }
+    match tran.commit().await {
+        Ok(_) => {
+            items.sort_by_key(|x| x.index);
+            HttpResponse::Ok().json(PostPinpointBatchResponse::new(mode, items))
+        },
         Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
+// The same checks handle_add_pinpoint makes, as an error message instead of a response
+fn parse_batch_pinpoint(
+    pinpoint: PostPinpointRequest,
+    username: &str,
+    max_attachments: usize,
+) -> Result<Pinpoint, String> {
+    // Checked before the attachments are decoded, so others cannot make us do the work
+    if pinpoint.username != username {
+        return Err(String::from("Pinpoints can only be posted as the signed in user."));
+    }
+    let keep_exif = pinpoint.keep_exif;
+    let mut new_pinpoint: Pinpoint = pinpoint.try_into()?;
+    if new_pinpoint.attachments.len() > max_attachments {
+        return Err(format!("A pinpoint can have at most {} attachments.", max_attachments));
+    }
+    (new_pinpoint.attachments, new_pinpoint.variants) =
+        normalize_images(std::mem::take(&mut new_pinpoint.attachments), keep_exif)?.into_iter().unzip();
+    Ok(new_pinpoint)
+}
+
+// The description and first attachment share the contents row at position 0,
+// every further attachment gets its own contents row at the next position.
+// The attachments are already in blob storage, stored holds them in order
+// and the rows only their keys. Attachments without variants have them left NULL.
+// Live streams are told about the pinpoint once the transaction commits.
 pub async fn insert_pinpoint(
-    pool: &PgPool,
+    tran: &mut Transaction<'_, Postgres>,
     new_pinpoint: &Pinpoint,
+    stored: &[StoredAttachment],
 ) -> Result<(), sqlx::Error> {
+    let first = stored.first();
+    let extra = stored.get(1..).unwrap_or_default();
+    let extra_contents_ids: Vec<Uuid> = extra.iter()
+        .map(|_| Uuid::new_v4())
+        .collect();
+    let extra_keys: Vec<String> = extra.iter().map(|x| x.full.key.clone()).collect();
+    let extra_small: Vec<Option<String>> = extra.iter().map(|x| x.small_key.clone()).collect();
+    let extra_medium: Vec<Option<String>> = extra.iter().map(|x| x.medium_key.clone()).collect();
+    let extra_with_exif: Vec<Option<String>> = extra.iter().map(|x| x.with_exif_key.clone()).collect();
+    let extra_sizes: Vec<i64> = extra.iter().map(|x| x.full.size).collect();
+    let extra_sha256: Vec<String> = extra.iter().map(|x| x.full.sha256.clone()).collect();
+    let extra_mime: Vec<String> = extra.iter().map(|x| x.full.mime_type.clone()).collect();
     sqlx::query!(
         r#"
 WITH pin AS (
-INSERT INTO pinpoints (id, latitude, longitude)
-VALUES ($1, $2, $3)
+INSERT INTO pinpoints (id, latitude, longitude, visibility, expires_at)
+VALUES ($1, $2, $3, $8, $9)
 RETURNING id
 ),
 con as (
-    INSERT INTO contents (id, description, attachment)
-    VALUES($4, $5, $6)
+    INSERT INTO contents (id, description, attachment_key, attachment_small_key,
+        attachment_medium_key, attachment_with_exif_key, attachment_size, attachment_sha256,
+        attachment_mime)
+    VALUES($4, $5, $6, $13, $14, $17, $19, $20, $21)
     RETURNING id
 ),
 usr_pin as (
     INSERT INTO user_pinpoints (pinpoint_id, user_id)
     SELECT id, (SELECT id FROM users WHERE username = $7) FROM pin
+),
+tag as (
+    INSERT INTO tags (name)
+    SELECT UNNEST($10::TEXT[])
+    ON CONFLICT (name) DO UPDATE SET name = EXCLUDED.name
+    RETURNING id
+),
+pin_tag as (
+    INSERT INTO pinpoint_tags (pinpoint_id, tag_id)
+    SELECT pin.id, tag.id FROM pin, tag
+),
+extra_con as (
+    INSERT INTO contents (id, attachment_key, attachment_small_key, attachment_medium_key,
+        attachment_with_exif_key, attachment_size, attachment_sha256, attachment_mime)
+    SELECT * FROM UNNEST($11::UUID[], $12::TEXT[], $15::TEXT[], $16::TEXT[], $18::TEXT[],
+        $22::BIGINT[], $23::TEXT[], $24::TEXT[])
+),
+extra_pin_con as (
+    INSERT INTO pinpoint_contents (pinpoint_id, content_id, position)
+    SELECT pin.id, extra.id, extra.position
+    FROM pin, UNNEST($11::UUID[]) WITH ORDINALITY AS extra(id, position)
 )
 INSERT INTO pinpoint_contents (pinpoint_id, content_id)
 SELECT pin.id, con.id FROM pin, con
         "#,
         new_pinpoint.pinpoint_id,
-        new_pinpoint.latitude,
-        new_pinpoint.longitude,
+        new_pinpoint.latitude.value(),
+        new_pinpoint.longitude.value(),
         new_pinpoint.contents_id,
         new_pinpoint.description,
-        new_pinpoint.attachment,
-        new_pinpoint.username
+        first.map(|x| &x.full.key),
+        new_pinpoint.username,
+        new_pinpoint.visibility.as_str(),
+        new_pinpoint.expires_at,
+        &new_pinpoint.tags.iter().map(|x| x.to_string()).collect::<Vec<String>>(),
+        &extra_contents_ids,
+        &extra_keys,
+        first.and_then(|x| x.small_key.as_ref()),
+        first.and_then(|x| x.medium_key.as_ref()),
+        &extra_small as &[Option<String>],
+        &extra_medium as &[Option<String>],
+        first.and_then(|x| x.with_exif_key.as_ref()),
+        &extra_with_exif as &[Option<String>],
+        first.map(|x| x.full.size),
+        first.map(|x| &x.full.sha256),
+        first.map(|x| &x.full.mime_type),
+        &extra_sizes,
+        &extra_sha256,
+        &extra_mime
     )
-        .execute(pool)
+        .execute(&mut *tran)
         .await
         .map_err(|e| {
             tracing::error!("Failed to execute query: {:?}", e);
@@ -73,5 +300,15 @@ SELECT pin.id, con.id FROM pin, con
             // Using the '?' operator to return early
             // if the function failed, returning a sqlx::Error.
         })?;
+
+    notify_pinpoint_event(&mut *tran, &PinpointEvent {
+        kind: PinpointEventKind::Created,
+        pinpoint_id: new_pinpoint.pinpoint_id,
+        latitude: new_pinpoint.latitude.value(),
+        longitude: new_pinpoint.longitude.value(),
+        owner: new_pinpoint.username.clone(),
+        visibility: new_pinpoint.visibility,
+        previous: None,
+    }).await?;
     Ok(())
 }
\ No newline at end of file
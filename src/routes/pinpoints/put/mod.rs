@@ -0,0 +1,5 @@
+pub mod put_routing;
+mod put_pinpoint_request;
+
+pub use put_pinpoint_request::PutPinpointRequest;
+pub use put_routing::handle_put_pinpoint;
@@ -0,0 +1,32 @@
+use crate::domain::{Latitude, Longitude, PinpointVisibility};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct PutPinpointRequest {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub description: Option<String>,
+    // Replaces the first attachment
+    pub attachment: Option<Vec<u8>>,
+    pub visibility: Option<PinpointVisibility>,
+    // Whether the new attachment keeps its EXIF for the owner, as when posting
+    #[serde(default)]
+    pub keep_exif: bool
+}
+
+impl PutPinpointRequest {
+    pub fn is_empty(&self) -> bool {
+        self.latitude.is_none()
+        && self.longitude.is_none()
+        && self.description.is_none()
+        && self.attachment.is_none()
+        && self.visibility.is_none()
+    }
+
+    pub fn parse_latitude(&self) -> Result<Option<Latitude>, String> {
+        self.latitude.map(Latitude::parse).transpose()
+    }
+
+    pub fn parse_longitude(&self) -> Result<Option<Longitude>, String> {
+        self.longitude.map(Longitude::parse).transpose()
+    }
+}
@@ -0,0 +1,201 @@
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, put, web};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use crate::authentication::AuthPermissions;
+use crate::blob_storage::{discard_attachments, store_attachments, BlobStorage, StoredAttachment};
+use crate::domain::{normalize_images_blocking, Latitude, Longitude, PinpointEvent, PinpointEventKind,
+                    PinpointEventPrevious, PinpointVisibility};
+use crate::pinpoint_events::notify_pinpoint_event;
+use crate::routes::pinpoints::put::put_pinpoint_request::PutPinpointRequest;
+
+#[tracing::instrument(
+name = "handle_put_pinpoint",
+skip(pool, storage, path, args)
+)]
+#[put("/{pinpoint_id}")]
+pub async fn handle_put_pinpoint(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
+    path: web::Path<Uuid>,
+    args: web::Json<PutPinpointRequest>
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let pinpoint_id = path.into_inner();
+    if args.is_empty() {
+        return HttpResponse::BadRequest().finish();
+    }
+    let latitude = match args.parse_latitude() {
+        Ok(x) => x,
+        Err(e) => return HttpResponse::BadRequest().body(e)
+    };
+    let longitude = match args.parse_longitude() {
+        Ok(x) => x,
+        Err(e) => return HttpResponse::BadRequest().body(e)
+    };
+    let owner = match get_db_pinpoint_owner(&pool, pinpoint_id).await {
+        Ok(Some(x)) => x,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    if auth_permissions.username != owner {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let mut args = args.into_inner();
+    let mut normalized = None;
+    if let Some(attachment) = args.attachment.take() {
+        normalized = match normalize_images_blocking(vec![attachment], args.keep_exif).await {
+            Ok(Ok(x)) => Some(x.into_iter().unzip()),
+            Ok(Err(e)) => return HttpResponse::BadRequest().body(e),
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        };
+    }
+    let mut tran = match pool.begin().await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let stored = match normalized {
+        Some((attachments, variants)) => match store_attachments(storage.get_ref(), attachments, variants).await {
+            Ok(x) => x.into_iter().next(),
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        },
+        None => None
+    };
+    match modify_db_pinpoint(&mut tran, pinpoint_id, latitude, longitude, &args,
+                             stored.as_ref()).await {
+        Ok(_) => {
+            match tran.commit().await {
+                Ok(_) => HttpResponse::Ok().finish(),
+                Err(_) => HttpResponse::InternalServerError().finish()
+            }
+        },
+        Err(_) => {
+            discard_attachments(storage.get_ref(), stored.as_slice()).await;
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+// Username of the user who posted the pinpoint, if the pinpoint exists
+pub async fn get_db_pinpoint_owner(
+    pool: &PgPool,
+    pinpoint_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT usr.username
+        FROM user_pinpoints usr_pin
+        INNER JOIN users usr ON usr.id = usr_pin.user_id
+        WHERE usr_pin.pinpoint_id = $1;
+        "#,
+        pinpoint_id
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(row.map(|x| x.username))
+}
+
+// Fields missing from the request are left as they are.
+// The pinpoint keeps its id and added_at, and gets a new modified_at.
+// A new first attachment, already in blob storage, replaces its other variants too.
+// Live streams are told about the edit once the transaction commits.
+pub async fn modify_db_pinpoint(
+    tran: &mut Transaction<'_, Postgres>,
+    pinpoint_id: Uuid,
+    latitude: Option<Latitude>,
+    longitude: Option<Longitude>,
+    args: &PutPinpointRequest,
+    attachment: Option<&StoredAttachment>,
+) -> Result<(), sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        WITH old AS (
+            SELECT id, latitude, longitude, visibility
+            FROM pinpoints
+            WHERE id = $1
+            FOR UPDATE
+        )
+        UPDATE pinpoints pin
+        SET latitude = COALESCE($2, pin.latitude),
+        longitude = COALESCE($3, pin.longitude),
+        visibility = COALESCE($4, pin.visibility),
+        modified_at = clock_timestamp()
+        FROM old
+        WHERE pin.id = old.id
+        RETURNING pin.latitude, pin.longitude, pin.visibility,
+        old.latitude AS "previous_latitude!", old.longitude AS "previous_longitude!",
+        old.visibility AS "previous_visibility!",
+        (SELECT usr.username FROM user_pinpoints usr_pin
+            INNER JOIN users usr ON usr.id = usr_pin.user_id
+            WHERE usr_pin.pinpoint_id = $1) AS "owner!";
+        "#,
+        pinpoint_id,
+        latitude.map(|x| x.value()),
+        longitude.map(|x| x.value()),
+        args.visibility.map(|x| x.as_str())
+    )
+        .fetch_one(&mut *tran)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+
+    if args.description.is_some() || attachment.is_some() {
+        sqlx::query!(
+            r#"
+            UPDATE contents
+            SET description = COALESCE($2, description),
+            attachment_key = COALESCE($3, attachment_key),
+            attachment_small_key = CASE WHEN $3::TEXT IS NULL THEN attachment_small_key ELSE $4 END,
+            attachment_medium_key = CASE WHEN $3::TEXT IS NULL THEN attachment_medium_key ELSE $5 END,
+            attachment_with_exif_key = CASE WHEN $3::TEXT IS NULL THEN attachment_with_exif_key ELSE $6 END,
+            attachment_size = COALESCE($7, attachment_size),
+            attachment_sha256 = COALESCE($8, attachment_sha256),
+            attachment_mime = COALESCE($9, attachment_mime)
+            WHERE id IN (
+                SELECT content_id FROM pinpoint_contents
+                WHERE pinpoint_id = $1 AND position = 0
+            );
+            "#,
+            pinpoint_id,
+            args.description,
+            attachment.map(|x| &x.full.key),
+            attachment.and_then(|x| x.small_key.as_ref()),
+            attachment.and_then(|x| x.medium_key.as_ref()),
+            attachment.and_then(|x| x.with_exif_key.as_ref()),
+            attachment.map(|x| x.full.size),
+            attachment.map(|x| &x.full.sha256),
+            attachment.map(|x| &x.full.mime_type)
+        )
+            .execute(&mut *tran)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                e
+            })?;
+    }
+
+    let visibility = PinpointVisibility::parse(&row.visibility)
+        .map_err(|e| sqlx::Error::Decode(e.into()))?;
+    let previous_visibility = PinpointVisibility::parse(&row.previous_visibility)
+        .map_err(|e| sqlx::Error::Decode(e.into()))?;
+    notify_pinpoint_event(&mut *tran, &PinpointEvent {
+        kind: PinpointEventKind::Updated,
+        pinpoint_id,
+        latitude: row.latitude,
+        longitude: row.longitude,
+        owner: row.owner,
+        visibility,
+        previous: Some(PinpointEventPrevious {
+            latitude: row.previous_latitude,
+            longitude: row.previous_longitude,
+            visibility: previous_visibility,
+        }),
+    }).await?;
+    Ok(())
+}
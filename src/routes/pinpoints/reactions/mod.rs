@@ -0,0 +1,5 @@
+pub mod reaction_routing;
+mod reaction_request;
+
+pub use reaction_request::PutReactionRequest;
+pub use reaction_routing::{handle_put_reaction, handle_delete_reaction};
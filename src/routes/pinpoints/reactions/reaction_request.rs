@@ -0,0 +1,12 @@
+use crate::domain::PinpointReaction;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct PutReactionRequest {
+    pub reaction: PinpointReaction
+}
+
+impl PutReactionRequest {
+    pub fn new(reaction: PinpointReaction) -> Self {
+        Self { reaction }
+    }
+}
@@ -0,0 +1,104 @@
+use actix_web::{delete, HttpMessage, HttpRequest, HttpResponse, put, web};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::authentication::AuthPermissions;
+use crate::domain::PinpointReaction;
+use crate::routes::pinpoints::get::get_routing::is_db_pinpoint_visible;
+use crate::routes::pinpoints::reactions::reaction_request::PutReactionRequest;
+
+#[tracing::instrument(
+name = "handle_put_reaction",
+skip(pool, path, args)
+)]
+#[put("/{pinpoint_id}/reaction")]
+pub async fn handle_put_reaction(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    args: web::Json<PutReactionRequest>
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let pinpoint_id = path.into_inner();
+    match is_db_pinpoint_visible(&pool, pinpoint_id, &auth_permissions.username).await {
+        Ok(true) => {},
+        Ok(false) => return HttpResponse::NotFound().finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    match upsert_db_reaction(&pool, pinpoint_id, &auth_permissions.username, args.reaction).await {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[tracing::instrument(
+name = "handle_delete_reaction",
+skip(pool, path)
+)]
+#[delete("/{pinpoint_id}/reaction")]
+pub async fn handle_delete_reaction(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let pinpoint_id = path.into_inner();
+    match delete_db_reaction(&pool, pinpoint_id, &auth_permissions.username).await {
+        Ok(0) => HttpResponse::NotFound().finish(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+// A user has at most one reaction on a pinpoint,
+// so reacting again replaces the previous one.
+pub async fn upsert_db_reaction(
+    pool: &PgPool,
+    pinpoint_id: Uuid,
+    username: &str,
+    reaction: PinpointReaction,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO pinpoint_reactions (pinpoint_id, user_id, reaction)
+        SELECT $1, usr.id, $3
+        FROM users usr
+        WHERE usr.username = $2
+        ON CONFLICT (pinpoint_id, user_id)
+        DO UPDATE SET reaction = EXCLUDED.reaction, added_at = clock_timestamp();
+        "#,
+        pinpoint_id,
+        username,
+        reaction.as_str()
+    )
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(())
+}
+
+// Returns how many reactions were removed, 0 if the user had not reacted
+pub async fn delete_db_reaction(
+    pool: &PgPool,
+    pinpoint_id: Uuid,
+    username: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM pinpoint_reactions
+        WHERE pinpoint_id = $1
+        AND user_id IN (SELECT id FROM users WHERE username = $2);
+        "#,
+        pinpoint_id,
+        username
+    )
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(result.rows_affected())
+}
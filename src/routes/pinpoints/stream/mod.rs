@@ -0,0 +1,7 @@
+pub mod stream_routing;
+mod stream_request;
+mod stream_message;
+
+pub use stream_request::GetPinpointStreamRequest;
+pub use stream_message::PinpointStreamMessage;
+pub use stream_routing::handle_get_pinpoint_stream;
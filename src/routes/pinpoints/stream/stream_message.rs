@@ -0,0 +1,13 @@
+use uuid::Uuid;
+use crate::routes::pinpoints::get::GetPinpointResponse;
+
+// Data of one server-sent event, whose event name is the
+// PinpointEventKind: created, updated or deleted.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct PinpointStreamMessage {
+    pub pinpoint_id: Uuid,
+    pub latitude: f64,
+    pub longitude: f64,
+    // The pinpoint as /pinpoints/{username} would list it, absent for deletions
+    pub pinpoint: Option<GetPinpointResponse>,
+}
@@ -0,0 +1,45 @@
+use std::fmt::{Display, Formatter};
+use crate::domain::{Latitude, Longitude};
+
+// The viewport a live stream is subscribed to, the same way as GetClustersRequest.
+// A min_longitude greater than max_longitude means the box
+// crosses the antimeridian.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct GetPinpointStreamRequest {
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64
+}
+
+impl GetPinpointStreamRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        Latitude::parse(self.min_latitude)?;
+        Latitude::parse(self.max_latitude)?;
+        Longitude::parse(self.min_longitude)?;
+        Longitude::parse(self.max_longitude)?;
+        if self.min_latitude > self.max_latitude {
+            return Err(String::from("min_latitude must not be greater than max_latitude."));
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        let in_latitude = latitude >= self.min_latitude && latitude <= self.max_latitude;
+        let in_longitude = if self.min_longitude <= self.max_longitude {
+            longitude >= self.min_longitude && longitude <= self.max_longitude
+        }
+        else {
+            longitude >= self.min_longitude || longitude <= self.max_longitude
+        };
+        in_latitude && in_longitude
+    }
+}
+
+impl Display for GetPinpointStreamRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GetPinpointStreamRequest(lat is {} to {}, lng is {} to {}).",
+               self.min_latitude, self.max_latitude,
+               self.min_longitude, self.max_longitude)
+    }
+}
@@ -0,0 +1,200 @@
+use std::time::Duration;
+use actix_web::{get, HttpMessage, HttpRequest, HttpResponse, Responder, web};
+use actix_web_lab::sse;
+use sqlx::PgPool;
+use tokio::sync::broadcast::error::RecvError;
+use crate::authentication::AuthPermissions;
+use crate::blob_storage::BlobStorage;
+use crate::domain::{LocationPrecision, PinpointEvent, PinpointEventKind, PinpointVisibility, PrivateZoneMode};
+use crate::pinpoint_events::PinpointEvents;
+use crate::routes::pinpoints::get::{GetPinpointRequest, PinpointSort};
+use crate::routes::pinpoints::get::get_routing::{
+    add_db_reactions, censor_pinpoints_by_username, convert_to_pinpoint_response,
+    get_db_pinpoints, get_db_private_zone, is_db_visible_by_owner, read_db_pinpoints};
+use crate::routes::pinpoints::stream::stream_message::PinpointStreamMessage;
+use crate::routes::pinpoints::stream::stream_request::GetPinpointStreamRequest;
+
+// How many messages may wait for a slow client before events are dropped
+pub const STREAM_BUFFER: usize = 64;
+pub const STREAM_KEEP_ALIVE_SECONDS: u64 = 15;
+
+// Server-sent events for pinpoints created, edited or deleted in the viewport,
+// as the requesting user would see them in /pinpoints/{username}.
+// An edit taking a pinpoint away from the viewer or out of the viewport comes as a deletion.
+#[tracing::instrument(
+name = "handle_get_pinpoint_stream",
+skip(pool, storage, path, args, events, precision),
+)]
+#[get("/{username}/stream")]
+pub async fn handle_get_pinpoint_stream(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
+    path: web::Path<String>,
+    args: web::Query<GetPinpointStreamRequest>,
+    events: web::Data<PinpointEvents>,
+    precision: web::Data<LocationPrecision>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let user_requesting = path.into_inner();
+    if auth_permissions.username != user_requesting {
+        return HttpResponse::Unauthorized().finish();
+    }
+    if let Err(e) = args.validate() {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    let mut receiver = events.0.subscribe();
+    let (sender, stream) = sse::channel(STREAM_BUFFER);
+    let pool = pool.into_inner();
+    let storage = storage.into_inner();
+    let viewport = args.into_inner();
+    let precision = **precision;
+    actix_web::rt::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(x) => x,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Pinpoint stream skipped {} events", skipped);
+                    continue;
+                },
+                Err(RecvError::Closed) => break
+            };
+            // Only the owner's own pinpoints are shown where they are. Others may be
+            // shown inside the viewport while they are outside it or the other way
+            // round, so they are only filtered once their message is ready.
+            let was_inside = event.previous
+                .is_some_and(|x| viewport.contains(x.latitude, x.longitude));
+            if event.owner == user_requesting && !was_inside
+                && !viewport.contains(event.latitude, event.longitude) {
+                continue;
+            }
+            let message = match get_stream_message(
+                &pool, storage.as_ref(), &event, &user_requesting, precision).await {
+                Ok(x) => x,
+                Err(e) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to get the stream message of pinpoint {}", event.pinpoint_id
+                    );
+                    continue;
+                }
+            };
+            // Where the viewer is shown the pinpoint decides, as in /pinpoints/{username}
+            let (kind, message) = match message {
+                Some(x) if viewport.contains(x.latitude, x.longitude) => (event.kind, x),
+                // An edit that takes the pinpoint away from the viewer, or out of
+                // the viewport, removes it where the viewer was shown it before
+                _ => match get_removal_message(
+                    &pool, &event, &user_requesting, precision).await {
+                    Ok(Some(x)) if viewport.contains(x.latitude, x.longitude) =>
+                        (PinpointEventKind::Deleted, x),
+                    Ok(_) => continue,
+                    Err(e) => {
+                        tracing::error!(
+                            error.cause_chain = ?e,
+                            error.message = %e,
+                            "Failed to get the removal message of pinpoint {}", event.pinpoint_id
+                        );
+                        continue;
+                    }
+                }
+            };
+            let data = match sse::Data::new_json(&message) {
+                Ok(x) => x.event(kind.as_str()),
+                Err(e) => {
+                    tracing::error!("Failed to serialize a pinpoint stream message: {:?}", e);
+                    continue;
+                }
+            };
+            // The client has gone away
+            if sender.send(data).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    stream
+        .with_keep_alive(Duration::from_secs(STREAM_KEEP_ALIVE_SECONDS))
+        .respond_to(&req)
+        .map_into_boxed_body()
+}
+
+// None when the viewer may not see the pinpoint
+pub async fn get_stream_message(
+    pool: &PgPool,
+    storage: &dyn BlobStorage,
+    event: &PinpointEvent,
+    viewer: &str,
+    precision: LocationPrecision,
+) -> Result<Option<PinpointStreamMessage>, anyhow::Error> {
+    if event.kind == PinpointEventKind::Deleted {
+        return get_shown_removal_message(
+            pool, event, event.latitude, event.longitude, event.visibility, viewer, precision).await;
+    }
+
+    // Streams feed the map, so attachments come as the default small thumbnails
+    let args = GetPinpointRequest { pinpoint_id: Some(event.pinpoint_id), ..Default::default() };
+    let rows = get_db_pinpoints(
+        pool, &args, viewer, PinpointSort::Newest, None, 1, precision).await?;
+    let pinpoints = read_db_pinpoints(storage, &rows).await?;
+    let mut response_pinpoints = convert_to_pinpoint_response(pinpoints)?;
+    add_db_reactions(pool, &mut response_pinpoints, viewer).await?;
+    let pinpoint = censor_pinpoints_by_username(&response_pinpoints, viewer, precision)
+        .into_iter()
+        .next();
+    match pinpoint {
+        None => Ok(None),
+        Some(x) => Ok(Some(PinpointStreamMessage {
+            pinpoint_id: event.pinpoint_id,
+            latitude: x.latitude,
+            longitude: x.longitude,
+            pinpoint: Some(x),
+        }))
+    }
+}
+
+// The message removing an edited pinpoint, None unless the viewer
+// could see it before the edit
+pub async fn get_removal_message(
+    pool: &PgPool,
+    event: &PinpointEvent,
+    viewer: &str,
+    precision: LocationPrecision,
+) -> Result<Option<PinpointStreamMessage>, anyhow::Error> {
+    match event.previous {
+        Some(x) => get_shown_removal_message(
+            pool, event, x.latitude, x.longitude, x.visibility, viewer, precision).await,
+        None => Ok(None)
+    }
+}
+
+// Without the pinpoint, at where the viewer is shown it at that location and
+// visibility. None when the viewer may not see it there.
+async fn get_shown_removal_message(
+    pool: &PgPool,
+    event: &PinpointEvent,
+    latitude: f64,
+    longitude: f64,
+    visibility: PinpointVisibility,
+    viewer: &str,
+    precision: LocationPrecision,
+) -> Result<Option<PinpointStreamMessage>, anyhow::Error> {
+    if !is_db_visible_by_owner(pool, &event.owner, visibility, viewer).await? {
+        return Ok(None);
+    }
+    let zone = get_db_private_zone(pool, &event.owner, latitude, longitude, viewer).await?;
+    let (latitude, longitude) = match zone {
+        Some((_, _, PrivateZoneMode::Hide)) => return Ok(None),
+        Some((x, y, PrivateZoneMode::Mask)) => precision.obscure(x, y),
+        None if event.owner == viewer => (latitude, longitude),
+        None => precision.obscure(latitude, longitude)
+    };
+    Ok(Some(PinpointStreamMessage {
+        pinpoint_id: event.pinpoint_id,
+        latitude,
+        longitude,
+        pinpoint: None,
+    }))
+}
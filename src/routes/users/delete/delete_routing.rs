@@ -16,8 +16,7 @@ pub async fn handle_delete_user(
     if username.is_empty() {
         return HttpResponse::BadRequest().finish();
     }
-    let req_ext = req.extensions_mut();
-    let auth_permissions: &AuthPermissions = req_ext.get::<AuthPermissions>().unwrap();
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
     if auth_permissions.username != username {
         return HttpResponse::Unauthorized().finish();
     }
@@ -0,0 +1,21 @@
+use chrono::{DateTime, Utc};
+use chrono::serde::{ts_seconds, ts_seconds_option};
+use uuid::Uuid;
+use crate::domain::DataExportStatus;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct DataExportResponse {
+    pub export_id: Uuid,
+    pub status: DataExportStatus,
+    #[serde(with = "ts_seconds")]
+    pub requested_at: DateTime<Utc>,
+    #[serde(with = "ts_seconds_option")]
+    pub finished_at: Option<DateTime<Utc>>,
+    // When the archive is deleted, once the export has finished
+    #[serde(with = "ts_seconds_option")]
+    pub expires_at: Option<DateTime<Utc>>,
+    // Where to poll for progress
+    pub status_url: String,
+    // Where to fetch the zip archive, once the export is done
+    pub download_url: Option<String>,
+}
@@ -0,0 +1,244 @@
+use actix_web::{get, post, HttpMessage, HttpRequest, HttpResponse, web};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType, LOCATION};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::authentication::AuthPermissions;
+use crate::blob_storage::BlobStorage;
+use crate::domain::DataExportStatus;
+use crate::routes::users::export::export_response::DataExportResponse;
+use crate::startup::ApplicationBaseUrl;
+
+pub const EXPORT_ARCHIVE_MIME: &str = "application/zip";
+
+pub struct DbDataExport {
+    pub export_id: Uuid,
+    pub status: String,
+    pub requested_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+// Queues a personal data export, built in the background by the export worker.
+// While one is still pending it is returned instead of queuing another.
+#[tracing::instrument(
+name = "handle_post_export",
+skip(pool, path, base_url)
+)]
+#[post("/{username}/exports")]
+pub async fn handle_post_export(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let username = path.into_inner();
+    if auth_permissions.username != username {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let export = match insert_db_export(&pool, &username).await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let response = match to_export_response(export, &base_url.0, &username) {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    HttpResponse::Accepted()
+        .insert_header((LOCATION, response.status_url.clone()))
+        .json(response)
+}
+
+#[tracing::instrument(
+name = "handle_get_export",
+skip(pool, path, base_url)
+)]
+#[get("/{username}/exports/{export_id}")]
+pub async fn handle_get_export(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<(String, Uuid)>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let (username, export_id) = path.into_inner();
+    if auth_permissions.username != username {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let export = match get_db_export(&pool, &username, export_id).await {
+        Ok(Some(x)) => x,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    match to_export_response(export, &base_url.0, &username) {
+        Ok(x) => HttpResponse::Ok().json(x),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+// 409 until the export worker has built the archive
+#[tracing::instrument(
+name = "handle_get_export_archive",
+skip(pool, storage, path)
+)]
+#[get("/{username}/exports/{export_id}/archive")]
+pub async fn handle_get_export_archive(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
+    path: web::Path<(String, Uuid)>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let (username, export_id) = path.into_inner();
+    if auth_permissions.username != username {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let archive_key = match get_db_export_archive_key(&pool, &username, export_id).await {
+        Ok(Some(x)) => x,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let archive = match archive_key {
+        Some(x) => match storage.get(&x).await {
+            Ok(y) => y,
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        },
+        None => return HttpResponse::Conflict().body("The export is not ready.")
+    };
+    HttpResponse::Ok()
+        .content_type(EXPORT_ARCHIVE_MIME)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("export-{}.zip", export_id))],
+        })
+        .body(archive)
+}
+
+fn to_export_response(
+    export: DbDataExport,
+    base_url: &str,
+    username: &str,
+) -> Result<DataExportResponse, String> {
+    let status = DataExportStatus::parse(&export.status)?;
+    let status_url = format!("{}/users/{}/exports/{}", base_url, username, export.export_id);
+    let download_url = match status {
+        DataExportStatus::Done => Some(format!("{}/archive", status_url)),
+        _ => None
+    };
+    Ok(DataExportResponse {
+        export_id: export.export_id,
+        status,
+        requested_at: export.requested_at,
+        finished_at: export.finished_at,
+        expires_at: export.expires_at,
+        status_url,
+        download_url,
+    })
+}
+
+// Returns the user's pending export if there is one, or a newly queued one
+pub async fn insert_db_export(
+    pool: &PgPool,
+    username: &str,
+) -> Result<DbDataExport, sqlx::Error> {
+    let mut tran = pool.begin().await?;
+    // Serializes concurrent requests of the same user
+    let user = sqlx::query!(
+        r#"
+        SELECT id FROM users WHERE username = $1 FOR UPDATE;
+        "#,
+        username
+    )
+        .fetch_one(&mut tran)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    let pending = sqlx::query_as!(
+        DbDataExport,
+        r#"
+        SELECT id AS export_id, status, requested_at, finished_at, expires_at
+        FROM data_exports
+        WHERE user_id = $1 AND status = 'pending';
+        "#,
+        user.id
+    )
+        .fetch_optional(&mut tran)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    let export = match pending {
+        Some(x) => x,
+        None => sqlx::query_as!(
+            DbDataExport,
+            r#"
+            INSERT INTO data_exports (id, user_id)
+            VALUES ($1, $2)
+            RETURNING id AS export_id, status, requested_at, finished_at, expires_at;
+            "#,
+            Uuid::new_v4(),
+            user.id
+        )
+            .fetch_one(&mut tran)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to execute query: {:?}", e);
+                e
+            })?
+    };
+    tran.commit().await?;
+    Ok(export)
+}
+
+pub async fn get_db_export(
+    pool: &PgPool,
+    username: &str,
+    export_id: Uuid,
+) -> Result<Option<DbDataExport>, sqlx::Error> {
+    sqlx::query_as!(
+        DbDataExport,
+        r#"
+        SELECT exp.id AS export_id, exp.status, exp.requested_at, exp.finished_at, exp.expires_at
+        FROM data_exports exp
+        INNER JOIN users usr ON usr.id = exp.user_id
+        WHERE exp.id = $1 AND usr.username = $2;
+        "#,
+        export_id,
+        username
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })
+}
+
+// None if there is no such export, Some(None) if it has no archive yet
+pub async fn get_db_export_archive_key(
+    pool: &PgPool,
+    username: &str,
+    export_id: Uuid,
+) -> Result<Option<Option<String>>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT exp.archive_key
+        FROM data_exports exp
+        INNER JOIN users usr ON usr.id = exp.user_id
+        WHERE exp.id = $1 AND usr.username = $2;
+        "#,
+        export_id,
+        username
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(row.map(|x| x.archive_key))
+}
@@ -0,0 +1,5 @@
+pub mod export_routing;
+mod export_response;
+
+pub use export_response::DataExportResponse;
+pub use export_routing::{handle_post_export, handle_get_export, handle_get_export_archive};
@@ -0,0 +1,10 @@
+use chrono::{DateTime, Utc};
+use chrono::serde::ts_seconds;
+
+// A user the requesting user follows
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct FollowResponse {
+    pub username: String,
+    #[serde(with = "ts_seconds")]
+    pub added_at: DateTime<Utc>,
+}
@@ -0,0 +1,153 @@
+use actix_web::{delete, get, put, HttpMessage, HttpRequest, HttpResponse, web};
+use sqlx::PgPool;
+use crate::authentication::AuthPermissions;
+use crate::routes::users::follows::follow_response::FollowResponse;
+
+// Following a user shows their followers-only pinpoints.
+// Who someone follows is only ever shown to themselves.
+#[tracing::instrument(
+name = "handle_get_follows",
+skip(pool, path)
+)]
+#[get("/{username}/follows")]
+pub async fn handle_get_follows(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let username = path.into_inner();
+    if auth_permissions.username != username {
+        return HttpResponse::Unauthorized().finish();
+    }
+    match get_db_follows(&pool, &username).await {
+        Ok(x) => HttpResponse::Ok().json(x),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+// Following someone already followed changes nothing
+#[tracing::instrument(
+name = "handle_put_follow",
+skip(pool, path)
+)]
+#[put("/{username}/follows/{followee}")]
+pub async fn handle_put_follow(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let (username, followee) = path.into_inner();
+    if auth_permissions.username != username {
+        return HttpResponse::Unauthorized().finish();
+    }
+    if username == followee {
+        return HttpResponse::BadRequest().body("Users cannot follow themselves.");
+    }
+    match insert_db_follow(&pool, &username, &followee).await {
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Ok(true) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[tracing::instrument(
+name = "handle_delete_follow",
+skip(pool, path)
+)]
+#[delete("/{username}/follows/{followee}")]
+pub async fn handle_delete_follow(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let (username, followee) = path.into_inner();
+    if auth_permissions.username != username {
+        return HttpResponse::Unauthorized().finish();
+    }
+    match delete_db_follow(&pool, &username, &followee).await {
+        Ok(0) => HttpResponse::NotFound().finish(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn get_db_follows(
+    pool: &PgPool,
+    username: &str,
+) -> Result<Vec<FollowResponse>, sqlx::Error> {
+    sqlx::query_as!(
+        FollowResponse,
+        r#"
+        SELECT usr.username, fol.added_at
+        FROM user_follows fol
+        INNER JOIN users follower ON follower.id = fol.follower_id
+        INNER JOIN users usr ON usr.id = fol.followee_id
+        WHERE follower.username = $1
+        ORDER BY fol.added_at, usr.username;
+        "#,
+        username
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })
+}
+
+// False when there is no user to follow
+pub async fn insert_db_follow(
+    pool: &PgPool,
+    username: &str,
+    followee: &str,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        WITH fol AS (
+            INSERT INTO user_follows (follower_id, followee_id)
+            SELECT follower.id, usr.id
+            FROM users follower, users usr
+            WHERE follower.username = $1 AND usr.username = $2
+            ON CONFLICT (follower_id, followee_id) DO NOTHING
+        )
+        SELECT usr.id FROM users usr WHERE usr.username = $2;
+        "#,
+        username,
+        followee
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(row.is_some())
+}
+
+// Returns how many follows were deleted
+pub async fn delete_db_follow(
+    pool: &PgPool,
+    username: &str,
+    followee: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM user_follows fol
+        USING users follower, users usr
+        WHERE fol.follower_id = follower.id AND fol.followee_id = usr.id
+        AND follower.username = $1 AND usr.username = $2;
+        "#,
+        username,
+        followee
+    )
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,5 @@
+pub mod follow_routing;
+mod follow_response;
+
+pub use follow_response::FollowResponse;
+pub use follow_routing::{handle_get_follows, handle_put_follow, handle_delete_follow};
@@ -4,6 +4,7 @@ use anyhow::{anyhow};
 use sqlx::PgPool;
 use uuid::Uuid;
 use crate::authentication::{AuthParameters, AuthPermissions, AuthService};
+use crate::blob_storage::BlobStorage;
 use crate::domain::database::DbUser;
 use crate::routes::users::get::get_user_request::GetUsersRequest;
 use crate::routes::users::get::user_response::UserResponse;
@@ -18,10 +19,11 @@ enum UserFilter {
 
 #[tracing::instrument(
 name = "handle_get_users",
-skip(pool, args, auth, auth_params),
+skip(pool, storage, args, auth, auth_params),
 )]
 pub async fn handle_get_users(
     pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
     args: web::Query<GetUsersRequest>,
     auth: web::Data<AuthService>,
     auth_params: Option<AuthParameters>
@@ -52,11 +54,12 @@ pub async fn handle_get_users(
         })
     });
 
-    get_user(pool, &args, permissions).await
+    get_user(pool, storage, &args, permissions).await
 }
 
 pub async fn get_user(
     pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
     args: &GetUsersRequest,
     auth: Option<AuthPermissions>
 ) -> HttpResponse {
@@ -93,6 +96,13 @@ pub async fn get_user(
     let determined_contents_id = user_val.contents_id.clone();
     println!("Contents ID represented as {:?}", determined_contents_id);
     if extra_rights {
+        let contents_attachment = match &user_val.contents_attachment_key {
+            Some(x) => match storage.get(x).await {
+                Ok(y) => Some(y),
+                Err(_) => return HttpResponse::InternalServerError().finish()
+            },
+            None => None
+        };
         // All the loot
         let user_resp = UserResponse {
             unique_id: Some(user_val.unique_id),
@@ -102,7 +112,7 @@ pub async fn get_user(
             role_title: Some(user_val.role_title),
             contents_id: user_val.contents_id,
             contents_description: user_val.contents_description,
-            contents_attachment: user_val.contents_attachment
+            contents_attachment
         };
         let json = serde_json::to_string(&user_resp).unwrap();
         return HttpResponse::Ok()
@@ -155,7 +165,8 @@ pub async fn get_db_user_with_id(
         rls.title AS role_title,
         COALESCE(con.id) AS contents_id,
         con.description AS contents_description,
-        con.attachment AS contents_attachment
+        con.attachment_key AS contents_attachment_key,
+        con.attachment_sha256 AS contents_attachment_sha256
         FROM users usr
         INNER JOIN user_roles usr_rls ON usr.id = usr_rls.user_id
         INNER JOIN roles rls ON rls.id = usr_rls.role_id
@@ -184,7 +195,8 @@ pub async fn get_db_user_with_username(
         rls.title AS role_title,
         COALESCE(con.id) AS contents_id,
         con.description AS contents_description,
-        con.attachment AS contents_attachment
+        con.attachment_key AS contents_attachment_key,
+        con.attachment_sha256 AS contents_attachment_sha256
         FROM users usr
         INNER JOIN user_roles usr_rls ON usr.id = usr_rls.user_id
         INNER JOIN roles rls ON rls.id = usr_rls.role_id
@@ -213,7 +225,8 @@ pub async fn get_db_user_with_email(
         rls.title AS role_title,
         COALESCE(con.id) AS contents_id,
         con.description AS contents_description,
-        con.attachment AS contents_attachment
+        con.attachment_key AS contents_attachment_key,
+        con.attachment_sha256 AS contents_attachment_sha256
         FROM users usr
         INNER JOIN user_roles usr_rls ON usr.id = usr_rls.user_id
         INNER JOIN roles rls ON rls.id = usr_rls.role_id
@@ -2,6 +2,9 @@ pub mod get;
 pub mod post;
 pub mod delete;
 pub mod put;
+pub mod export;
+pub mod zones;
+pub mod follows;
 
 /*
 pub use get::handle_get_users;
@@ -6,18 +6,21 @@ use std::convert::{TryFrom};
 use secrecy::{ExposeSecret};
 use uuid::Uuid;
 use crate::authentication::{AuthParameters, AuthService, basic_authentication, validate_credentials};
+use crate::blob_storage::{discard_attachments, store_attachment, BlobStorage, StoredAttachment};
 use crate::domain::app_user::AppUser;
+use crate::domain::normalize_image_blocking;
 use crate::domain::user_sign_up::UserSignUp;
 use crate::routes::users::post::post_user_request::PostUserRequest;
 
 #[tracing::instrument(
 name = "handle_signup",
-skip(payload, pool, auth)
+skip(payload, pool, storage, auth)
 )]
 pub async fn handle_signup(
     request: HttpRequest,
     payload: web::Json<PostUserRequest>,
     pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
     auth: web::Data<AuthService>,
 ) -> HttpResponse {
     let credentials = match basic_authentication(&request.headers()) {
@@ -28,12 +31,20 @@ pub async fn handle_signup(
         }
     };
     let email = payload.0.email;
+    let contents_attachment = match payload.0.contents_attachment {
+        Some(x) if !x.is_empty() => match normalize_image_blocking(x).await {
+            Ok(Ok(x)) => Some(x),
+            Ok(Err(e)) => return HttpResponse::BadRequest().body(e),
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        },
+        x => x
+    };
     let combined_payload = UserSignUp {
         email,
         username: credentials.username.clone(),
         pw: credentials.pw.expose_secret().to_string(),
         contents_description: payload.0.contents_description,
-        contents_attachment: payload.0.contents_attachment
+        contents_attachment
     };
     let mut transaction: Transaction<Postgres> = match pool.begin().await {
         Ok(x) => x,
@@ -42,7 +53,7 @@ pub async fn handle_signup(
             return HttpResponse::InternalServerError().finish()
         }
     };
-    match sign_up_user(combined_payload, &mut transaction).await {
+    match sign_up_user(combined_payload, storage.get_ref(), &mut transaction).await {
         Ok(_) => {},
         Err(_) => {
             println!("Failure from sign_up_user.");
@@ -76,13 +87,20 @@ pub async fn handle_signup(
 
 pub async fn sign_up_user(
     user_sign_up: UserSignUp,
+    storage: &dyn BlobStorage,
     tran: &mut Transaction<'_, Postgres>,
 ) -> Result<(), anyhow::Error> {
     // Salt and hashing are generated within try_from
-    let new_user = AppUser::try_from(user_sign_up)?;
-    match store_new_user(tran, &new_user).await {
+    let mut new_user = AppUser::try_from(user_sign_up)?;
+    // A blank attachment still makes a contents row, without anything stored
+    let stored = match new_user.contents_attachment.take().filter(|x| !x.is_empty()) {
+        Some(x) => Some(store_attachment(storage, x, None, None, None).await?),
+        None => None
+    };
+    match store_new_user(tran, &new_user, stored.as_ref()).await {
         Ok(_) => Ok(()),
         Err(_) => {
+            discard_attachments(storage, stored.as_slice()).await;
             println!("Failed to store new user in the database.");
             Err(anyhow!("Failed to store new user in the database."))
         }
@@ -92,6 +110,7 @@ pub async fn sign_up_user(
 async fn store_new_user(
     tran: &mut Transaction<'_, Postgres>,
     user: &AppUser,
+    attachment: Option<&StoredAttachment>,
 ) -> Result<Uuid, sqlx::Error> {
     let uuid = user.unique_id;
     let email = user.email.to_string();
@@ -124,7 +143,7 @@ async fn store_new_user(
         // Voodoo dealing with re-borrowing
         let another = &mut *tran;
         if user.contents_id.is_some() {
-            let attempt = store_new_user_contents(another, user).await?;
+            let attempt = store_new_user_contents(another, user, attachment).await?;
         }
     }
     /*
@@ -140,12 +159,14 @@ async fn store_new_user(
 async fn store_new_user_contents(
     tran: &mut Transaction<'_, Postgres>,
     user: &AppUser,
+    attachment: Option<&StoredAttachment>,
 ) -> Result<Uuid, sqlx::Error> {
     let execute_result = sqlx::query!(
             r#"
             WITH cts AS (
-                INSERT INTO contents (id, description, attachment)
-                VALUES ($1, $2, $3)
+                INSERT INTO contents (id, description, attachment_key, attachment_size,
+                    attachment_sha256, attachment_mime)
+                VALUES ($1, $2, $3, $5, $6, $7)
                 RETURNING id
             )
             INSERT INTO user_contents (user_id, contents_id)
@@ -153,8 +174,11 @@ async fn store_new_user_contents(
             "#,
             user.contents_id,
             user.contents_description,
-            user.contents_attachment,
-            user.unique_id
+            attachment.map(|x| &x.full.key),
+            user.unique_id,
+            attachment.map(|x| x.full.size),
+            attachment.map(|x| &x.full.sha256),
+            attachment.map(|x| &x.full.mime_type)
         )
         .execute(tran)
         .await?;
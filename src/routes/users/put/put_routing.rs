@@ -5,7 +5,9 @@ use secrecy::{ExposeSecret, Secret};
 use sqlx::{PgPool, Postgres, Transaction};
 use uuid::{Uuid};
 use crate::authentication::{AuthPermissions, AuthService, compute_password_hash, rand_salt_string};
+use crate::blob_storage::{discard_attachments, sha256_hex, store_attachment, BlobStorage, StoredAttachment};
 use crate::domain::database::DbUser;
+use crate::domain::normalize_image_blocking;
 use crate::{ok_or_return_with, some_or_return_with};
 use crate::routes::users::get::{get_db_user_with_id, get_db_user_with_username};
 use crate::routes::users::put::put_user_request::PutUserRequest;
@@ -39,28 +41,24 @@ impl TryFrom<(DbUser, &PutUserRequest)> for DbUser {
             Some(x) => Some(x.as_str().to_string()),
             None => value.0.contents_description
         };
-        if value.1.contents_attachment.is_some() {
-            // There is likely a solution to prevent this clone.
-            result.contents_attachment = value.1.contents_attachment.clone();
-        }
         Ok(result)
     }
 }
 
 #[tracing::instrument(
 name = "handle_put_users",
-skip(pool, path, auth)
+skip(pool, storage, path, auth)
 )]
 #[put("/{user_id}")]
 pub async fn handle_put_user(
     req: HttpRequest,
     pool: web::Data<PgPool>,
+    storage: web::Data<dyn BlobStorage>,
     path: web::Path<Uuid>,
     auth: web::Data<AuthService>,
     args: web::Json<PutUserRequest>
 ) -> HttpResponse {
-    let req_ext = req.extensions_mut();
-    let auth_permissions: &AuthPermissions = req_ext.get::<AuthPermissions>().unwrap();
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
     let user_id = path.into_inner();
     println!("User ID in path : {}", user_id.clone());
     let user_requesting = match get_db_user_with_id(&pool, user_id).await {
@@ -83,8 +81,18 @@ pub async fn handle_put_user(
         println!("TEST ERROR C");
         return HttpResponse::BadRequest().finish();
     }
-    modify_user(&pool, &user_requesting.username, &args.0).await;
-    match args.0.username {
+    let mut args = args.into_inner();
+    // A blank attachment erases the stored one and is left as it is
+    args.contents_attachment = match args.contents_attachment.take() {
+        Some(x) if !x.is_empty() => match normalize_image_blocking(x).await {
+            Ok(Ok(x)) => Some(x),
+            Ok(Err(e)) => return HttpResponse::BadRequest().body(e),
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        },
+        x => x
+    };
+    modify_user(&pool, storage.get_ref(), &user_requesting.username, &args).await;
+    match args.username {
         Some(x) => {
             // We now need a new JWT that corresponds with the new username
             let auth_jwt = auth.create_jwt(
@@ -99,8 +107,8 @@ pub async fn handle_put_user(
 
 }
 
-pub async fn modify_user(pool: &PgPool, existing_username: &str, args: &PutUserRequest)
--> HttpResponse {
+pub async fn modify_user(pool: &PgPool, storage: &dyn BlobStorage, existing_username: &str,
+                         args: &PutUserRequest) -> HttpResponse {
     let get_stored_result = ok_or_return_with!(
         get_db_user_with_username(pool, existing_username).await,
         HttpResponse::BadRequest().finish()
@@ -112,9 +120,13 @@ pub async fn modify_user(pool: &PgPool, existing_username: &str, args: &PutUserR
     // A blank contents item in the request will be used to erase
     // the corresponding contents field in the database.
     // A null contents item in the request will do nothing.
-    let modify_contents = (
-        args.contents_attachment.is_some()
-        && !options_eq(&existing_user.contents_attachment, &args.contents_attachment))
+    // Attachments are compared by their hash, nothing is stored for a blank one.
+    // Users without a contents row have nowhere to keep one.
+    let attachment_sha256 = args.contents_attachment.as_ref()
+        .map(|x| Some(x).filter(|x| !x.is_empty()).map(|x| sha256_hex(x)));
+    let modify_attachment = existing_user.contents_id.is_some() && matches!(
+        &attachment_sha256, Some(x) if *x != existing_user.contents_attachment_sha256);
+    let modify_contents = modify_attachment
         ||
         (args.contents_description.is_some()
             && !options_eq(&existing_user.contents_description, &args.contents_description));
@@ -141,16 +153,27 @@ pub async fn modify_user(pool: &PgPool, existing_username: &str, args: &PutUserR
     let user_changes: DbUser = match (existing_user, args).try_into() {
         Ok(x) => x,
         Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let stored = match args.contents_attachment.clone().filter(|x| modify_attachment && !x.is_empty()) {
+        Some(x) => match store_attachment(storage, x, None, None, None).await {
+            Ok(y) => Some(y),
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        },
+        None => None
     };
      match modify_stored_user(
-        &mut tran, existing_username, user_changes, modify_contents).await {
+        &mut tran, existing_username, user_changes, modify_contents,
+        modify_attachment, stored.as_ref()).await {
         Ok(_) => {
             match tran.commit().await {
                 Ok(_) => HttpResponse::Ok().finish(),
                 Err(_) => HttpResponse::InternalServerError().finish()
             }
         },
-        Err(_) => HttpResponse::InternalServerError().finish()
+        Err(_) => {
+            discard_attachments(storage, stored.as_slice()).await;
+            HttpResponse::InternalServerError().finish()
+        }
     }
 }
 
@@ -196,7 +219,10 @@ async fn stored_user_exists(tran: &mut Transaction<'_, Postgres>,
 async fn modify_stored_user(
     tran: &mut Transaction<'_, Postgres>,
     existing_username: &str, user_changes: DbUser,
-    update_contents: bool
+    update_contents: bool,
+    // With update_contents, replaces the attachment by this one, already in blob storage
+    replace_attachment: bool,
+    attachment: Option<&StoredAttachment>,
 ) -> Result<bool, sqlx::Error> {
     println!("Modification occurring: {:?}", user_changes.clone());
     let query_results = sqlx::query!(
@@ -225,12 +251,20 @@ async fn modify_stored_user(
 
         sqlx::query!(
             r#"
-                UPDATE contents SET description = $1, attachment = $2
+                UPDATE contents SET description = $1,
+                attachment_key = CASE WHEN $2 THEN $4 ELSE attachment_key END,
+                attachment_size = CASE WHEN $2 THEN $5 ELSE attachment_size END,
+                attachment_sha256 = CASE WHEN $2 THEN $6 ELSE attachment_sha256 END,
+                attachment_mime = CASE WHEN $2 THEN $7 ELSE attachment_mime END
                 WHERE id = $3;
             "#,
             user_changes.contents_description,
-            user_changes.contents_attachment,
-            cnt_id
+            replace_attachment,
+            cnt_id,
+            attachment.map(|x| &x.full.key),
+            attachment.map(|x| x.full.size),
+            attachment.map(|x| &x.full.sha256),
+            attachment.map(|x| &x.full.mime_type)
         ).execute(tran).await?;
     }
     Ok(true)
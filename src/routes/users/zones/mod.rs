@@ -0,0 +1,7 @@
+pub mod zone_routing;
+mod zone_request;
+mod zone_response;
+
+pub use zone_request::PostPrivateZoneRequest;
+pub use zone_response::PrivateZoneResponse;
+pub use zone_routing::{handle_get_private_zones, handle_add_private_zone, handle_delete_private_zone};
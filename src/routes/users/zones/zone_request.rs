@@ -0,0 +1,19 @@
+use crate::domain::{PrivateZone, PrivateZoneMode};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub struct PostPrivateZoneRequest {
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_meters: f64,
+    // Defaults to mask
+    pub mode: Option<PrivateZoneMode>,
+}
+
+impl TryFrom<PostPrivateZoneRequest> for PrivateZone {
+    type Error = String;
+    fn try_from(value: PostPrivateZoneRequest) -> Result<Self, Self::Error> {
+        PrivateZone::parse(&value.name, value.latitude, value.longitude,
+                           value.radius_meters, value.mode.unwrap_or_default())
+    }
+}
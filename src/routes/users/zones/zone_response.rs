@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use chrono::serde::ts_seconds;
+use uuid::Uuid;
+use crate::domain::PrivateZoneMode;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct PrivateZoneResponse {
+    pub zone_id: Uuid,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_meters: f64,
+    pub mode: PrivateZoneMode,
+    #[serde(with = "ts_seconds")]
+    pub added_at: DateTime<Utc>,
+}
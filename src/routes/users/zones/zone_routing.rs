@@ -0,0 +1,201 @@
+use actix_web::{delete, get, post, HttpMessage, HttpRequest, HttpResponse, web};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use crate::authentication::AuthPermissions;
+use crate::domain::{PrivateZone, PrivateZoneMode};
+use crate::domain::private_zone::MAX_PRIVATE_ZONES_PER_USER;
+use crate::routes::users::zones::zone_request::PostPrivateZoneRequest;
+use crate::routes::users::zones::zone_response::PrivateZoneResponse;
+
+pub struct DbPrivateZone {
+    pub zone_id: Uuid,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub radius_meters: f64,
+    pub mode: String,
+    pub added_at: DateTime<Utc>,
+}
+
+impl TryFrom<DbPrivateZone> for PrivateZoneResponse {
+    type Error = String;
+    fn try_from(value: DbPrivateZone) -> Result<Self, Self::Error> {
+        Ok(Self {
+            zone_id: value.zone_id,
+            name: value.name,
+            latitude: value.latitude,
+            longitude: value.longitude,
+            radius_meters: value.radius_meters,
+            mode: PrivateZoneMode::parse(&value.mode)?,
+            added_at: value.added_at,
+        })
+    }
+}
+
+// Zones are only ever shown to their owner
+#[tracing::instrument(
+name = "handle_get_private_zones",
+skip(pool, path)
+)]
+#[get("/{username}/zones")]
+pub async fn handle_get_private_zones(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let username = path.into_inner();
+    if auth_permissions.username != username {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let zones = match get_db_private_zones(&pool, &username).await {
+        Ok(x) => x,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    let response: Result<Vec<PrivateZoneResponse>, String> = zones.into_iter()
+        .map(PrivateZoneResponse::try_from)
+        .collect();
+    match response {
+        Ok(x) => HttpResponse::Ok().json(x),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[tracing::instrument(
+name = "handle_add_private_zone",
+skip(pool, path, args)
+)]
+#[post("/{username}/zones")]
+pub async fn handle_add_private_zone(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    args: web::Json<PostPrivateZoneRequest>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let username = path.into_inner();
+    if auth_permissions.username != username {
+        return HttpResponse::Unauthorized().finish();
+    }
+    let zone: PrivateZone = match args.into_inner().try_into() {
+        Ok(x) => x,
+        Err(e) => return HttpResponse::BadRequest().body(e)
+    };
+    let zone = match insert_db_private_zone(&pool, &username, &zone).await {
+        Ok(Some(x)) => x,
+        Ok(None) => return HttpResponse::BadRequest().body(
+            format!("A user can have at most {} private zones.", MAX_PRIVATE_ZONES_PER_USER)),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    match PrivateZoneResponse::try_from(zone) {
+        Ok(x) => HttpResponse::Ok().json(x),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+#[tracing::instrument(
+name = "handle_delete_private_zone",
+skip(pool, path)
+)]
+#[delete("/{username}/zones/{zone_id}")]
+pub async fn handle_delete_private_zone(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<(String, Uuid)>,
+) -> HttpResponse {
+    let auth_permissions: AuthPermissions = req.extensions().get::<AuthPermissions>().cloned().unwrap();
+    let (username, zone_id) = path.into_inner();
+    if auth_permissions.username != username {
+        return HttpResponse::Unauthorized().finish();
+    }
+    match delete_db_private_zone(&pool, &username, zone_id).await {
+        Ok(0) => HttpResponse::NotFound().finish(),
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn get_db_private_zones(
+    pool: &PgPool,
+    username: &str,
+) -> Result<Vec<DbPrivateZone>, sqlx::Error> {
+    sqlx::query_as!(
+        DbPrivateZone,
+        r#"
+        SELECT zon.id AS zone_id, zon.name, zon.latitude, zon.longitude,
+        zon.radius_meters, zon.mode, zon.added_at
+        FROM user_private_zones zon
+        INNER JOIN users usr ON usr.id = zon.user_id
+        WHERE usr.username = $1
+        ORDER BY zon.added_at, zon.id;
+        "#,
+        username
+    )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })
+}
+
+// None once the user already has the most zones allowed.
+// Where other users see masked pinpoints is worked out once, here.
+pub async fn insert_db_private_zone(
+    pool: &PgPool,
+    username: &str,
+    zone: &PrivateZone,
+) -> Result<Option<DbPrivateZone>, sqlx::Error> {
+    sqlx::query_as!(
+        DbPrivateZone,
+        r#"
+        INSERT INTO user_private_zones (id, user_id, name, latitude, longitude, radius_meters, mode,
+            public_latitude, public_longitude)
+        SELECT $1, usr.id, $3, $4, $5, $6, $7, pub.latitude, pub.longitude
+        FROM users usr
+        CROSS JOIN LATERAL private_zone_public_location($4, $5, $6, usr.id) pub
+        WHERE usr.username = $2
+        AND (SELECT COUNT(*) FROM user_private_zones zon WHERE zon.user_id = usr.id) < $8
+        RETURNING id AS zone_id, name, latitude, longitude, radius_meters, mode, added_at;
+        "#,
+        zone.zone_id,
+        username,
+        zone.name,
+        zone.latitude.value(),
+        zone.longitude.value(),
+        zone.radius_meters,
+        zone.mode.as_str(),
+        MAX_PRIVATE_ZONES_PER_USER
+    )
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })
+}
+
+// Returns how many zones were deleted
+pub async fn delete_db_private_zone(
+    pool: &PgPool,
+    username: &str,
+    zone_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM user_private_zones zon
+        USING users usr
+        WHERE zon.user_id = usr.id AND usr.username = $1 AND zon.id = $2;
+        "#,
+        username,
+        zone_id
+    )
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to execute query: {:?}", e);
+            e
+        })?;
+    Ok(result.rows_affected())
+}
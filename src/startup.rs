@@ -1,4 +1,6 @@
-use crate::configuration::{DatabaseSettings, Settings};
+use crate::blob_storage::{run_exif_backfill, BlobStorage, LocalBlobStorage, S3BlobStorage};
+use crate::configuration::{ApplicationSettings, BlobStorageBackend, BlobStorageSettings, DatabaseSettings, Settings};
+use crate::domain::LocationPrecision;
 use actix_web::dev::Server;
 use actix_web::web::Data;
 use actix_web::{web, App, HttpServer};
@@ -6,18 +8,33 @@ use secrecy::{Secret};
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::net::TcpListener;
+use std::sync::Arc;
 use actix_web_lab::middleware::from_fn;
 use tracing_actix_web::TracingLogger;
 use crate::authentication::AuthService;
 use crate::authentication::middleware::get_jwt_permissions;
+use crate::expiry_worker::run_expiry_worker_until_stopped;
+use crate::export_worker::run_export_worker_until_stopped;
+use crate::pinpoint_events::{PinpointEvents, PINPOINT_EVENT_BUFFER,
+                             run_pinpoint_event_listener_until_stopped};
 use crate::routes::health_check;
 use crate::routes::login::handle_login;
-use crate::routes::pinpoints::{handle_add_pinpoint, handle_get_pinpoints};
+use crate::routes::pinpoints::{handle_add_pinpoint, handle_add_pinpoint_batch, handle_get_pinpoint_clusters, handle_get_pinpoints,
+                               handle_get_popular_tags, handle_get_pinpoint_heatmap,
+                               handle_get_pinpoint_stream, handle_import_pinpoints,
+                               handle_put_pinpoint, handle_put_reaction, handle_delete_reaction,
+                               handle_get_comments, handle_add_comment, handle_put_comment,
+                               handle_delete_comment};
 use crate::routes::pinpoints::delete::delete_routing::handle_delete_pinpoints;
+use crate::routes::pinpoints::import::import_routing::MAX_IMPORT_FILE_BYTES;
 use crate::routes::users::delete::delete_routing::handle_delete_user;
 use crate::routes::users::get::handle_get_users;
 use crate::routes::users::post::post_routing::{handle_signup};
 use crate::routes::users::put::handle_put_user;
+use crate::routes::users::export::{handle_post_export, handle_get_export, handle_get_export_archive};
+use crate::routes::users::zones::{handle_get_private_zones, handle_add_private_zone,
+                                  handle_delete_private_zone};
+use crate::routes::users::follows::{handle_get_follows, handle_put_follow, handle_delete_follow};
 
 pub struct Application {
     port: u16,
@@ -28,6 +45,21 @@ impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
         let connection_pool = get_connection_pool(&configuration.database);
         let auth_service = get_auth_service(&configuration);
+        let blob_storage = get_blob_storage(&configuration.blob_storage)?;
+        let purge_interval = std::time::Duration::from_secs(
+            configuration.application.pinpoint_purge_interval_seconds);
+        tokio::spawn(run_expiry_worker_until_stopped(
+            connection_pool.clone(), blob_storage.clone(), purge_interval));
+        tokio::spawn(run_exif_backfill(connection_pool.clone(), blob_storage.clone()));
+        let export_interval = std::time::Duration::from_secs(
+            configuration.application.data_export_poll_interval_seconds);
+        let export_retention = chrono::Duration::from_std(std::time::Duration::from_secs(
+            configuration.application.data_export_retention_seconds))?;
+        tokio::spawn(run_export_worker_until_stopped(
+            connection_pool.clone(), blob_storage.clone(), export_interval, export_retention));
+        let (pinpoint_events, _) = tokio::sync::broadcast::channel(PINPOINT_EVENT_BUFFER);
+        tokio::spawn(run_pinpoint_event_listener_until_stopped(
+            connection_pool.clone(), pinpoint_events.clone()));
 
         let address = format!(
             "{}:{}",
@@ -39,8 +71,10 @@ impl Application {
         let server = run(
             listener,
             connection_pool,
-            configuration.application.base_url,
+            blob_storage,
             auth_service,
+            configuration.application,
+            PinpointEvents(pinpoint_events),
         )
             .await?;
 
@@ -62,25 +96,45 @@ pub fn get_connection_pool(configuration: &DatabaseSettings) -> PgPool {
         .connect_lazy_with(configuration.with_db())
 }
 
+pub fn get_blob_storage(configuration: &BlobStorageSettings) -> Result<Arc<dyn BlobStorage>, anyhow::Error> {
+    let storage: Arc<dyn BlobStorage> = match configuration.backend {
+        BlobStorageBackend::Local => Arc::new(LocalBlobStorage::new(&configuration.local_directory)),
+        BlobStorageBackend::S3 => Arc::new(S3BlobStorage::new(&configuration.s3)?),
+    };
+    Ok(storage)
+}
+
 pub fn get_auth_service(configuration: &Settings) -> AuthService {
     AuthService::new(configuration.application.jwt_secret.clone())
 }
 
 pub struct ApplicationBaseUrl(pub String);
 
+pub struct MaxPinpointAttachments(pub usize);
+
 async fn run(
     listener: TcpListener,
     db_pool: PgPool,
-    base_url: String,
+    blob_storage: Arc<dyn BlobStorage>,
     auth_service: AuthService,
+    application: ApplicationSettings,
+    pinpoint_events: PinpointEvents,
 ) -> Result<Server, anyhow::Error> {
+    let location_precision = LocationPrecision::parse(application.pinpoint_location_precision_meters)
+        .map_err(|e| anyhow::anyhow!(e))?;
     let db_pool = Data::new(db_pool);
-    let base_url = Data::new(ApplicationBaseUrl(base_url));
+    let blob_storage: Data<dyn BlobStorage> = Data::from(blob_storage);
+    let base_url = Data::new(ApplicationBaseUrl(application.base_url));
     let auth_service = Data::new(auth_service);
+    let max_attachments = Data::new(MaxPinpointAttachments(application.pinpoint_max_attachments));
+    let pinpoint_events = Data::new(pinpoint_events);
+    let location_precision = Data::new(location_precision);
     //let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
     //let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
     let json_config = web::JsonConfig::default()
         .limit(20000000);
+    // Raw bodies, such as imported GPX and KML files
+    let payload_config = web::PayloadConfig::new(MAX_IMPORT_FILE_BYTES);
     let server = HttpServer::new(move || {
         App::new()
             .wrap(TracingLogger::default())
@@ -94,20 +148,47 @@ async fn run(
                 web::scope("/pinpoints")
                     .wrap(from_fn(get_jwt_permissions))
                     .route("", web::post().to(handle_add_pinpoint))
+                    .route("/batch", web::post().to(handle_add_pinpoint_batch))
                     .route("", web::delete().to(handle_delete_pinpoints))
                     .service(handle_get_pinpoints)
+                    .service(handle_get_pinpoint_clusters)
+                    .service(handle_get_popular_tags)
+                    .service(handle_get_pinpoint_heatmap)
+                    .service(handle_get_pinpoint_stream)
+                    .service(handle_import_pinpoints)
+                    .service(handle_put_pinpoint)
+                    .service(handle_put_reaction)
+                    .service(handle_delete_reaction)
+                    .service(handle_get_comments)
+                    .service(handle_add_comment)
+                    .service(handle_put_comment)
+                    .service(handle_delete_comment)
             )
             .service(
                 web::scope("/users")
                     .wrap(from_fn(get_jwt_permissions))
                     .route("", web::delete().to(handle_delete_user))
                     .service(handle_put_user)
+                    .service(handle_post_export)
+                    .service(handle_get_export)
+                    .service(handle_get_export_archive)
+                    .service(handle_get_private_zones)
+                    .service(handle_add_private_zone)
+                    .service(handle_delete_private_zone)
+                    .service(handle_get_follows)
+                    .service(handle_put_follow)
+                    .service(handle_delete_follow)
             )
             .app_data(db_pool.clone())
+            .app_data(blob_storage.clone())
             .app_data(base_url.clone())
             .app_data(json_config.clone())
+            .app_data(payload_config.clone())
             //.app_data(Data::new(HmacSecret(hmac_secret.clone())))
             .app_data(auth_service.clone())
+            .app_data(max_attachments.clone())
+            .app_data(pinpoint_events.clone())
+            .app_data(location_precision.clone())
     })
         .listen(listener)?
         .run();
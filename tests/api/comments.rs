@@ -0,0 +1,180 @@
+use gvserver::domain::PinpointVisibility;
+use gvserver::routes::pinpoints::comments::{GetCommentResponse, GetCommentsRequest, PostCommentRequest,
+                                            PostCommentResponse, PutCommentRequest};
+use gvserver::routes::pinpoints::get::{GetPinpointRequest, GetPinpointResponse, NEXT_CURSOR_HEADER};
+use gvserver::routes::pinpoints::post::PostPinpointRequest;
+use uuid::Uuid;
+use crate::helpers::{spawn_app, TestApp};
+
+async fn post_test_pinpoint(app: &TestApp, jwt: String, username: String,
+                            visibility: PinpointVisibility) -> Uuid {
+    let mut request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, username.clone());
+    request_body.visibility = Some(visibility);
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let pinpoints = app.get_pinpoints(jwt, username, GetPinpointRequest::default()).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    pinpoints[0].pinpoint_id.unwrap()
+}
+
+async fn post_test_comment(app: &TestApp, jwt: String, pinpoint_id: Uuid,
+                           body: &str, parent_id: Option<Uuid>) -> Uuid {
+    let response = app.post_comment(jwt, pinpoint_id,
+                                    PostCommentRequest::new(String::from(body), parent_id)).await;
+    assert_eq!(response.status(), 200);
+    response.json::<PostCommentResponse>().await
+        .expect("Failed to get a JSON response back.")
+        .comment_id
+}
+
+#[tokio::test]
+async fn comments_and_replies_are_listed_in_order() {
+    let app = spawn_app().await;
+    let owner = String::from("TestGeneratedUser");
+    let owner_jwt = app.sign_up_test_user(owner.as_str(),
+                                          "initialtestingemail@something.com", None).await;
+    let other_jwt = app.sign_up_test_user("SomeoneElse",
+                                          "someoneelse@something.com", None).await;
+    let pinpoint_id = post_test_pinpoint(&app, owner_jwt.clone(), owner.clone(),
+                                         PinpointVisibility::Public).await;
+    let first = post_test_comment(&app, other_jwt.clone(), pinpoint_id, "First", None).await;
+    post_test_comment(&app, owner_jwt.clone(), pinpoint_id, "Second", None).await;
+    post_test_comment(&app, owner_jwt.clone(), pinpoint_id, "Reply", Some(first)).await;
+
+    let response = app.get_comments(owner_jwt.clone(), pinpoint_id,
+                                    GetCommentsRequest::default()).await;
+    assert_eq!(response.status(), 200);
+    let comments = response.json::<Vec<GetCommentResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    let bodies: Vec<&str> = comments.iter().map(|x| x.body.as_str()).collect();
+    assert_eq!(bodies, vec!["First", "Second"]);
+    assert_eq!(comments[0].username, "SomeoneElse");
+    assert_eq!(comments[0].reply_count, 1);
+    assert_eq!(comments[1].reply_count, 0);
+
+    let request_body = GetCommentsRequest { parent_id: Some(first), ..Default::default() };
+    let replies = app.get_comments(other_jwt.clone(), pinpoint_id, request_body).await
+        .json::<Vec<GetCommentResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(replies.len(), 1);
+    assert_eq!(replies[0].body, "Reply");
+    assert_eq!(replies[0].parent_id, Some(first));
+}
+
+#[tokio::test]
+async fn comments_page_through_with_cursors() {
+    let app = spawn_app().await;
+    let owner = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(owner.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let pinpoint_id = post_test_pinpoint(&app, jwt.clone(), owner.clone(),
+                                         PinpointVisibility::Public).await;
+    for i in 0..5 {
+        post_test_comment(&app, jwt.clone(), pinpoint_id, &i.to_string(), None).await;
+    }
+    let mut bodies: Vec<String> = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let request_body = GetCommentsRequest { limit: Some(2), cursor: cursor.clone(), ..Default::default() };
+        let response = app.get_comments(jwt.clone(), pinpoint_id, request_body).await;
+        assert_eq!(response.status(), 200);
+        cursor = response.headers().get(NEXT_CURSOR_HEADER)
+            .map(|x| x.to_str().unwrap().to_string());
+        let page = response.json::<Vec<GetCommentResponse>>().await
+            .expect("Failed to get a JSON response back.");
+        assert!(page.len() <= 2);
+        bodies.extend(page.into_iter().map(|x| x.body));
+        if cursor.is_none() {
+            break;
+        }
+    }
+    assert_eq!(bodies, vec!["0", "1", "2", "3", "4"]);
+}
+
+#[tokio::test]
+async fn replies_cannot_be_nested_or_empty() {
+    let app = spawn_app().await;
+    let owner = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(owner.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let pinpoint_id = post_test_pinpoint(&app, jwt.clone(), owner.clone(),
+                                         PinpointVisibility::Public).await;
+    let top = post_test_comment(&app, jwt.clone(), pinpoint_id, "Top", None).await;
+    let reply = post_test_comment(&app, jwt.clone(), pinpoint_id, "Reply", Some(top)).await;
+
+    let test_cases = vec![
+        PostCommentRequest::new(String::from("Nested"), Some(reply)),
+        PostCommentRequest::new(String::from("Unknown parent"), Some(Uuid::new_v4())),
+        PostCommentRequest::new(String::from("   "), None),
+        PostCommentRequest::new("x".repeat(2001), None),
+    ];
+    for request_body in test_cases {
+        let response = app.post_comment(jwt.clone(), pinpoint_id, request_body).await;
+        assert_eq!(response.status(), 400);
+    }
+}
+
+#[tokio::test]
+async fn only_the_author_can_edit_or_delete_a_comment() {
+    let app = spawn_app().await;
+    let owner = String::from("TestGeneratedUser");
+    let owner_jwt = app.sign_up_test_user(owner.as_str(),
+                                          "initialtestingemail@something.com", None).await;
+    let other_jwt = app.sign_up_test_user("SomeoneElse",
+                                          "someoneelse@something.com", None).await;
+    let pinpoint_id = post_test_pinpoint(&app, owner_jwt.clone(), owner.clone(),
+                                         PinpointVisibility::Public).await;
+    let top = post_test_comment(&app, other_jwt.clone(), pinpoint_id, "Frm unit testing", None).await;
+    post_test_comment(&app, owner_jwt.clone(), pinpoint_id, "Reply", Some(top)).await;
+
+    let edit = PutCommentRequest { body: String::from("From unit testing") };
+    let response = app.put_comment(owner_jwt.clone(), pinpoint_id, top, edit.clone()).await;
+    assert_eq!(response.status(), 401);
+    let response = app.put_comment(other_jwt.clone(), pinpoint_id, top, edit.clone()).await;
+    assert_eq!(response.status(), 200);
+    let comments = app.get_comments(owner_jwt.clone(), pinpoint_id, GetCommentsRequest::default()).await
+        .json::<Vec<GetCommentResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(comments[0].body, "From unit testing");
+    assert!(comments[0].modified_at.is_some());
+
+    let response = app.delete_comment(owner_jwt.clone(), pinpoint_id, top).await;
+    assert_eq!(response.status(), 401);
+    let response = app.delete_comment(other_jwt.clone(), pinpoint_id, top).await;
+    assert_eq!(response.status(), 200);
+    let response = app.delete_comment(other_jwt.clone(), pinpoint_id, top).await;
+    assert_eq!(response.status(), 404);
+    // The reply went with its parent, along with both bodies
+    let remaining = sqlx::query!("SELECT COUNT(*) AS count FROM pinpoint_comments")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count comments.");
+    assert_eq!(remaining.count, Some(0));
+    let contents = sqlx::query!("SELECT description FROM contents")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch contents.");
+    assert_eq!(contents.len(), 1);
+}
+
+#[tokio::test]
+async fn comments_on_hidden_pinpoints_are_not_found() {
+    let app = spawn_app().await;
+    let owner = String::from("TestGeneratedUser");
+    let owner_jwt = app.sign_up_test_user(owner.as_str(),
+                                          "initialtestingemail@something.com", None).await;
+    let other_jwt = app.sign_up_test_user("SomeoneElse",
+                                          "someoneelse@something.com", None).await;
+    let pinpoint_id = post_test_pinpoint(&app, owner_jwt.clone(), owner.clone(),
+                                         PinpointVisibility::Private).await;
+    post_test_comment(&app, owner_jwt.clone(), pinpoint_id, "Note to self", None).await;
+
+    let response = app.get_comments(other_jwt.clone(), pinpoint_id,
+                                    GetCommentsRequest::default()).await;
+    assert_eq!(response.status(), 404);
+    let response = app.post_comment(other_jwt.clone(), pinpoint_id,
+                                    PostCommentRequest::new(String::from("Hello"), None)).await;
+    assert_eq!(response.status(), 404);
+}
@@ -1,4 +1,5 @@
 use std::io::Cursor;
+use std::sync::Arc;
 use anyhow::anyhow;
 use image::{ColorType, DynamicImage, ImageFormat, ImageResult};
 use once_cell::sync::Lazy;
@@ -6,15 +7,22 @@ use serde_json::json;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use uuid::Uuid;
 use gvserver::authentication::{AuthParameters, AuthService};
+use gvserver::blob_storage::BlobStorage;
 use gvserver::configuration::{get_configuration, DatabaseSettings};
 use gvserver::domain::database::db_user::DbUser;
 use gvserver::routes::login::post::LoginData;
-use gvserver::startup::{get_connection_pool, Application};
+use gvserver::startup::{get_blob_storage, get_connection_pool, Application};
 use gvserver::telemetry::{get_subscriber, init_subscriber};
 use image::io::Reader;
 use gvserver::domain::user_sign_up::UserSignUp;
-use gvserver::routes::pinpoints::get::GetPinpointRequest;
-use gvserver::routes::pinpoints::post::PostPinpointRequest;
+use gvserver::routes::pinpoints::comments::{GetCommentsRequest, PostCommentRequest, PutCommentRequest};
+use gvserver::routes::pinpoints::get::{GetClustersRequest, GetHeatmapRequest, GetPinpointRequest,
+                                       GetPopularTagsRequest};
+use gvserver::routes::pinpoints::post::{PostPinpointBatchRequest, PostPinpointRequest};
+use gvserver::routes::pinpoints::import::ImportPinpointsRequest;
+use gvserver::routes::pinpoints::put::PutPinpointRequest;
+use gvserver::routes::users::zones::PostPrivateZoneRequest;
+use gvserver::routes::pinpoints::stream::GetPinpointStreamRequest;
 use gvserver::routes::users::get::{GetUsersRequest, UserResponse};
 use gvserver::routes::users::post::PostUserRequest;
 use gvserver::routes::users::put::put_user_request::PutUserRequest;
@@ -38,7 +46,8 @@ pub struct TestApp {
     pub db_pool: PgPool,
     pub test_user: TestUser,
     pub api_client: reqwest::Client,
-    pub auth_service: AuthService
+    pub auth_service: AuthService,
+    pub blob_storage: Arc<dyn BlobStorage>
 }
 
 impl TestApp {
@@ -112,6 +121,174 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_pinpoints_geojson(&self, jwt: String, username: String,
+                                       query: GetPinpointRequest) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/pinpoints/{}", &self.address, username))
+            .header("Authorization", jwt)
+            .header("Accept", "application/geo+json")
+            .query(&query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_pinpoint_clusters(&self, jwt: String, username: String,
+                                       query: GetClustersRequest) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/pinpoints/{}/clusters", &self.address, username))
+            .header("Authorization", jwt)
+            .query(&query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_popular_tags(&self, jwt: String, username: String,
+                                  query: GetPopularTagsRequest) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/pinpoints/{}/tags", &self.address, username))
+            .header("Authorization", jwt)
+            .query(&query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_pinpoint_heatmap(&self, jwt: String, username: String,
+                                      query: GetHeatmapRequest) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/pinpoints/{}/heatmap", &self.address, username))
+            .header("Authorization", jwt)
+            .query(&query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_pinpoint_stream(&self, jwt: String, username: String,
+                                     query: GetPinpointStreamRequest) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/pinpoints/{}/stream", &self.address, username))
+            .header("Authorization", jwt)
+            .query(&query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn import_pinpoints(&self, jwt: String, username: String,
+                                  query: ImportPinpointsRequest, file: String) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/pinpoints/{}/import", &self.address, username))
+            .header("Content-Type", "application/xml")
+            .header("Authorization", jwt)
+            .query(&query)
+            .body(file)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_export(&self, jwt: String, username: String) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/users/{}/exports", &self.address, username))
+            .header("Authorization", jwt)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_export(&self, jwt: String, username: String,
+                            export_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/users/{}/exports/{}", &self.address, username, export_id))
+            .header("Authorization", jwt)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_export_archive(&self, jwt: String, username: String,
+                                    export_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/users/{}/exports/{}/archive", &self.address, username, export_id))
+            .header("Authorization", jwt)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_pinpoint_batch(&self, jwt: String, body: PostPinpointBatchRequest)
+        -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/pinpoints/batch", &self.address))
+            .header("Authorization", jwt)
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_private_zones(&self, jwt: String, username: String) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/users/{}/zones", &self.address, username))
+            .header("Authorization", jwt)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_private_zone(&self, jwt: String, username: String,
+                                   body: PostPrivateZoneRequest) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/users/{}/zones", &self.address, username))
+            .header("Authorization", jwt)
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_private_zone(&self, jwt: String, username: String,
+                                     zone_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .delete(format!("{}/users/{}/zones/{}", &self.address, username, zone_id))
+            .header("Authorization", jwt)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_follows(&self, jwt: String, username: String) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/users/{}/follows", &self.address, username))
+            .header("Authorization", jwt)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_follow(&self, jwt: String, username: String,
+                            followee: String) -> reqwest::Response {
+        self.api_client
+            .put(format!("{}/users/{}/follows/{}", &self.address, username, followee))
+            .header("Authorization", jwt)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_follow(&self, jwt: String, username: String,
+                               followee: String) -> reqwest::Response {
+        self.api_client
+            .delete(format!("{}/users/{}/follows/{}", &self.address, username, followee))
+            .header("Authorization", jwt)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_pinpoints(&self, jwt: String, body: PostPinpointRequest) -> reqwest::Response
     {
         let json_body = json!(body).to_string();
@@ -125,6 +302,87 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn put_pinpoints(&self, jwt: String, pinpoint_id: Uuid, body: PutPinpointRequest)
+                               -> reqwest::Response
+    {
+        let json_body = json!(body).to_string();
+        self.api_client
+            .put(format!("{}/pinpoints/{}", &self.address, pinpoint_id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", jwt)
+            .body(json_body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_reaction(&self, jwt: String, pinpoint_id: Uuid, body: serde_json::Value)
+                              -> reqwest::Response
+    {
+        self.api_client
+            .put(format!("{}/pinpoints/{}/reaction", &self.address, pinpoint_id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", jwt)
+            .body(body.to_string())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_reaction(&self, jwt: String, pinpoint_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .delete(format!("{}/pinpoints/{}/reaction", &self.address, pinpoint_id))
+            .header("Authorization", jwt)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_comments(&self, jwt: String, pinpoint_id: Uuid, query: GetCommentsRequest)
+                              -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/pinpoints/{}/comments", &self.address, pinpoint_id))
+            .header("Authorization", jwt)
+            .query(&query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_comment(&self, jwt: String, pinpoint_id: Uuid, body: PostCommentRequest)
+                              -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/pinpoints/{}/comments", &self.address, pinpoint_id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", jwt)
+            .body(json!(body).to_string())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_comment(&self, jwt: String, pinpoint_id: Uuid, comment_id: Uuid,
+                             body: PutCommentRequest) -> reqwest::Response {
+        self.api_client
+            .put(format!("{}/pinpoints/{}/comments/{}", &self.address, pinpoint_id, comment_id))
+            .header("Content-Type", "application/json")
+            .header("Authorization", jwt)
+            .body(json!(body).to_string())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_comment(&self, jwt: String, pinpoint_id: Uuid, comment_id: Uuid)
+                                -> reqwest::Response {
+        self.api_client
+            .delete(format!("{}/pinpoints/{}/comments/{}", &self.address, pinpoint_id, comment_id))
+            .header("Authorization", jwt)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn sign_up_test_user(&self, username: &str, email: &str, pw: Option<&str>)
     -> String {
         let request_data = PostUserRequest {
@@ -243,7 +501,8 @@ impl TestApp {
         rls.title AS role_title,
         COALESCE(con.id) AS contents_id,
         con.description AS contents_description,
-        con.attachment AS contents_attachment
+        con.attachment_key AS contents_attachment_key,
+        con.attachment_sha256 AS contents_attachment_sha256
         FROM users usr
         INNER JOIN user_roles usr_rls ON usr.id = usr_rls.user_id
         INNER JOIN roles rls ON rls.id = usr_rls.role_id
@@ -333,6 +592,11 @@ pub async fn spawn_app() -> TestApp {
         c.database.database_name = Uuid::new_v4().to_string();
         // Use a random OS port
         c.application.port = 0;
+        // Use a different blob directory for each test case
+        c.blob_storage.local_directory = std::env::temp_dir()
+            .join(format!("gvserver-blobs-{}", Uuid::new_v4()))
+            .to_string_lossy()
+            .to_string();
         // Use the mock server as email API
         // c.email_client.base_url = email_server.uri();
         c
@@ -362,7 +626,8 @@ pub async fn spawn_app() -> TestApp {
         db_pool: get_connection_pool(&configuration.database),
         test_user: TestUser::generate(),
         api_client: client,
-        auth_service
+        auth_service,
+        blob_storage: get_blob_storage(&configuration.blob_storage).expect("Failed to set up blob storage.")
     };
 
     //test_app.test_user.store_new_user(&test_app.db_pool).await;
@@ -421,3 +686,62 @@ pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
     assert_eq!(response.status().as_u16(), 303);
     assert_eq!(response.headers().get("Location").unwrap(), location);
 }
+
+// An encoded image to attach, attachments of different widths tell apart
+// images that got re-encoded on the way in
+pub fn test_image(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Cursor::new(Vec::new());
+    DynamicImage::new_rgb8(width, height)
+        .write_to(&mut bytes, ImageFormat::Png)
+        .expect("Failed to encode the test image.");
+    bytes.into_inner()
+}
+
+pub fn image_dimensions(bytes: &[u8]) -> (u32, u32) {
+    let image = image::load_from_memory(bytes).expect("The attachment is not an image.");
+    (image.width(), image.height())
+}
+
+// A JPEG with the EXIF a phone would add, including where it was taken
+pub fn test_photo(width: u32, height: u32, latitude: f64, longitude: f64) -> Vec<u8> {
+    use exif::{Field, In, Rational, Tag, Value};
+    let degrees = |x: f64| Value::Rational(vec![
+        Rational { num: (x.abs() * 1_000_000.0).round() as u32, denom: 1_000_000 },
+        Rational { num: 0, denom: 1 },
+        Rational { num: 0, denom: 1 }]);
+    let reference = |x: &[u8]| Value::Ascii(vec![x.to_vec()]);
+    let fields = [
+        Field { tag: Tag::Model, ifd_num: In::PRIMARY, value: reference(b"Phone") },
+        Field { tag: Tag::GPSLatitudeRef, ifd_num: In::PRIMARY,
+            value: reference(if latitude < 0.0 { b"S" } else { b"N" }) },
+        Field { tag: Tag::GPSLatitude, ifd_num: In::PRIMARY, value: degrees(latitude) },
+        Field { tag: Tag::GPSLongitudeRef, ifd_num: In::PRIMARY,
+            value: reference(if longitude < 0.0 { b"W" } else { b"E" }) },
+        Field { tag: Tag::GPSLongitude, ifd_num: In::PRIMARY, value: degrees(longitude) },
+    ];
+    let mut writer = exif::experimental::Writer::new();
+    for field in fields.iter() {
+        writer.push_field(field);
+    }
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, false).expect("Failed to write the EXIF.");
+    let tiff = tiff.into_inner();
+    let mut jpeg = Cursor::new(Vec::new());
+    DynamicImage::new_rgb8(width, height)
+        .write_to(&mut jpeg, ImageFormat::Jpeg)
+        .expect("Failed to encode the test photo.");
+    let jpeg = jpeg.into_inner();
+    let mut bytes = jpeg[..2].to_vec();
+    bytes.extend_from_slice(&[0xFF, 0xE1]);
+    bytes.extend_from_slice(&(8 + tiff.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(b"Exif\0\0");
+    bytes.extend_from_slice(&tiff);
+    bytes.extend_from_slice(&jpeg[2..]);
+    bytes
+}
+
+pub fn has_gps_exif(bytes: &[u8]) -> bool {
+    exif::Reader::new().read_from_container(&mut Cursor::new(bytes))
+        .map(|x| x.get_field(exif::Tag::GPSLatitude, exif::In::PRIMARY).is_some())
+        .unwrap_or(false)
+}
@@ -1,5 +1,6 @@
 mod health_check;
 mod helpers;
+mod comments;
 mod pinpoints;
 mod users;
 mod login;
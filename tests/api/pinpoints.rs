@@ -1,8 +1,21 @@
 use claim::assert_gt;
 use validator::HasLen;
-use gvserver::routes::pinpoints::get::{GetPinpointRequest, GetPinpointResponse};
-use gvserver::routes::pinpoints::post::PostPinpointRequest;
-use crate::helpers::{spawn_app};
+use gvserver::routes::pinpoints::get::{GetClusterResponse, GetClustersRequest, GetHeatmapCell,
+                                       GetHeatmapRequest, GetPinpointRequest,
+                                       GetPinpointResponse, GetPopularTagsRequest, GetTagResponse,
+                                       PinpointSort, TagMatch, NEXT_CURSOR_HEADER};
+use gvserver::routes::pinpoints::import::{ImportPinpointsRequest, ImportPinpointsResponse};
+use gvserver::routes::pinpoints::post::{BatchMode, PostPinpointBatchRequest,
+                                        PostPinpointBatchResponse, PostPinpointRequest,
+                                        MAX_BATCH_PINPOINTS};
+use gvserver::routes::pinpoints::put::PutPinpointRequest;
+use gvserver::routes::pinpoints::stream::{GetPinpointStreamRequest, PinpointStreamMessage};
+use serde_json::json;
+use uuid::Uuid;
+use gvserver::domain::{ImageSize, PinpointReaction, PinpointVisibility};
+use gvserver::blob_storage::{put_blob, sha256_hex, strip_legacy_exif};
+use gvserver::expiry_worker::{delete_queued_blobs, purge_expired_pinpoints};
+use crate::helpers::{has_gps_exif, image_dimensions, spawn_app, test_image, test_photo};
 
 #[tokio::test]
 async fn get_all_pinpoints_allowed_with_custom_credentials() {
@@ -11,9 +24,10 @@ async fn get_all_pinpoints_allowed_with_custom_credentials() {
     let request_body = GetPinpointRequest {
         latitude: Some(5.0),
         longitude: Some(5.0),
-        proximity: Some(555.0),
+        radius: Some(20_000_000.0),
         pinpoint_id: None,
-        username: None
+        username: None,
+        ..Default::default()
     };
     let response = app.get_pinpoints(jwt, String::from("TESTUSER"), request_body).await;
     let status = &response.status();
@@ -34,9 +48,10 @@ async fn get_all_pinpoints_fails_with_invalid_jwts() {
     let request_body = GetPinpointRequest {
         latitude: None,
         longitude: None,
-        proximity: None,
+        radius: None,
         pinpoint_id: None,
-        username: None
+        username: None,
+        ..Default::default()
     };
     let response = app.get_pinpoints(jwt, String::from("TESTUSER"), request_body).await;
     assert_eq!(response.status(), 401);
@@ -51,9 +66,10 @@ pub async fn get_all_pinpoints_allowed_with_new_user_jwt() {
     let request_body = GetPinpointRequest {
         latitude: None,
         longitude: None,
-        proximity: None,
+        radius: None,
         pinpoint_id: None,
-        username: None
+        username: None,
+        ..Default::default()
     };
     let response = app.get_pinpoints(jwt, username.clone(), request_body).await;
     assert_eq!(response.status(), 200);
@@ -108,8 +124,8 @@ pub async fn post_pinpoint_with_attachment() {
     let jwt = app.sign_up_test_user(username.as_str(),
                                     "initialtestingemail@something.com", None).await;
     let input_path = format!("{}/icantdoitsquidward.jpg", app.get_test_input_dir_path());
-    let attachment = app.load_img_bytes_at(&input_path).await;
-    let img_bytes = attachment.expect("Failed to load image bytes.");
+    // Attachments are uploaded as encoded image files
+    let img_bytes = std::fs::read(&input_path).expect("Failed to load image bytes.");
     let expensive = img_bytes.clone().len();
     println!("Loaded image byte length: {:?}", expensive);
 
@@ -129,21 +145,22 @@ pub async fn post_pinpoint_with_attachment_full_validation() {
                                     "initialtestingemail@something.com", None).await;
     let input_path = format!("{}/icantdoitsquidward.jpg", app.get_test_input_dir_path());
     let output_path = format!("{}/icanindeeddoitsquidward.jpg", app.get_test_output_dir_path());
-    let attachment = app.load_img_bytes_at(&input_path).await;
-    let img_bytes = attachment.expect("Failed to load image bytes.");
+    let img_bytes = std::fs::read(&input_path).expect("Failed to load image bytes.");
     let expensive = img_bytes.clone().len();
     println!("Loaded image byte length: {:?}", expensive);
     let request_body = PostPinpointRequest::new(
         12.34, 12.34, String::from(
-            "From unit testing"), Some(img_bytes), username.clone());
+            "From unit testing"), Some(img_bytes.clone()), username.clone());
     let response = app.post_pinpoints(jwt.clone(), request_body).await;
     assert_eq!(response.status(), 200);
     let get_req = GetPinpointRequest {
         latitude: Some(12.34),
         longitude: Some(12.34),
-        proximity: Some(0.01),
+        radius: Some(1000.0),
         pinpoint_id: None,
         username: None,
+        attachment_size: Some(ImageSize::Full),
+        ..Default::default()
     };
     let get_back = app.get_pinpoints(
         jwt, username.to_string(), get_req).await;
@@ -154,8 +171,8 @@ pub async fn post_pinpoint_with_attachment_full_validation() {
     if !json_return[0].attachment.is_empty() {
         println!("Attachment in GetPinpointResponse: length {}", json_return[0].attachment.len());
     }
-    let save_attempt = app.save_img_bytes_at(
-        &output_path, &json_return[0].attachment, 50).await;
+    assert_eq!(image_dimensions(&json_return[0].attachment), image_dimensions(&img_bytes));
+    let save_attempt = std::fs::write(&output_path, &json_return[0].attachment);
     assert!(save_attempt.is_ok());
 }
 
@@ -177,9 +194,10 @@ pub async fn get_all_pinpoints_not_allowed_with_new_user_faulty_jwt() {
     let request_body = GetPinpointRequest {
         latitude: None,
         longitude: None,
-        proximity: None,
+        radius: None,
         pinpoint_id: None,
-        username: None
+        username: None,
+        ..Default::default()
     };
     let response = app.get_pinpoints(jwt, username.clone(), request_body).await;
     assert_eq!(response.status(), 200);
@@ -187,9 +205,10 @@ pub async fn get_all_pinpoints_not_allowed_with_new_user_faulty_jwt() {
     let request_body = GetPinpointRequest {
         latitude: None,
         longitude: None,
-        proximity: None,
+        radius: None,
         pinpoint_id: None,
-        username: None
+        username: None,
+        ..Default::default()
     };
     let response = app.get_pinpoints(evil_jwt, username.to_string(), request_body).await;
     assert_eq!(response.status(), 401);
@@ -202,7 +221,7 @@ async fn post_get_pinpoint_allowed_with_generated_user() {
     let jwt = app.sign_up_test_user(username.as_str(),
                                     "initialtestingemail@something.com", None).await;
     let pinpoint_request_body = PostPinpointRequest::new(
-        123.0,
+        23.0,
         123.0,
         String::from("Description: This pinpoint was added from unit testing."),
         None,
@@ -215,9 +234,10 @@ async fn post_get_pinpoint_allowed_with_generated_user() {
     let request_body = GetPinpointRequest {
         latitude: None,
         longitude: None,
-        proximity: None,
+        radius: None,
         pinpoint_id: None,
-        username: Some(username.clone())
+        username: Some(username.clone()),
+        ..Default::default()
     };
     let response = app.get_pinpoints(jwt.clone(), username.to_string(), request_body).await;
     assert_eq!(response.status(), 200);
@@ -228,3 +248,1502 @@ async fn post_get_pinpoint_allowed_with_generated_user() {
     // Assert
     assert_eq!(status.as_u16(), 200);
 }
+
+#[tokio::test]
+async fn get_pinpoints_by_radius_at_high_latitude() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    // Two degrees of longitude at 70 degrees north is only about 76 km
+    for (lat, lng) in [(70.0, 10.0), (70.0, 12.0), (71.0, 10.0)] {
+        let request_body = PostPinpointRequest::new(
+            lat, lng, String::from("From unit testing"), None, username.clone());
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+    let request_body = GetPinpointRequest {
+        latitude: Some(70.0),
+        longitude: Some(10.5),
+        radius: Some(80_000.0),
+        pinpoint_id: None,
+        username: None,
+        ..Default::default()
+    };
+    let response = app.get_pinpoints(jwt, username.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let json_return = response.json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(json_return.len(), 2);
+    // Nearest first, with the distance in meters
+    assert_eq!(json_return[0].longitude, 10.0);
+    assert_eq!(json_return[1].longitude, 12.0);
+    let nearest = json_return[0].distance.expect("Missing distance.");
+    assert!(nearest > 18_000.0 && nearest < 20_000.0);
+}
+
+#[tokio::test]
+async fn get_pinpoints_by_radius_across_the_antimeridian() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let request_body = PostPinpointRequest::new(
+        0.0, -179.99, String::from("From unit testing"), None, username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let request_body = GetPinpointRequest {
+        latitude: Some(0.0),
+        longitude: Some(179.99),
+        radius: Some(5_000.0),
+        pinpoint_id: None,
+        username: None,
+        ..Default::default()
+    };
+    let response = app.get_pinpoints(jwt, username.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let json_return = response.json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(json_return.len(), 1);
+}
+
+#[tokio::test]
+async fn get_pinpoints_radius_without_location_is_rejected() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let request_body = GetPinpointRequest {
+        latitude: None,
+        longitude: None,
+        radius: Some(5_000.0),
+        pinpoint_id: None,
+        username: None,
+        ..Default::default()
+    };
+    let response = app.get_pinpoints(jwt, username.clone(), request_body).await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn get_pinpoint_clusters_groups_nearby_pinpoints() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    for (lat, lng) in [(10.001, 10.001), (10.002, 10.002), (10.003, 10.001), (10.5, 10.5)] {
+        let request_body = PostPinpointRequest::new(
+            lat, lng, String::from("From unit testing"), None, username.clone());
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+    let request_body = GetClustersRequest {
+        min_latitude: 9.0,
+        min_longitude: 9.0,
+        max_latitude: 11.0,
+        max_longitude: 11.0,
+        zoom: 10
+    };
+    let response = app.get_pinpoint_clusters(jwt, username.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let json_return = response.json::<Vec<GetClusterResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(json_return.len(), 2);
+    assert_eq!(json_return[0].count, 3);
+    assert_eq!(json_return[0].sample_pinpoint_ids.len(), 3);
+    // Centroids are snapped to the 100 meter location grid
+    assert!((json_return[0].latitude - 10.002).abs() < 0.001);
+    assert_eq!(json_return[1].count, 1);
+}
+
+#[tokio::test]
+async fn get_pinpoint_clusters_rejects_invalid_bounding_box() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let request_body = GetClustersRequest {
+        min_latitude: 50.0,
+        min_longitude: 9.0,
+        max_latitude: 11.0,
+        max_longitude: 11.0,
+        zoom: 10
+    };
+    let response = app.get_pinpoint_clusters(jwt, username.clone(), request_body).await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn get_pinpoints_as_geojson_feature_collection() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let request_body = PostPinpointRequest::new(
+        12.5, -45.25, String::from("From unit testing"), None, username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let request_body = GetPinpointRequest {
+        latitude: None,
+        longitude: None,
+        radius: None,
+        pinpoint_id: None,
+        username: None,
+        ..Default::default()
+    };
+    let response = app.get_pinpoints_geojson(jwt, username.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers().get("Content-Type").unwrap(), "application/geo+json");
+    let json_return = response.json::<serde_json::Value>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(json_return["type"], "FeatureCollection");
+    let feature = &json_return["features"][0];
+    assert_eq!(feature["type"], "Feature");
+    assert_eq!(feature["geometry"]["type"], "Point");
+    assert_eq!(feature["geometry"]["coordinates"][0], -45.25);
+    assert_eq!(feature["geometry"]["coordinates"][1], 12.5);
+    assert_eq!(feature["properties"]["description"], "From unit testing");
+    assert_eq!(feature["properties"]["pinpoint_username"], username.as_str());
+}
+
+#[tokio::test]
+async fn get_pinpoints_pages_through_every_pinpoint_with_cursors() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    for i in 0..5 {
+        let request_body = PostPinpointRequest::new(
+            1.0 + i as f64, 1.0, format!("Pinpoint {}", i), None, username.clone());
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+    for sort in [PinpointSort::Oldest, PinpointSort::Newest, PinpointSort::Nearest] {
+        let mut cursor = None;
+        let mut descriptions = Vec::new();
+        let mut pages = 0;
+        loop {
+            let request_body = GetPinpointRequest {
+                latitude: Some(0.0),
+                longitude: Some(1.0),
+                limit: Some(2),
+                cursor: cursor.clone(),
+                sort: Some(sort),
+                ..Default::default()
+            };
+            let response = app.get_pinpoints(jwt.clone(), username.clone(), request_body).await;
+            assert_eq!(response.status(), 200);
+            cursor = response.headers().get("X-Next-Cursor")
+                .map(|x| x.to_str().unwrap().to_string());
+            let json_return = response.json::<Vec<GetPinpointResponse>>().await
+                .expect("Failed to get a JSON response back.");
+            assert!(json_return.len() <= 2);
+            descriptions.extend(json_return.into_iter().map(|x| x.description));
+            pages += 1;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(pages, 3);
+        let mut expected: Vec<String> = (0..5).map(|i| format!("Pinpoint {}", i)).collect();
+        if sort == PinpointSort::Newest {
+            expected.reverse();
+        }
+        assert_eq!(descriptions, expected);
+    }
+}
+
+#[tokio::test]
+async fn get_pinpoints_rejects_bad_paging_arguments() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let bad_requests = [
+        GetPinpointRequest { limit: Some(0), ..Default::default() },
+        GetPinpointRequest { limit: Some(100_000), ..Default::default() },
+        GetPinpointRequest { cursor: Some(String::from("garbage")), ..Default::default() },
+        GetPinpointRequest { sort: Some(PinpointSort::Nearest), ..Default::default() },
+    ];
+    for request_body in bad_requests {
+        let response = app.get_pinpoints(jwt.clone(), username.clone(), request_body).await;
+        assert_eq!(response.status(), 400);
+    }
+}
+
+#[tokio::test]
+async fn post_pinpoint_rejects_out_of_range_coordinates() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    for (lat, lng) in [(500.0, 5.0), (-90.5, 5.0), (5.0, 180.5), (5.0, -500.0)] {
+        let request_body = PostPinpointRequest::new(
+            lat, lng, String::from("From unit testing"), None, username.clone());
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 400);
+        let body = response.text().await.unwrap();
+        assert!(body.contains("is not a valid"));
+    }
+}
+
+#[tokio::test]
+async fn put_pinpoint_edits_in_place() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Frm unit testing"), None, username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let get_request = GetPinpointRequest { username: Some(username.clone()), ..Default::default() };
+    let before = app.get_pinpoints(jwt.clone(), username.clone(), get_request).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(before.len(), 1);
+    assert!(before[0].modified_at.is_none());
+    let pinpoint_id = before[0].pinpoint_id.unwrap();
+
+    let put_request = PutPinpointRequest {
+        latitude: Some(6.5),
+        description: Some(String::from("From unit testing")),
+        ..Default::default()
+    };
+    let response = app.put_pinpoints(jwt.clone(), pinpoint_id, put_request).await;
+    assert_eq!(response.status(), 200);
+
+    let get_request = GetPinpointRequest { username: Some(username.clone()), ..Default::default() };
+    let after = app.get_pinpoints(jwt.clone(), username.clone(), get_request).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(after.len(), 1);
+    assert_eq!(after[0].pinpoint_id, Some(pinpoint_id));
+    assert_eq!(after[0].added_at, before[0].added_at);
+    assert!(after[0].modified_at.is_some());
+    assert_eq!(after[0].description, "From unit testing");
+    assert_eq!(after[0].latitude, 6.5);
+    assert_eq!(after[0].longitude, 5.0);
+}
+
+#[tokio::test]
+async fn put_pinpoint_rejects_other_users_and_bad_input() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let other_jwt = app.sign_up_test_user("SomeoneElse",
+                                          "someoneelse@something.com", None).await;
+    let request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let get_request = GetPinpointRequest { username: Some(username.clone()), ..Default::default() };
+    let pinpoints = app.get_pinpoints(jwt.clone(), username.clone(), get_request).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    let pinpoint_id = pinpoints[0].pinpoint_id.unwrap();
+
+    let put_request = PutPinpointRequest {
+        description: Some(String::from("Not mine to edit")),
+        ..Default::default()
+    };
+    let response = app.put_pinpoints(other_jwt, pinpoint_id, put_request.clone()).await;
+    assert_eq!(response.status(), 401);
+    let response = app.put_pinpoints(jwt.clone(), Uuid::new_v4(), put_request).await;
+    assert_eq!(response.status(), 404);
+    let response = app.put_pinpoints(
+        jwt.clone(), pinpoint_id, PutPinpointRequest::default()).await;
+    assert_eq!(response.status(), 400);
+    let put_request = PutPinpointRequest { longitude: Some(200.0), ..Default::default() };
+    let response = app.put_pinpoints(jwt.clone(), pinpoint_id, put_request).await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn get_pinpoints_respects_visibility() {
+    let app = spawn_app().await;
+    let owner = String::from("TestGeneratedUser");
+    let owner_jwt = app.sign_up_test_user(owner.as_str(),
+                                          "initialtestingemail@something.com", None).await;
+    let viewer = String::from("SomeoneElse");
+    let viewer_jwt = app.sign_up_test_user(viewer.as_str(),
+                                           "someoneelse@something.com", None).await;
+    for visibility in [PinpointVisibility::Public, PinpointVisibility::Followers,
+        PinpointVisibility::Private] {
+        let mut request_body = PostPinpointRequest::new(
+            5.0, 5.0, visibility.to_string(), None, owner.clone());
+        request_body.visibility = Some(visibility);
+        let response = app.post_pinpoints(owner_jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+    let visible_to = |jwt: String, username: String| async {
+        let request_body = GetPinpointRequest { sort: Some(PinpointSort::Oldest), ..Default::default() };
+        let response = app.get_pinpoints(jwt, username, request_body).await;
+        assert_eq!(response.status(), 200);
+        response.json::<Vec<GetPinpointResponse>>().await
+            .expect("Failed to get a JSON response back.")
+            .into_iter().map(|x| x.description).collect::<Vec<String>>()
+    };
+    assert_eq!(visible_to(owner_jwt.clone(), owner.clone()).await,
+               vec!["public", "followers", "private"]);
+    assert_eq!(visible_to(viewer_jwt.clone(), viewer.clone()).await, vec!["public"]);
+
+    let response = app.put_follow(viewer_jwt.clone(), viewer.clone(), owner.clone()).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(visible_to(viewer_jwt.clone(), viewer.clone()).await,
+               vec!["public", "followers"]);
+    let response = app.delete_follow(viewer_jwt.clone(), viewer.clone(), owner.clone()).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(visible_to(viewer_jwt.clone(), viewer.clone()).await, vec!["public"]);
+}
+
+#[tokio::test]
+async fn expired_pinpoints_are_hidden_and_purged() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let mut request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Ephemeral"), None, username.clone());
+    request_body.ttl_seconds = Some(1);
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Forever"), None, username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+
+    let get_descriptions = || async {
+        let request_body = GetPinpointRequest { sort: Some(PinpointSort::Oldest), ..Default::default() };
+        app.get_pinpoints(jwt.clone(), username.clone(), request_body).await
+            .json::<Vec<GetPinpointResponse>>().await
+            .expect("Failed to get a JSON response back.")
+            .into_iter().map(|x| x.description).collect::<Vec<String>>()
+    };
+    assert_eq!(get_descriptions().await, vec!["Ephemeral", "Forever"]);
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    assert_eq!(get_descriptions().await, vec!["Forever"]);
+
+    let purged = purge_expired_pinpoints(&app.db_pool).await
+        .expect("Failed to purge expired pinpoints.");
+    assert_eq!(purged, 1);
+    let remaining = sqlx::query!("SELECT description FROM contents")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch contents.");
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].description.as_deref(), Some("Forever"));
+}
+
+#[tokio::test]
+async fn purged_pinpoints_leave_no_blobs_behind() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let attachment = test_image(1024, 512);
+    let mut request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Ephemeral"), Some(attachment), username.clone());
+    request_body.ttl_seconds = Some(1);
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+
+    // contents only keeps where the attachment is and what it is
+    let stored = sqlx::query!(
+        r#"SELECT attachment_key AS "attachment_key!", attachment_small_key, attachment_medium_key,
+        attachment_size, attachment_sha256, attachment_mime FROM contents"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch contents.");
+    let full = app.blob_storage.get(&stored.attachment_key).await
+        .expect("The attachment is not in blob storage.");
+    assert_eq!(stored.attachment_size, Some(full.len() as i64));
+    assert_eq!(stored.attachment_sha256.as_deref(), Some(sha256_hex(&full).as_str()));
+    assert_eq!(stored.attachment_mime.as_deref(), Some("image/jpeg"));
+    let keys = [Some(stored.attachment_key), stored.attachment_small_key, stored.attachment_medium_key]
+        .into_iter().flatten().collect::<Vec<String>>();
+    assert_eq!(keys.len(), 3);
+
+    tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+    let purged = purge_expired_pinpoints(&app.db_pool).await
+        .expect("Failed to purge expired pinpoints.");
+    assert_eq!(purged, 1);
+    // Blobs outlive their rows until the queue is worked through
+    assert!(app.blob_storage.get(&keys[0]).await.is_ok());
+    let deleted = delete_queued_blobs(&app.db_pool, app.blob_storage.as_ref()).await
+        .expect("Failed to delete queued blobs.");
+    assert_eq!(deleted, 3);
+    for key in &keys {
+        assert!(app.blob_storage.get(key).await.is_err(), "{} is still stored", key);
+    }
+    assert_eq!(delete_queued_blobs(&app.db_pool, app.blob_storage.as_ref()).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn post_pinpoint_rejects_bad_expiry() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let mut past = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, username.clone());
+    past.expires_at = Some(chrono::Utc::now() - chrono::Duration::hours(1));
+    let mut both = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, username.clone());
+    both.expires_at = Some(chrono::Utc::now() + chrono::Duration::hours(1));
+    both.ttl_seconds = Some(60);
+    let mut negative = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, username.clone());
+    negative.ttl_seconds = Some(-60);
+    let mut overflowing = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, username.clone());
+    overflowing.ttl_seconds = Some(i64::MAX);
+    for request_body in [past, both, negative, overflowing] {
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 400);
+    }
+}
+
+#[tokio::test]
+async fn reactions_are_counted_per_type() {
+    let app = spawn_app().await;
+    let owner = String::from("TestGeneratedUser");
+    let owner_jwt = app.sign_up_test_user(owner.as_str(),
+                                          "initialtestingemail@something.com", None).await;
+    let viewer = String::from("SomeoneElse");
+    let viewer_jwt = app.sign_up_test_user(viewer.as_str(),
+                                           "someoneelse@something.com", None).await;
+    let request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, owner.clone());
+    let response = app.post_pinpoints(owner_jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let get_pinpoint = |jwt: String, username: String| async {
+        let mut pinpoints = app.get_pinpoints(jwt, username, GetPinpointRequest::default()).await
+            .json::<Vec<GetPinpointResponse>>().await
+            .expect("Failed to get a JSON response back.");
+        assert_eq!(pinpoints.len(), 1);
+        pinpoints.remove(0)
+    };
+    let pinpoint = get_pinpoint(owner_jwt.clone(), owner.clone()).await;
+    let pinpoint_id = pinpoint.pinpoint_id.unwrap();
+    assert!(pinpoint.reactions.is_empty());
+    assert_eq!(pinpoint.viewer_reaction, None);
+
+    let response = app.put_reaction(owner_jwt.clone(), pinpoint_id, json!({"reaction": "fire"})).await;
+    assert_eq!(response.status(), 200);
+    let response = app.put_reaction(viewer_jwt.clone(), pinpoint_id, json!({"reaction": "heart"})).await;
+    assert_eq!(response.status(), 200);
+    // Reacting again replaces the earlier reaction
+    let response = app.put_reaction(viewer_jwt.clone(), pinpoint_id, json!({"reaction": "fire"})).await;
+    assert_eq!(response.status(), 200);
+
+    let pinpoint = get_pinpoint(owner_jwt.clone(), owner.clone()).await;
+    assert_eq!(pinpoint.reactions.len(), 1);
+    assert_eq!(pinpoint.reactions.get(&PinpointReaction::Fire), Some(&2));
+    assert_eq!(pinpoint.viewer_reaction, Some(PinpointReaction::Fire));
+
+    let response = app.delete_reaction(owner_jwt.clone(), pinpoint_id).await;
+    assert_eq!(response.status(), 200);
+    let response = app.delete_reaction(owner_jwt.clone(), pinpoint_id).await;
+    assert_eq!(response.status(), 404);
+    let pinpoint = get_pinpoint(viewer_jwt.clone(), viewer.clone()).await;
+    assert_eq!(pinpoint.reactions.get(&PinpointReaction::Fire), Some(&1));
+    assert_eq!(pinpoint.viewer_reaction, Some(PinpointReaction::Fire));
+    let pinpoint = get_pinpoint(owner_jwt.clone(), owner.clone()).await;
+    assert_eq!(pinpoint.viewer_reaction, None);
+}
+
+#[tokio::test]
+async fn reactions_reject_unknown_kinds_and_hidden_pinpoints() {
+    let app = spawn_app().await;
+    let owner = String::from("TestGeneratedUser");
+    let owner_jwt = app.sign_up_test_user(owner.as_str(),
+                                          "initialtestingemail@something.com", None).await;
+    let viewer = String::from("SomeoneElse");
+    let viewer_jwt = app.sign_up_test_user(viewer.as_str(),
+                                           "someoneelse@something.com", None).await;
+    let mut request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, owner.clone());
+    request_body.visibility = Some(PinpointVisibility::Private);
+    let response = app.post_pinpoints(owner_jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let pinpoints = app.get_pinpoints(owner_jwt.clone(), owner.clone(), GetPinpointRequest::default()).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    let pinpoint_id = pinpoints[0].pinpoint_id.unwrap();
+
+    let response = app.put_reaction(owner_jwt.clone(), pinpoint_id, json!({"reaction": "shrug"})).await;
+    assert_eq!(response.status(), 400);
+    let response = app.put_reaction(viewer_jwt.clone(), pinpoint_id, json!({"reaction": "heart"})).await;
+    assert_eq!(response.status(), 404);
+    let response = app.put_reaction(owner_jwt.clone(), Uuid::new_v4(), json!({"reaction": "heart"})).await;
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn get_pinpoints_filters_by_any_or_all_tags() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let test_cases = vec![
+        ("Lunch", vec!["Food", "#lunch"]),
+        ("Beach", vec!["Sunset", "beach"]),
+        ("Dinner", vec!["food", "sunset", "FOOD"]),
+    ];
+    for (description, tags) in test_cases {
+        let mut request_body = PostPinpointRequest::new(
+            5.0, 5.0, String::from(description), None, username.clone());
+        request_body.tags = tags.into_iter().map(String::from).collect();
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+    let get_descriptions = |tags: &str, tag_match: Option<TagMatch>| {
+        let request_body = GetPinpointRequest {
+            sort: Some(PinpointSort::Oldest),
+            tags: Some(String::from(tags)),
+            tag_match,
+            ..Default::default()
+        };
+        let jwt = jwt.clone();
+        let username = username.clone();
+        let app = &app;
+        async move {
+            let response = app.get_pinpoints(jwt, username, request_body).await;
+            assert_eq!(response.status(), 200);
+            response.json::<Vec<GetPinpointResponse>>().await
+                .expect("Failed to get a JSON response back.")
+        }
+    };
+    let any = get_descriptions("food,beach", None).await;
+    assert_eq!(any.iter().map(|x| x.description.as_str()).collect::<Vec<&str>>(),
+               vec!["Lunch", "Beach", "Dinner"]);
+    assert_eq!(any[0].tags, vec!["food", "lunch"]);
+    assert_eq!(any[2].tags, vec!["food", "sunset"]);
+    let all = get_descriptions("Food, sunset", Some(TagMatch::All)).await;
+    assert_eq!(all.iter().map(|x| x.description.as_str()).collect::<Vec<&str>>(),
+               vec!["Dinner"]);
+    assert!(get_descriptions("coffee", None).await.is_empty());
+
+    let request_body = GetPinpointRequest { tags: Some(String::from("no good!")), ..Default::default() };
+    let response = app.get_pinpoints(jwt.clone(), username.clone(), request_body).await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn post_pinpoint_rejects_bad_tags() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let test_cases = vec![
+        vec![String::from("")],
+        vec![String::from("semi;colon")],
+        vec!["a".repeat(33)],
+        (0..11).map(|x| x.to_string()).collect(),
+    ];
+    for tags in test_cases {
+        let mut request_body = PostPinpointRequest::new(
+            5.0, 5.0, String::from("From unit testing"), None, username.clone());
+        request_body.tags = tags;
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 400);
+    }
+}
+
+#[tokio::test]
+async fn popular_tags_are_counted_in_the_bounding_box() {
+    let app = spawn_app().await;
+    let owner = String::from("TestGeneratedUser");
+    let owner_jwt = app.sign_up_test_user(owner.as_str(),
+                                          "initialtestingemail@something.com", None).await;
+    let viewer = String::from("SomeoneElse");
+    let viewer_jwt = app.sign_up_test_user(viewer.as_str(),
+                                           "someoneelse@something.com", None).await;
+    let test_cases = vec![
+        (5.0, vec!["food", "sunset"], PinpointVisibility::Public),
+        (5.5, vec!["food"], PinpointVisibility::Public),
+        (6.0, vec!["sunset"], PinpointVisibility::Private),
+        (50.0, vec!["food", "sunset"], PinpointVisibility::Public),
+    ];
+    for (latitude, tags, visibility) in test_cases {
+        let mut request_body = PostPinpointRequest::new(
+            latitude, 5.0, String::from("From unit testing"), None, owner.clone());
+        request_body.tags = tags.into_iter().map(String::from).collect();
+        request_body.visibility = Some(visibility);
+        let response = app.post_pinpoints(owner_jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+    let request_body = || GetPopularTagsRequest {
+        min_latitude: 0.0,
+        min_longitude: 0.0,
+        max_latitude: 10.0,
+        max_longitude: 10.0,
+        limit: None,
+    };
+    let counts = |tags: Vec<GetTagResponse>| tags.into_iter()
+        .map(|x| (x.tag, x.count))
+        .collect::<Vec<(String, i64)>>();
+
+    let response = app.get_popular_tags(owner_jwt.clone(), owner.clone(), request_body()).await;
+    assert_eq!(response.status(), 200);
+    let tags = response.json::<Vec<GetTagResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(counts(tags), vec![(String::from("food"), 2), (String::from("sunset"), 2)]);
+
+    let response = app.get_popular_tags(viewer_jwt.clone(), viewer.clone(), request_body()).await;
+    let tags = response.json::<Vec<GetTagResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(counts(tags), vec![(String::from("food"), 2), (String::from("sunset"), 1)]);
+
+    let bad_request = GetPopularTagsRequest { limit: Some(0), ..request_body() };
+    let response = app.get_popular_tags(viewer_jwt.clone(), viewer.clone(), bad_request).await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn search_ranks_matching_descriptions_near_a_location() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let test_cases = vec![
+        (5.0, "Great coffee, the best coffee near the station"),
+        (5.0, "A quiet park bench"),
+        (5.001, "Coffee cart"),
+        (40.0, "Coffee far away from everything"),
+    ];
+    for (latitude, description) in test_cases {
+        let request_body = PostPinpointRequest::new(
+            latitude, 5.0, String::from(description), None, username.clone());
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+    let request_body = GetPinpointRequest {
+        latitude: Some(5.0),
+        longitude: Some(5.0),
+        radius: Some(10_000.0),
+        search: Some(String::from("coffees")),
+        ..Default::default()
+    };
+    let response = app.get_pinpoints(jwt.clone(), username.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let pinpoints = response.json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(pinpoints.iter().map(|x| x.description.as_str()).collect::<Vec<&str>>(),
+               vec!["Great coffee, the best coffee near the station", "Coffee cart"]);
+    assert!(pinpoints[0].rank.unwrap() >= pinpoints[1].rank.unwrap());
+    assert!(pinpoints[1].distance.is_some());
+
+    // Paging by relevance visits every match once
+    let mut descriptions: Vec<String> = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let request_body = GetPinpointRequest {
+            search: Some(String::from("coffee")),
+            limit: Some(1),
+            cursor: cursor.clone(),
+            ..Default::default()
+        };
+        let response = app.get_pinpoints(jwt.clone(), username.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+        cursor = response.headers().get(NEXT_CURSOR_HEADER)
+            .map(|x| x.to_str().unwrap().to_string());
+        let page = response.json::<Vec<GetPinpointResponse>>().await
+            .expect("Failed to get a JSON response back.");
+        descriptions.extend(page.into_iter().map(|x| x.description));
+        if cursor.is_none() {
+            break;
+        }
+    }
+    descriptions.sort();
+    assert_eq!(descriptions, vec!["Coffee cart", "Coffee far away from everything",
+                                  "Great coffee, the best coffee near the station"]);
+}
+
+#[tokio::test]
+async fn search_rejects_bad_arguments() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let test_cases = vec![
+        GetPinpointRequest { search: Some(String::from("  ")), ..Default::default() },
+        GetPinpointRequest { search: Some("a".repeat(201)), ..Default::default() },
+        GetPinpointRequest { sort: Some(PinpointSort::Relevance), ..Default::default() },
+    ];
+    for request_body in test_cases {
+        let response = app.get_pinpoints(jwt.clone(), username.clone(), request_body).await;
+        assert_eq!(response.status(), 400);
+    }
+}
+
+#[tokio::test]
+async fn pinpoints_keep_their_attachments_in_order() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let mut request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Many"), None, username.clone());
+    request_body.attachments = vec![test_image(10, 10), test_image(20, 10), test_image(30, 10)];
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Legacy"), Some(test_image(9, 9)), username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("None"), None, username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+
+    let get_pinpoints = || async {
+        let request_body = GetPinpointRequest { sort: Some(PinpointSort::Oldest), ..Default::default() };
+        app.get_pinpoints(jwt.clone(), username.clone(), request_body).await
+            .json::<Vec<GetPinpointResponse>>().await
+            .expect("Failed to get a JSON response back.")
+    };
+    let pinpoints = get_pinpoints().await;
+    assert_eq!(pinpoints.len(), 3);
+    assert_eq!(pinpoints[0].description, "Many");
+    let widths = |attachments: &Vec<Vec<u8>>| attachments.iter()
+        .map(|x| image_dimensions(x).0)
+        .collect::<Vec<u32>>();
+    assert_eq!(widths(&pinpoints[0].attachments), vec![10, 20, 30]);
+    assert_eq!(image_dimensions(&pinpoints[0].attachment), (10, 10));
+    assert_eq!(widths(&pinpoints[1].attachments), vec![9]);
+    assert_eq!(image_dimensions(&pinpoints[1].attachment), (9, 9));
+    assert!(pinpoints[2].attachments.is_empty());
+    assert!(pinpoints[2].attachment.is_empty());
+
+    // Editing the legacy attachment only replaces the first one
+    let put_request = PutPinpointRequest { attachment: Some(test_image(7, 7)), ..Default::default() };
+    let response = app.put_pinpoints(jwt.clone(), pinpoints[0].pinpoint_id.unwrap(), put_request).await;
+    assert_eq!(response.status(), 200);
+    let pinpoints = get_pinpoints().await;
+    assert_eq!(pinpoints[0].description, "Many");
+    assert_eq!(widths(&pinpoints[0].attachments), vec![7, 20, 30]);
+
+    let put_request = PutPinpointRequest { attachment: Some(vec![7]), ..Default::default() };
+    let response = app.put_pinpoints(jwt.clone(), pinpoints[0].pinpoint_id.unwrap(), put_request).await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn post_pinpoint_rejects_bad_attachments() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let mut both = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), Some(vec![1]), username.clone());
+    both.attachments = vec![vec![2]];
+    let mut too_many = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, username.clone());
+    too_many.attachments = (0..11).map(|_| test_image(1, 1)).collect();
+    let mut not_an_image = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, username.clone());
+    not_an_image.attachments = vec![test_image(1, 1), vec![1, 1]];
+    let mut truncated = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, username.clone());
+    let image = test_image(100, 100);
+    truncated.attachments = vec![image[..image.len() / 2].to_vec()];
+    for request_body in [both, too_many, not_an_image, truncated] {
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 400);
+    }
+    // Someone else's pinpoint is refused before its attachments are looked at
+    let mut not_mine = PostPinpointRequest::new(
+        5.0, 5.0, String::from("From unit testing"), None, String::from("SomeoneElse"));
+    not_mine.attachments = vec![vec![1, 1]];
+    let response = app.post_pinpoints(jwt.clone(), not_mine).await;
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn attachments_come_in_the_requested_size() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Panorama"), Some(test_image(4096, 512)), username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let get_attachment = |attachment_size: Option<ImageSize>| {
+        let jwt = jwt.clone();
+        let username = username.clone();
+        let app = &app;
+        async move {
+            let request_body = GetPinpointRequest { attachment_size, ..Default::default() };
+            let pinpoints = app.get_pinpoints(jwt, username, request_body).await
+                .json::<Vec<GetPinpointResponse>>().await
+                .expect("Failed to get a JSON response back.");
+            assert_eq!(pinpoints[0].attachments[0], pinpoints[0].attachment);
+            pinpoints[0].attachment.clone()
+        }
+    };
+    // Lists only get the small thumbnails unless asked otherwise
+    assert_eq!(image_dimensions(&get_attachment(None).await), (64, 8));
+    assert_eq!(image_dimensions(&get_attachment(Some(ImageSize::Small)).await), (64, 8));
+    assert_eq!(image_dimensions(&get_attachment(Some(ImageSize::Medium)).await), (512, 64));
+    let full = get_attachment(Some(ImageSize::Full)).await;
+    assert_eq!(image::guess_format(&full).unwrap(), image::ImageFormat::Jpeg);
+    assert_eq!(image_dimensions(&full), (2048, 256));
+
+    // A new attachment comes with new thumbnails
+    let pinpoint_id = app.get_pinpoints(jwt.clone(), username.clone(), GetPinpointRequest::default()).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.")[0].pinpoint_id.unwrap();
+    let put_request = PutPinpointRequest { attachment: Some(test_image(1024, 1024)), ..Default::default() };
+    let response = app.put_pinpoints(jwt.clone(), pinpoint_id, put_request).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(image_dimensions(&get_attachment(None).await), (64, 64));
+    assert_eq!(image_dimensions(&get_attachment(Some(ImageSize::Full)).await), (1024, 1024));
+}
+
+#[tokio::test]
+async fn photo_exif_is_only_kept_for_the_owner() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let other = String::from("OtherGeneratedUser");
+    let other_jwt = app.sign_up_test_user(other.as_str(),
+                                          "othertestingemail@something.com", None).await;
+    let photo = test_photo(40, 30, 51.500712, -0.124613);
+    assert!(has_gps_exif(&photo));
+    let mut kept = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Kept"), Some(photo.clone()), username.clone());
+    kept.keep_exif = true;
+    let stripped = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Stripped"), Some(photo), username.clone());
+    for request_body in [kept, stripped] {
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+
+    let get_attachments = |jwt: String, viewer: String, attachment_size: ImageSize| {
+        let app = &app;
+        async move {
+            let request_body = GetPinpointRequest {
+                sort: Some(PinpointSort::Oldest),
+                attachment_size: Some(attachment_size),
+                ..Default::default()
+            };
+            app.get_pinpoints(jwt, viewer, request_body).await
+                .json::<Vec<GetPinpointResponse>>().await
+                .expect("Failed to get a JSON response back.")
+                .into_iter()
+                .map(|x| (x.description, has_gps_exif(&x.attachment)))
+                .collect::<Vec<(String, bool)>>()
+        }
+    };
+    assert_eq!(get_attachments(jwt.clone(), username.clone(), ImageSize::Full).await,
+               vec![(String::from("Kept"), true), (String::from("Stripped"), false)]);
+    // Thumbnails and other users never get the EXIF
+    assert_eq!(get_attachments(jwt.clone(), username.clone(), ImageSize::Medium).await,
+               vec![(String::from("Kept"), false), (String::from("Stripped"), false)]);
+    assert_eq!(get_attachments(other_jwt, other, ImageSize::Full).await,
+               vec![(String::from("Kept"), false), (String::from("Stripped"), false)]);
+}
+
+#[tokio::test]
+async fn legacy_attachments_have_their_exif_stripped() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Legacy"), Some(test_image(10, 10)), username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    // Stored as it was uploaded, the way attachments were before normalization
+    let legacy = put_blob(app.blob_storage.as_ref(), format!("attachments/{}/full", Uuid::new_v4()),
+                          test_photo(40, 30, 51.500712, -0.124613)).await
+        .expect("Failed to store the blob.");
+    sqlx::query!(
+        "UPDATE contents SET attachment_key = $1, attachment_small_key = NULL,
+        attachment_medium_key = NULL, attachment_exif_checked = FALSE
+        WHERE attachment_key IS NOT NULL",
+        legacy.key
+    )
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to make the attachment a legacy one.");
+    let get_attachment = |attachment_size: ImageSize| {
+        let app = &app;
+        let (jwt, username) = (jwt.clone(), username.clone());
+        async move {
+            let request_body = GetPinpointRequest {
+                attachment_size: Some(attachment_size),
+                ..Default::default()
+            };
+            app.get_pinpoints(jwt, username, request_body).await
+                .json::<Vec<GetPinpointResponse>>().await
+                .expect("Failed to get a JSON response back.")
+                .remove(0)
+                .attachment
+        }
+    };
+    assert!(has_gps_exif(&get_attachment(ImageSize::Full).await));
+
+    strip_legacy_exif(&app.db_pool, app.blob_storage.as_ref(), 10).await
+        .expect("Failed to strip the EXIF.");
+    let full = get_attachment(ImageSize::Full).await;
+    assert!(!has_gps_exif(&full));
+    assert_eq!(image_dimensions(&full), (40, 30));
+    assert_eq!(image_dimensions(&get_attachment(ImageSize::Small).await), (40, 30));
+    // Checked attachments are left alone
+    assert_eq!(strip_legacy_exif(&app.db_pool, app.blob_storage.as_ref(), 10).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn pinpoints_can_be_placed_where_the_photo_was_taken() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let mut request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Old photo"), None, username.clone());
+    request_body.latitude = None;
+    request_body.longitude = None;
+    request_body.attachments = vec![test_image(10, 10), test_photo(40, 30, -33.8568, 151.2153)];
+    request_body.use_photo_location = true;
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+
+    let request_body = GetPinpointRequest {
+        attachment_size: Some(ImageSize::Full),
+        ..Default::default()
+    };
+    let pinpoints = app.get_pinpoints(jwt.clone(), username.clone(), request_body).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert!((pinpoints[0].latitude - -33.8568).abs() < 1e-6);
+    assert!((pinpoints[0].longitude - 151.2153).abs() < 1e-6);
+    // The location was read before the EXIF was stripped
+    assert!(!has_gps_exif(&pinpoints[0].attachments[1]));
+
+    // Without a photo location the given one is used, and then required
+    let mut request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("No location"), Some(test_image(10, 10)), username.clone());
+    request_body.use_photo_location = true;
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let pinpoints = app.get_pinpoints(jwt.clone(), username.clone(), GetPinpointRequest::default()).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert!(pinpoints.iter().any(|x| (x.latitude, x.longitude) == (5.0, 5.0)));
+
+    let mut request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("No location"), Some(test_image(10, 10)), username.clone());
+    request_body.latitude = None;
+    request_body.longitude = None;
+    request_body.use_photo_location = true;
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 400);
+
+    let mut request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("No location"), None, username.clone());
+    request_body.longitude = None;
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn heatmap_counts_pinpoints_per_cell() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let test_cases = vec![
+        (5.1, 5.1, vec!["food"]),
+        (5.2, 5.3, vec!["food"]),
+        (5.9, 5.9, vec![]),
+        (6.5, 5.5, vec!["food"]),
+        (45.0, 45.0, vec!["food"]),
+    ];
+    for (latitude, longitude, tags) in test_cases {
+        let mut request_body = PostPinpointRequest::new(
+            latitude, longitude, String::from("From unit testing"), None, username.clone());
+        request_body.tags = tags.into_iter().map(String::from).collect();
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+    let request_body = || GetHeatmapRequest {
+        min_latitude: 0.0,
+        min_longitude: 0.0,
+        max_latitude: 10.0,
+        max_longitude: 10.0,
+        cell_size: 1.0,
+        ..Default::default()
+    };
+    let cells = |cells: Vec<GetHeatmapCell>| cells.into_iter()
+        .map(|x| (x.latitude, x.longitude, x.count))
+        .collect::<Vec<(f64, f64, i64)>>();
+
+    let response = app.get_pinpoint_heatmap(jwt.clone(), username.clone(), request_body()).await;
+    assert_eq!(response.status(), 200);
+    let heatmap = response.json::<Vec<GetHeatmapCell>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(cells(heatmap), vec![(5.5, 5.5, 3), (6.5, 5.5, 1)]);
+
+    let tagged = GetHeatmapRequest { tag: Some(String::from("#Food")), ..request_body() };
+    let heatmap = app.get_pinpoint_heatmap(jwt.clone(), username.clone(), tagged).await
+        .json::<Vec<GetHeatmapCell>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(cells(heatmap), vec![(5.5, 5.5, 2), (6.5, 5.5, 1)]);
+
+    let future = GetHeatmapRequest {
+        added_after: Some(chrono::Utc::now() + chrono::Duration::hours(1)),
+        ..request_body()
+    };
+    let heatmap = app.get_pinpoint_heatmap(jwt.clone(), username.clone(), future).await
+        .json::<Vec<GetHeatmapCell>>().await
+        .expect("Failed to get a JSON response back.");
+    assert!(heatmap.is_empty());
+}
+
+#[tokio::test]
+async fn heatmap_rejects_bad_arguments() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let request_body = || GetHeatmapRequest {
+        min_latitude: 0.0,
+        min_longitude: 0.0,
+        max_latitude: 10.0,
+        max_longitude: 10.0,
+        cell_size: 1.0,
+        ..Default::default()
+    };
+    let now = chrono::Utc::now();
+    let test_cases = vec![
+        GetHeatmapRequest { cell_size: 0.0, ..request_body() },
+        GetHeatmapRequest { cell_size: 0.0001, ..request_body() },
+        GetHeatmapRequest { min_latitude: 20.0, ..request_body() },
+        GetHeatmapRequest { added_after: Some(now), added_before: Some(now), ..request_body() },
+        GetHeatmapRequest { tag: Some(String::from("not ok!")), ..request_body() },
+    ];
+    for request_body in test_cases {
+        let response = app.get_pinpoint_heatmap(jwt.clone(), username.clone(), request_body).await;
+        assert_eq!(response.status(), 400);
+    }
+}
+
+#[tokio::test]
+async fn stream_sends_pinpoints_created_in_viewport() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let viewport = GetPinpointStreamRequest {
+        min_latitude: 0.0,
+        min_longitude: 0.0,
+        max_latitude: 10.0,
+        max_longitude: 10.0,
+    };
+    let mut response = app.get_pinpoint_stream(jwt.clone(), username.clone(), viewport).await;
+    assert_eq!(response.status(), 200);
+
+    let outside = PostPinpointRequest::new(
+        45.0, 45.0, String::from("Outside"), None, username.clone());
+    let response_post = app.post_pinpoints(jwt.clone(), outside).await;
+    assert_eq!(response_post.status(), 200);
+    let inside = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Inside"), None, username.clone());
+    let response_post = app.post_pinpoints(jwt.clone(), inside).await;
+    assert_eq!(response_post.status(), 200);
+
+    // Keep-alive comments may arrive before the event
+    let mut received = String::new();
+    let deadline = std::time::Duration::from_secs(10);
+    let event = tokio::time::timeout(deadline, async {
+        loop {
+            let chunk = response.chunk().await
+                .expect("Failed to read the stream.")
+                .expect("The stream ended early.");
+            received.push_str(std::str::from_utf8(&chunk).unwrap());
+            if let Some(start) = received.find("event: created\ndata: ") {
+                let data = &received[start + "event: created\ndata: ".len()..];
+                if let Some(end) = data.find('\n') {
+                    return data[..end].to_string();
+                }
+            }
+        }
+    }).await.expect("No event was streamed.");
+    let message = serde_json::from_str::<PinpointStreamMessage>(&event)
+        .expect("Failed to parse the streamed event.");
+    assert_eq!((message.latitude, message.longitude), (5.0, 5.0));
+    assert_eq!(message.pinpoint.unwrap().description, "Inside");
+}
+
+// Reads the stream until the next event of the kind, keep-alive comments
+// and other events are skipped. What follows it stays in received.
+async fn next_stream_event(
+    response: &mut reqwest::Response,
+    received: &mut String,
+    kind: &str,
+) -> PinpointStreamMessage {
+    let prefix = format!("event: {}\ndata: ", kind);
+    let deadline = std::time::Duration::from_secs(10);
+    let event = tokio::time::timeout(deadline, async {
+        loop {
+            if let Some(start) = received.find(&prefix) {
+                let data = received[start + prefix.len()..].to_string();
+                if let Some(end) = data.find('\n') {
+                    *received = data[end..].to_string();
+                    return data[..end].to_string();
+                }
+            }
+            let chunk = response.chunk().await
+                .expect("Failed to read the stream.")
+                .expect("The stream ended early.");
+            received.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }).await.expect("No event was streamed.");
+    serde_json::from_str::<PinpointStreamMessage>(&event)
+        .expect("Failed to parse the streamed event.")
+}
+
+#[tokio::test]
+async fn stream_removes_pinpoints_taken_away_by_an_edit() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let viewer = String::from("SomeoneElse");
+    let viewer_jwt = app.sign_up_test_user(viewer.as_str(),
+                                           "someoneelse@something.com", None).await;
+    let request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Streamed"), None, username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let get_request = GetPinpointRequest { username: Some(username.clone()), ..Default::default() };
+    let pinpoint_id = app.get_pinpoints(jwt.clone(), username.clone(), get_request).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.")[0]
+        .pinpoint_id
+        .unwrap();
+    let viewport = GetPinpointStreamRequest {
+        min_latitude: 0.0,
+        min_longitude: 0.0,
+        max_latitude: 10.0,
+        max_longitude: 10.0,
+    };
+    let mut response = app.get_pinpoint_stream(viewer_jwt.clone(), viewer.clone(), viewport).await;
+    assert_eq!(response.status(), 200);
+    let mut received = String::new();
+
+    let put_request = PutPinpointRequest {
+        description: Some(String::from("Still public")),
+        ..Default::default()
+    };
+    let response_put = app.put_pinpoints(jwt.clone(), pinpoint_id, put_request).await;
+    assert_eq!(response_put.status(), 200);
+    let message = next_stream_event(&mut response, &mut received, "updated").await;
+    assert_eq!(message.pinpoint_id, pinpoint_id);
+    assert!(message.pinpoint.is_some());
+
+    let put_request = PutPinpointRequest {
+        visibility: Some(PinpointVisibility::Private),
+        ..Default::default()
+    };
+    let response_put = app.put_pinpoints(jwt.clone(), pinpoint_id, put_request).await;
+    assert_eq!(response_put.status(), 200);
+    let message = next_stream_event(&mut response, &mut received, "deleted").await;
+    assert_eq!(message.pinpoint_id, pinpoint_id);
+    assert!(message.pinpoint.is_none());
+
+    // Back again, then moved out of the viewport
+    let put_request = PutPinpointRequest {
+        visibility: Some(PinpointVisibility::Public),
+        ..Default::default()
+    };
+    let response_put = app.put_pinpoints(jwt.clone(), pinpoint_id, put_request).await;
+    assert_eq!(response_put.status(), 200);
+    let message = next_stream_event(&mut response, &mut received, "updated").await;
+    assert!(message.pinpoint.is_some());
+    let put_request = PutPinpointRequest {
+        latitude: Some(45.0),
+        ..Default::default()
+    };
+    let response_put = app.put_pinpoints(jwt.clone(), pinpoint_id, put_request).await;
+    assert_eq!(response_put.status(), 200);
+    let message = next_stream_event(&mut response, &mut received, "deleted").await;
+    assert_eq!(message.pinpoint_id, pinpoint_id);
+    assert!(message.pinpoint.is_none());
+}
+
+#[tokio::test]
+async fn stream_rejects_other_users_and_bad_viewports() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let viewport = || GetPinpointStreamRequest {
+        min_latitude: 0.0,
+        min_longitude: 0.0,
+        max_latitude: 10.0,
+        max_longitude: 10.0,
+    };
+    let response = app.get_pinpoint_stream(
+        jwt.clone(), String::from("SomeoneElse"), viewport()).await;
+    assert_eq!(response.status(), 401);
+
+    let test_cases = vec![
+        GetPinpointStreamRequest { min_latitude: 20.0, ..viewport() },
+        GetPinpointStreamRequest { max_longitude: 200.0, ..viewport() },
+    ];
+    for request_body in test_cases {
+        let response = app.get_pinpoint_stream(jwt.clone(), username.clone(), request_body).await;
+        assert_eq!(response.status(), 400);
+    }
+}
+
+#[tokio::test]
+async fn import_creates_pinpoints_from_valid_waypoints() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let gpx = r#"<?xml version="1.0"?>
+    <gpx version="1.1" xmlns="http://www.topografix.com/GPX/1/1">
+        <wpt lat="5.0" lon="5.0"><name>Summit</name><desc>Windy</desc></wpt>
+        <wpt lat="95.0" lon="5.0"><name>Nowhere</name></wpt>
+        <wpt lat="5.001" lon="5.0"><name>Hut</name></wpt>
+    </gpx>"#;
+    let query = ImportPinpointsRequest { visibility: Some(PinpointVisibility::Private) };
+    let response = app.import_pinpoints(
+        jwt.clone(), username.clone(), query, String::from(gpx)).await;
+    assert_eq!(response.status(), 200);
+    let report = response.json::<ImportPinpointsResponse>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!((report.imported, report.rejected), (2, 1));
+    assert!(report.items[0].pinpoint_id.is_some());
+    assert_eq!(report.items[1].name, Some(String::from("Nowhere")));
+    assert!(report.items[1].pinpoint_id.is_none());
+    assert!(report.items[1].error.is_some());
+
+    let request_body = GetPinpointRequest {
+        latitude: Some(5.0),
+        longitude: Some(5.0),
+        ..Default::default()
+    };
+    let pinpoints = app.get_pinpoints(jwt.clone(), username.clone(), request_body).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(pinpoints.iter().map(|x| x.description.as_str()).collect::<Vec<&str>>(),
+               vec!["Summit\nWindy", "Hut"]);
+    assert!(pinpoints.iter().all(|x| x.visibility == PinpointVisibility::Private));
+}
+
+#[tokio::test]
+async fn import_rejects_other_users_and_bad_files() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let kml = String::from(r#"<kml><Placemark><Point>
+        <coordinates>5.0,5.0</coordinates></Point></Placemark></kml>"#);
+    let response = app.import_pinpoints(jwt.clone(), String::from("SomeoneElse"),
+                                        ImportPinpointsRequest::default(), kml.clone()).await;
+    assert_eq!(response.status(), 401);
+
+    let test_cases = vec![
+        String::from("not a waypoint file"),
+        String::from("<html><body/></html>"),
+        String::from("<gpx version=\"1.1\"></gpx>"),
+    ];
+    for file in test_cases {
+        let response = app.import_pinpoints(jwt.clone(), username.clone(),
+                                            ImportPinpointsRequest::default(), file).await;
+        assert_eq!(response.status(), 400);
+    }
+    let response = app.import_pinpoints(jwt.clone(), username.clone(),
+                                        ImportPinpointsRequest::default(), kml).await;
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn batch_creates_all_pinpoints_or_none() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let pinpoints = || {
+        let mut not_mine = PostPinpointRequest::new(
+            5.0, 5.0, String::from("Not mine"), Some(vec![1, 2, 3]), String::from("SomeoneElse"));
+        not_mine.latitude = None;
+        not_mine.use_photo_location = true;
+        vec![
+            PostPinpointRequest::new(5.0, 5.0, String::from("First"), None, username.clone()),
+            PostPinpointRequest::new(95.0, 5.0, String::from("Invalid"), None, username.clone()),
+            PostPinpointRequest::new(5.001, 5.0, String::from("Third"), None, username.clone()),
+            not_mine,
+        ]
+    };
+    let get_descriptions = || async {
+        let request_body = GetPinpointRequest {
+            latitude: Some(5.0),
+            longitude: Some(5.0),
+            sort: Some(PinpointSort::Oldest),
+            ..Default::default()
+        };
+        app.get_pinpoints(jwt.clone(), username.clone(), request_body).await
+            .json::<Vec<GetPinpointResponse>>().await
+            .expect("Failed to get a JSON response back.")
+            .into_iter()
+            .map(|x| x.description)
+            .collect::<Vec<String>>()
+    };
+
+    let request_body = PostPinpointBatchRequest { mode: BatchMode::AllOrNothing, pinpoints: pinpoints() };
+    let response = app.post_pinpoint_batch(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 400);
+    let report = response.json::<PostPinpointBatchResponse>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!((report.created, report.rejected), (0, 4));
+    assert_eq!(report.items.iter().map(|x| x.index).collect::<Vec<usize>>(), vec![0, 1, 2, 3]);
+    assert!(get_descriptions().await.is_empty());
+
+    let request_body = PostPinpointBatchRequest { mode: BatchMode::BestEffort, pinpoints: pinpoints() };
+    let response = app.post_pinpoint_batch(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let report = response.json::<PostPinpointBatchResponse>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!((report.created, report.rejected), (2, 2));
+    assert!(report.items[0].pinpoint_id.is_some());
+    assert!(report.items[1].error.is_some());
+    // Turned away before its attachment is even looked at
+    assert_eq!(report.items[3].error.as_deref(),
+               Some("Pinpoints can only be posted as the signed in user."));
+    assert_eq!(get_descriptions().await, vec!["First", "Third"]);
+
+    let valid = pinpoints().into_iter().step_by(2).collect::<Vec<PostPinpointRequest>>();
+    let request_body = PostPinpointBatchRequest { mode: BatchMode::AllOrNothing, pinpoints: valid };
+    let response = app.post_pinpoint_batch(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(get_descriptions().await, vec!["First", "Third", "First", "Third"]);
+}
+
+#[tokio::test]
+async fn batch_rejects_empty_and_oversized_batches() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let too_many = (0..=MAX_BATCH_PINPOINTS)
+        .map(|_| PostPinpointRequest::new(
+            5.0, 5.0, String::from("From unit testing"), None, username.clone()))
+        .collect::<Vec<PostPinpointRequest>>();
+    for pinpoints in [Vec::new(), too_many] {
+        let request_body = PostPinpointBatchRequest { mode: BatchMode::BestEffort, pinpoints };
+        let response = app.post_pinpoint_batch(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 400);
+    }
+}
+
+#[tokio::test]
+async fn other_users_see_obscured_coordinates() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let other = String::from("OtherGeneratedUser");
+    let other_jwt = app.sign_up_test_user(other.as_str(),
+                                          "othertestingemail@something.com", None).await;
+    let request_body = PostPinpointRequest::new(
+        51.500712, -0.124613, String::from("Near home"), None, username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let request_body = || GetPinpointRequest {
+        latitude: Some(51.5),
+        longitude: Some(-0.12),
+        ..Default::default()
+    };
+
+    let owned = app.get_pinpoints(jwt.clone(), username.clone(), request_body()).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!((owned[0].latitude, owned[0].longitude), (51.500712, -0.124613));
+
+    let mut seen: Vec<(f64, f64, f64)> = Vec::new();
+    for _ in 0..2 {
+        let pinpoints = app.get_pinpoints(other_jwt.clone(), other.clone(), request_body()).await
+            .json::<Vec<GetPinpointResponse>>().await
+            .expect("Failed to get a JSON response back.");
+        assert!(pinpoints[0].pinpoint_username.is_none());
+        // Still addressable, so others can react to and comment on it
+        assert_eq!(pinpoints[0].pinpoint_id, owned[0].pinpoint_id);
+        seen.push((pinpoints[0].latitude, pinpoints[0].longitude,
+                   pinpoints[0].distance.unwrap()));
+    }
+    // The same answer every time, so repeating the query does not narrow it down
+    assert_eq!(seen[0], seen[1]);
+    let (latitude, longitude, distance) = seen[0];
+    assert_ne!((latitude, longitude), (51.500712, -0.124613));
+    assert!((latitude - 51.500712).abs() < 0.001);
+    assert!((longitude - -0.124613).abs() < 0.002);
+    assert_eq!(distance % 100.0, 0.0);
+}
+
+#[tokio::test]
+async fn obscured_coordinates_cannot_be_narrowed_down() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let other = String::from("OtherGeneratedUser");
+    let other_jwt = app.sign_up_test_user(other.as_str(),
+                                          "othertestingemail@something.com", None).await;
+    let request_body = PostPinpointRequest::new(
+        51.500712, -0.124613, String::from("Near home"), None, username.clone());
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+    let around = |latitude: f64, longitude: f64| GetPinpointRequest {
+        latitude: Some(latitude),
+        longitude: Some(longitude),
+        radius: Some(1.0),
+        ..Default::default()
+    };
+    let shown = app.get_pinpoints(other_jwt.clone(), other.clone(), GetPinpointRequest {
+        latitude: Some(51.5),
+        longitude: Some(-0.12),
+        ..Default::default()
+    }).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    let (latitude, longitude) = (shown[0].latitude, shown[0].longitude);
+
+    // A radius only finds other users' pinpoints where they are shown
+    for (jwt, user, location, found) in [
+        (jwt.clone(), username.clone(), (51.500712, -0.124613), 1),
+        (other_jwt.clone(), other.clone(), (51.500712, -0.124613), 0),
+        (other_jwt.clone(), other.clone(), (latitude, longitude), 1)] {
+        let pinpoints = app.get_pinpoints(jwt, user, around(location.0, location.1)).await
+            .json::<Vec<GetPinpointResponse>>().await
+            .expect("Failed to get a JSON response back.");
+        assert_eq!(pinpoints.len(), found);
+    }
+
+    // Heatmap cells finer than the precision only find where it is shown
+    let request_body = GetHeatmapRequest {
+        min_latitude: 51.49,
+        min_longitude: -0.13,
+        max_latitude: 51.51,
+        max_longitude: -0.11,
+        cell_size: 0.0001,
+        ..Default::default()
+    };
+    let heatmap = app.get_pinpoint_heatmap(other_jwt.clone(), other.clone(), request_body).await
+        .json::<Vec<GetHeatmapCell>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(heatmap.len(), 1);
+    assert!((heatmap[0].latitude - latitude).abs() <= 0.0001);
+    assert!((heatmap[0].longitude - longitude).abs() <= 0.0001);
+}
@@ -1,7 +1,18 @@
-use crate::helpers::{spawn_app};
+use uuid::Uuid;
+use crate::helpers::{image_dimensions, spawn_app, test_image};
 use gvserver::routes::users::get::{GetUsersRequest, UserResponse};
 use gvserver::routes::users::post::PostUserRequest;
 use gvserver::routes::users::put::put_user_request::PutUserRequest;
+use gvserver::routes::users::export::DataExportResponse;
+use gvserver::routes::pinpoints::post::PostPinpointRequest;
+use gvserver::domain::DataExportStatus;
+use gvserver::expiry_worker::{delete_queued_blobs, purge_expired_exports};
+use gvserver::export_worker::try_execute_export;
+use gvserver::domain::PrivateZoneMode;
+use gvserver::routes::users::zones::{PostPrivateZoneRequest, PrivateZoneResponse};
+use gvserver::routes::users::follows::FollowResponse;
+use gvserver::routes::pinpoints::get::{GetClusterResponse, GetClustersRequest, GetPinpointRequest,
+                                       GetPinpointResponse};
 
 #[tokio::test()]
 async fn sign_up_persists_users() {
@@ -157,16 +168,14 @@ pub async fn user_attachment_full_validation() {
                              app.get_test_input_dir_path());
     let output_path = format!("{}/icandoitfortheusersquidward.jpg",
                               app.get_test_output_dir_path());
-    let attachment = app.load_img_bytes_at(&input_path).await;
-    assert!(attachment.is_some());
-    let img_bytes = attachment.expect("Failed to load image bytes.");
+    let img_bytes = std::fs::read(&input_path).expect("Failed to load image bytes.");
     let expensive = img_bytes.clone().len();
     println!("Loaded image byte length: {:?}", expensive);
     let jwt = app.sign_up_test_user_full(username.as_str(),
                                          "someemailagain@asdf.com",
                                          Some("MyPassword10293120!"),
                                          Some(description.clone()),
-                                         Some(img_bytes)).await;
+                                         Some(img_bytes.clone())).await;
     let user_request = GetUsersRequest {
         email: None,
         username: Some(username.clone()),
@@ -180,8 +189,8 @@ pub async fn user_attachment_full_validation() {
     //assert_gt!((&json_return).len(), 0);
     let response_body = &json_return.contents_attachment.as_mut().unwrap();
 
-    let save_attempt = app.save_img_bytes_at(
-        &output_path, &response_body, 50).await;
+    assert_eq!(image_dimensions(response_body), image_dimensions(&img_bytes));
+    let save_attempt = std::fs::write(&output_path, response_body);
     assert!(save_attempt.is_ok());
 }
 
@@ -250,8 +259,8 @@ pub async fn update_user_username_attachment() {
     let passwd = "MyBadPassword";
     let description_a = "Initial description here!";
     let description_b = "Modified description here!";
-    let attachment_a = vec![45u8, 52u8, 0u8, 0u8, 1u8, 22u8, 122u8];
-    let attachment_b = vec![0u8, 0u8, 0u8, 0u8, 22u8, 1u8, 23u8];
+    let attachment_a = test_image(45, 52);
+    let attachment_b = test_image(22, 23);
     let replacement_username = "MentallyAbsurd007";
     let (jwt, user_obj) = app.sign_up_get_full_user(
         username, email, Some(passwd), Some(description_a.to_string()),
@@ -259,7 +268,7 @@ pub async fn update_user_username_attachment() {
     assert_eq!(user_obj.email, Some(email.to_string()));
     assert_eq!(user_obj.username, Some(username.to_string()));
     assert_eq!(user_obj.contents_description, Some(description_a.to_string()));
-    assert_eq!(user_obj.contents_attachment, Some(attachment_a.clone()));
+    assert_eq!(user_obj.contents_attachment.map(|x| image_dimensions(&x)), Some((45, 52)));
     let (jwt, response_object) = app.put_user_get_user(
         jwt.clone(), user_obj.unique_id.unwrap(),
         username.to_string(), Some(replacement_username.to_string()), None,
@@ -268,7 +277,7 @@ pub async fn update_user_username_attachment() {
     assert_eq!(response_object.email, Some(email.to_string()));
     assert_eq!(response_object.username, Some(replacement_username.to_string()));
     assert_eq!(response_object.contents_description, Some(description_b.to_string()));
-    assert_eq!(response_object.contents_attachment, Some(attachment_b));
+    assert_eq!(response_object.contents_attachment.map(|x| image_dimensions(&x)), Some((22, 23)));
 }
 
 #[tokio::test]
@@ -279,8 +288,8 @@ pub async fn update_user_contents() {
     let passwd = "MyBadPassword";
     let description_a = "Initial description here!";
     let description_b = "Modified description here!";
-    let attachment_a = vec![45u8, 52u8, 0u8, 0u8, 1u8, 22u8, 122u8];
-    let attachment_b = vec![0u8, 0u8, 0u8, 0u8, 22u8, 1u8, 23u8];
+    let attachment_a = test_image(45, 52);
+    let attachment_b = test_image(22, 23);
     let (jwt, user_obj) = app.sign_up_get_full_user(
         username, email, Some(passwd),
         Some(description_a.to_string()),
@@ -288,7 +297,7 @@ pub async fn update_user_contents() {
     assert_eq!(user_obj.email, Some(email.to_string()));
     assert_eq!(user_obj.username, Some(username.to_string()));
     assert_eq!(user_obj.contents_description, Some(description_a.to_string()));
-    assert_eq!(user_obj.contents_attachment, Some(attachment_a.clone()));
+    assert_eq!(user_obj.contents_attachment.map(|x| image_dimensions(&x)), Some((45, 52)));
     let (jwt, response_object) = app.put_user_get_user(
         jwt.clone(), user_obj.unique_id.unwrap(),
         username.to_string(), None, None,
@@ -296,7 +305,7 @@ pub async fn update_user_contents() {
         Some(attachment_b.clone())).await;
     assert_eq!(response_object.email, Some(email.to_string()));
     assert_eq!(response_object.username, Some(username.to_string()));
-    assert_eq!(response_object.contents_attachment, Some(attachment_b));
+    assert_eq!(response_object.contents_attachment.map(|x| image_dimensions(&x)), Some((22, 23)));
     assert_eq!(response_object.contents_description, Some(description_b.to_string()));
 }
 
@@ -324,4 +333,303 @@ pub async fn update_user_contents_description_only() {
     assert_eq!(response_object.username, Some(username.to_string()));
     assert_eq!(response_object.contents_description, Some(description_b.to_string()));
     assert_eq!(response_object.contents_attachment, None);
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn data_export_archives_profile_pinpoints_and_attachments() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let mut request_body = PostPinpointRequest::new(
+        5.0, 5.0, String::from("Exported"), None, username.clone());
+    request_body.attachments = vec![test_image(11, 11), test_image(22, 22)];
+    let response = app.post_pinpoints(jwt.clone(), request_body).await;
+    assert_eq!(response.status(), 200);
+
+    let response = app.post_export(jwt.clone(), username.clone()).await;
+    assert_eq!(response.status(), 202);
+    let export = response.json::<DataExportResponse>().await
+        .expect("Failed to get a JSON response back.");
+    assert!(export.status_url.ends_with(&format!("/users/{}/exports/{}",
+                                                 username, export.export_id)));
+    // Asking again while it is pending gives back the same export
+    let again = app.post_export(jwt.clone(), username.clone()).await
+        .json::<DataExportResponse>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(again.export_id, export.export_id);
+    if export.status == DataExportStatus::Pending {
+        let response = app.get_export_archive(jwt.clone(), username.clone(), export.export_id).await;
+        assert_eq!(response.status(), 409);
+    }
+
+    // The background worker may already be building it
+    let mut export = export;
+    for _ in 0..50 {
+        try_execute_export(&app.db_pool, app.blob_storage.as_ref(), chrono::Duration::days(7)).await.expect("Failed to run the export.");
+        export = app.get_export(jwt.clone(), username.clone(), export.export_id).await
+            .json::<DataExportResponse>().await
+            .expect("Failed to get a JSON response back.");
+        if export.status != DataExportStatus::Pending {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(export.status, DataExportStatus::Done);
+    assert!(export.download_url.is_some());
+
+    let response = app.get_export_archive(jwt.clone(), username.clone(), export.export_id).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["Content-Type"], "application/zip");
+    let bytes = response.bytes().await.expect("Failed to read the archive.");
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes.to_vec()))
+        .expect("Failed to open the archive.");
+    let profile: serde_json::Value = serde_json::from_reader(
+        archive.by_name("profile.json").expect("No profile in the archive."))
+        .expect("Failed to parse the profile.");
+    assert_eq!(profile["username"], username.as_str());
+    assert!(profile.get("phash").is_none());
+    assert!(profile.get("salt").is_none());
+    let pinpoints: serde_json::Value = serde_json::from_reader(
+        archive.by_name("pinpoints.geojson").expect("No pinpoints in the archive."))
+        .expect("Failed to parse the pinpoints.");
+    assert_eq!(pinpoints["type"], "FeatureCollection");
+    let feature = &pinpoints["features"][0];
+    assert_eq!(feature["properties"]["description"], "Exported");
+    let pinpoint_id = feature["id"].as_str().unwrap();
+    let attachment_names: Vec<String> = archive.file_names()
+        .filter(|x| x.starts_with("attachments/"))
+        .map(String::from)
+        .collect();
+    assert_eq!(attachment_names.len(), 2);
+    let mut first = archive.by_name(&format!("attachments/{}/0.jpg", pinpoint_id))
+        .expect("No attachment in the archive.");
+    let mut first_bytes = Vec::new();
+    std::io::Read::read_to_end(&mut first, &mut first_bytes).unwrap();
+    assert_eq!(image_dimensions(&first_bytes), (11, 11));
+
+    // Deleted by the expiry worker once the retention has passed
+    let expires_at = export.expires_at.expect("A finished export should expire.");
+    assert!(expires_at > export.finished_at.unwrap());
+    assert_eq!(purge_expired_exports(&app.db_pool).await.unwrap(), 0);
+    let archive_key = sqlx::query!("SELECT archive_key FROM data_exports WHERE id = $1", export.export_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to get the export.")
+        .archive_key
+        .expect("The archive should be in blob storage.");
+    assert!(app.blob_storage.get(&archive_key).await.is_ok());
+    sqlx::query!("UPDATE data_exports SET expires_at = NOW() WHERE id = $1", export.export_id)
+        .execute(&app.db_pool)
+        .await
+        .expect("Failed to expire the export.");
+    assert_eq!(purge_expired_exports(&app.db_pool).await.unwrap(), 1);
+    let response = app.get_export(jwt.clone(), username.clone(), export.export_id).await;
+    assert_eq!(response.status(), 404);
+    delete_queued_blobs(&app.db_pool, app.blob_storage.as_ref()).await
+        .expect("Failed to delete the queued blobs.");
+    assert!(app.blob_storage.get(&archive_key).await.is_err());
+}
+
+#[tokio::test]
+async fn data_exports_belong_to_their_user() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let other = String::from("OtherGeneratedUser");
+    let other_jwt = app.sign_up_test_user(other.as_str(),
+                                          "othertestingemail@something.com", None).await;
+    let response = app.post_export(jwt.clone(), other.clone()).await;
+    assert_eq!(response.status(), 401);
+
+    let export = app.post_export(jwt.clone(), username.clone()).await
+        .json::<DataExportResponse>().await
+        .expect("Failed to get a JSON response back.");
+    let response = app.get_export(other_jwt.clone(), other.clone(), export.export_id).await;
+    assert_eq!(response.status(), 404);
+    let response = app.get_export_archive(
+        other_jwt.clone(), other.clone(), export.export_id).await;
+    assert_eq!(response.status(), 404);
+    let response = app.get_export(other_jwt.clone(), username.clone(), export.export_id).await;
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn follows_are_managed_by_the_follower() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let followee = String::from("SomeoneElse");
+    let followee_jwt = app.sign_up_test_user(followee.as_str(),
+                                             "someoneelse@something.com", None).await;
+    let get_follows = || async {
+        app.get_follows(jwt.clone(), username.clone()).await
+            .json::<Vec<FollowResponse>>().await
+            .expect("Failed to get a JSON response back.")
+            .into_iter().map(|x| x.username).collect::<Vec<String>>()
+    };
+    for _ in 0..2 {
+        let response = app.put_follow(jwt.clone(), username.clone(), followee.clone()).await;
+        assert_eq!(response.status(), 200);
+    }
+    assert_eq!(get_follows().await, vec![followee.clone()]);
+
+    let response = app.put_follow(jwt.clone(), username.clone(), username.clone()).await;
+    assert_eq!(response.status(), 400);
+    let response = app.put_follow(jwt.clone(), username.clone(), String::from("Nobody")).await;
+    assert_eq!(response.status(), 404);
+    let response = app.put_follow(followee_jwt.clone(), username.clone(), followee.clone()).await;
+    assert_eq!(response.status(), 401);
+    let response = app.get_follows(followee_jwt.clone(), username.clone()).await;
+    assert_eq!(response.status(), 401);
+    let response = app.delete_follow(followee_jwt.clone(), username.clone(), followee.clone()).await;
+    assert_eq!(response.status(), 401);
+
+    let response = app.delete_follow(jwt.clone(), username.clone(), followee.clone()).await;
+    assert_eq!(response.status(), 200);
+    let response = app.delete_follow(jwt.clone(), username.clone(), followee.clone()).await;
+    assert_eq!(response.status(), 404);
+    assert!(get_follows().await.is_empty());
+}
+
+#[tokio::test]
+async fn private_zones_are_managed_by_their_owner() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let home = || PostPrivateZoneRequest {
+        name: String::from("Home"),
+        latitude: 51.5,
+        longitude: -0.12,
+        radius_meters: 300.0,
+        mode: Some(PrivateZoneMode::Hide),
+    };
+    let response = app.post_private_zone(jwt.clone(), username.clone(), home()).await;
+    assert_eq!(response.status(), 200);
+    let zone = response.json::<PrivateZoneResponse>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!((zone.name.as_str(), zone.mode), ("Home", PrivateZoneMode::Hide));
+
+    let test_cases = vec![
+        PostPrivateZoneRequest { name: String::from(" "), ..home() },
+        PostPrivateZoneRequest { latitude: 91.0, ..home() },
+        PostPrivateZoneRequest { radius_meters: 0.0, ..home() },
+        PostPrivateZoneRequest { radius_meters: 100_000.0, ..home() },
+    ];
+    for request_body in test_cases {
+        let response = app.post_private_zone(jwt.clone(), username.clone(), request_body).await;
+        assert_eq!(response.status(), 400);
+    }
+    let response = app.post_private_zone(jwt.clone(), String::from("SomeoneElse"), home()).await;
+    assert_eq!(response.status(), 401);
+
+    let zones = app.get_private_zones(jwt.clone(), username.clone()).await
+        .json::<Vec<PrivateZoneResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(zones.iter().map(|x| x.zone_id).collect::<Vec<Uuid>>(), vec![zone.zone_id]);
+
+    let response = app.delete_private_zone(jwt.clone(), username.clone(), zone.zone_id).await;
+    assert_eq!(response.status(), 200);
+    let response = app.delete_private_zone(jwt.clone(), username.clone(), zone.zone_id).await;
+    assert_eq!(response.status(), 404);
+    let zones = app.get_private_zones(jwt.clone(), username.clone()).await
+        .json::<Vec<PrivateZoneResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert!(zones.is_empty());
+}
+
+#[tokio::test]
+async fn private_zones_mask_or_hide_pinpoints_from_other_users() {
+    let app = spawn_app().await;
+    let username = String::from("TestGeneratedUser");
+    let jwt = app.sign_up_test_user(username.as_str(),
+                                    "initialtestingemail@something.com", None).await;
+    let other = String::from("OtherGeneratedUser");
+    let other_jwt = app.sign_up_test_user(other.as_str(),
+                                          "othertestingemail@something.com", None).await;
+    for (name, latitude, mode) in [("Home", 10.0, PrivateZoneMode::Hide),
+                                   ("Work", 10.1, PrivateZoneMode::Mask)] {
+        let request_body = PostPrivateZoneRequest {
+            name: String::from(name),
+            latitude,
+            longitude: 10.0,
+            radius_meters: 500.0,
+            mode: Some(mode),
+        };
+        let response = app.post_private_zone(jwt.clone(), username.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+    for (latitude, description) in [(10.001, "At home"), (10.101, "At work"), (10.3, "Outside")] {
+        let request_body = PostPinpointRequest::new(
+            latitude, 10.001, String::from(description), None, username.clone());
+        let response = app.post_pinpoints(jwt.clone(), request_body).await;
+        assert_eq!(response.status(), 200);
+    }
+    let request_body = || GetPinpointRequest {
+        latitude: Some(10.0),
+        longitude: Some(10.0),
+        ..Default::default()
+    };
+
+    let owned = app.get_pinpoints(jwt.clone(), username.clone(), request_body()).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(owned.iter().map(|x| (x.description.as_str(), x.latitude))
+                   .collect::<Vec<(&str, f64)>>(),
+               vec![("At home", 10.001), ("At work", 10.101), ("Outside", 10.3)]);
+
+    let seen = app.get_pinpoints(other_jwt.clone(), other.clone(), request_body()).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(seen.iter().map(|x| x.description.as_str()).collect::<Vec<&str>>(),
+               vec!["At work", "Outside"]);
+    // Away from the centre of the zone, but no further than its radius,
+    // give or take the location precision grid
+    let meters_from_centre = |x: &GetPinpointResponse| {
+        let north = (x.latitude - 10.1).to_radians();
+        let east = (x.longitude - 10.0).to_radians() * 10.1_f64.to_radians().cos();
+        (north * north + east * east).sqrt() * 6_371_008.8
+    };
+    let shown_at = meters_from_centre(&seen[0]);
+    assert!((250.0 - 100.0..=500.0 + 100.0).contains(&shown_at));
+
+    // The same zone added again shows its pinpoints at the same place
+    let zones = app.get_private_zones(jwt.clone(), username.clone()).await
+        .json::<Vec<PrivateZoneResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    let work = zones.iter().find(|x| x.name == "Work").unwrap();
+    let response = app.delete_private_zone(jwt.clone(), username.clone(), work.zone_id).await;
+    assert_eq!(response.status(), 200);
+    let request_body_zone = PostPrivateZoneRequest {
+        name: String::from("Work again"),
+        latitude: 10.1,
+        longitude: 10.0,
+        radius_meters: 500.0,
+        mode: Some(PrivateZoneMode::Mask),
+    };
+    let response = app.post_private_zone(jwt.clone(), username.clone(), request_body_zone).await;
+    assert_eq!(response.status(), 200);
+    let again = app.get_pinpoints(other_jwt.clone(), other.clone(), request_body()).await
+        .json::<Vec<GetPinpointResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!((again[0].latitude, again[0].longitude), (seen[0].latitude, seen[0].longitude));
+
+    let clusters_request = || GetClustersRequest {
+        min_latitude: 9.0,
+        min_longitude: 9.0,
+        max_latitude: 11.0,
+        max_longitude: 11.0,
+        zoom: 2
+    };
+    let count = |clusters: Vec<GetClusterResponse>| clusters.iter().map(|x| x.count).sum::<i64>();
+    let clusters = app.get_pinpoint_clusters(jwt.clone(), username.clone(), clusters_request()).await
+        .json::<Vec<GetClusterResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(count(clusters), 3);
+    let clusters = app.get_pinpoint_clusters(other_jwt.clone(), other.clone(), clusters_request()).await
+        .json::<Vec<GetClusterResponse>>().await
+        .expect("Failed to get a JSON response back.");
+    assert_eq!(count(clusters), 2);
+}